@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+pub const ALTERNATE_REL: &str = "alternate";
+
+// A <link rel="alternate" media="..."> variant of a page, typically its m-dot mobile version.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MediaAlternate {
+    pub href: String,
+    pub media: String,
+}
+
+// Each page's media alternates, and - when they're being checked - what fetching each one returned.
+#[derive(Default, Serialize)]
+pub struct AlternateReport {
+    pub alternates_by_page: BTreeMap<String, Vec<MediaAlternate>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub target_statuses: BTreeMap<String, String>,
+}
+
+impl AlternateReport {
+    pub fn record_alternates(&mut self, page: String, alternates: Vec<MediaAlternate>) {
+        if !alternates.is_empty() {
+            self.alternates_by_page.insert(page, alternates);
+        }
+    }
+
+    // Returns false if the alternate has already been checked (or is being checked) for another page.
+    pub fn start_target_check(&mut self, target: &str) -> bool {
+        if self.target_statuses.contains_key(target) {
+            return false;
+        }
+
+        self.target_statuses.insert(target.to_string(), String::new());
+        true
+    }
+
+    pub fn record_target_status(&mut self, target: String, status: String) {
+        self.target_statuses.insert(target, status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alternates_are_only_checked_once() {
+        let mut report = AlternateReport::default();
+
+        assert!(report.start_target_check("https://m.example.com/"));
+        assert!(!report.start_target_check("https://m.example.com/"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::decode_html;
+
+const INDEX_FILENAME: &str = "index.html";
+const HTML_EXTENSION: &str = "html";
+
+// One mirrored file as it was read, so the snapshot can be verified later and identical files spotted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    pub url: String,
+    pub sha256: String,
+    pub bytes: usize,
+}
+
+pub struct ArchivedPage {
+    pub html: String,
+    // Relative to the archive directory, e.g. example.com/blog/index.html.
+    pub file_path: String,
+    pub manifest_entry: ManifestEntry,
+}
+
+// Pages are looked up using the same layout `wget --mirror` produces: {archive_dir}/{host}/{path}.
+pub fn read_archived_page(archive_dir: &Path, link: &str) -> Option<ArchivedPage> {
+    let path = archived_path_candidates(archive_dir, link)?.into_iter().find(|path| path.is_file())?;
+    let body = fs::read(&path).ok()?;
+
+    let file_path = path.strip_prefix(archive_dir).unwrap_or(&path).display().to_string();
+    let manifest_entry = ManifestEntry { url: link.to_string(), sha256: sha256_hex(&body), bytes: body.len() };
+
+    Some(ArchivedPage { html: decode_html(&body, None), file_path, manifest_entry })
+}
+
+pub fn sha256_hex(body: &[u8]) -> String {
+    Sha256::digest(body).iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).unwrap();
+        hex
+    })
+}
+
+// A single URL could have been saved under a few names depending on the mirroring tool's options.
+fn archived_path_candidates(archive_dir: &Path, link: &str) -> Option<Vec<PathBuf>> {
+    let parsed_url = Url::parse(link).ok()?;
+    let host_dir = archive_dir.join(parsed_url.host_str()?);
+
+    let relative_path = parsed_url.path().trim_start_matches('/');
+    let page_path = match parsed_url.query() {
+        Some(query) => host_dir.join(format!("{}?{}", relative_path, query)),
+        None => host_dir.join(relative_path),
+    };
+
+    let mut candidates = vec![page_path.join(INDEX_FILENAME)];
+
+    if !relative_path.is_empty() {
+        candidates.push(page_path.clone());
+        candidates.push(page_path.with_extension(HTML_EXTENSION));
+    }
+
+    Some(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHIVE_DIR: &str = "resources/archive";
+
+    #[test]
+    fn test_archived_root_page_resolves_to_index() {
+        let page = read_archived_page(Path::new(ARCHIVE_DIR), "https://example.com/");
+
+        let page = page.unwrap();
+        assert!(page.html.contains("/goodLink"));
+        assert_eq!("example.com/index.html", page.file_path);
+    }
+
+    #[test]
+    fn test_archived_page_without_extension() {
+        let page = read_archived_page(Path::new(ARCHIVE_DIR), "https://example.com/goodLink");
+
+        assert!(page.is_some());
+    }
+
+    #[test]
+    fn test_manifest_entry_hashes_file_contents() {
+        let page = read_archived_page(Path::new(ARCHIVE_DIR), "https://example.com/robots.txt").unwrap();
+        let body = fs::read(Path::new(ARCHIVE_DIR).join("example.com/robots.txt")).unwrap();
+
+        assert_eq!(body.len(), page.manifest_entry.bytes);
+        assert_eq!(sha256_hex(&body), page.manifest_entry.sha256);
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", sha256_hex(b"abc"));
+    }
+
+    #[test]
+    fn test_archived_page_missing() {
+        let page = read_archived_page(Path::new(ARCHIVE_DIR), "https://example.com/missing");
+
+        assert!(page.is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+const STYLESHEET_REL: &str = "stylesheet";
+const MANIFEST_REL: &str = "manifest";
+// "shortcut icon" is two rels, the second of which is "icon".
+const ICON_RELS: [&str; 4] = ["icon", "apple-touch-icon", "apple-touch-icon-precomposed", "mask-icon"];
+
+// What a page loads an asset as, going by the element (or <link> rel) that refers to it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssetKind {
+    Image,
+    Script,
+    Stylesheet,
+    Icon,
+    Manifest,
+    Iframe,
+    Video,
+    Audio,
+}
+
+impl AssetKind {
+    // Only the <link> rels that load something into the page - canonicals, alternates and hints are reported elsewhere.
+    pub fn from_link_rel(rel_value: &str) -> Option<Self> {
+        rel_value.split_ascii_whitespace().map(str::to_ascii_lowercase).find_map(|rel| match rel.as_str() {
+            STYLESHEET_REL => Some(AssetKind::Stylesheet),
+            MANIFEST_REL => Some(AssetKind::Manifest),
+            _ if ICON_RELS.contains(&rel.as_str()) => Some(AssetKind::Icon),
+            _ => None,
+        })
+    }
+}
+
+// The URLs of a srcset's candidates, e.g. "hero.jpg" and "hero@2x.jpg" from "hero.jpg 1x, hero@2x.jpg 2x". A URL
+// runs to the next whitespace, and its descriptors to the next comma - a URL can contain commas, but can't end in one.
+pub fn parse_srcset(srcset: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|character: char| character.is_ascii_whitespace() || character == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+
+        let url_end = rest.find(|character: char| character.is_ascii_whitespace()).unwrap_or(rest.len());
+        let url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // A URL followed straight by a comma has no descriptors.
+        if url.ends_with(',') {
+            urls.push(url.trim_end_matches(','));
+        } else {
+            urls.push(url);
+            rest = rest.split_once(',').map_or("", |(_, after_comma)| after_comma);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srcset_candidates_are_split() {
+        assert_eq!(vec!["hero.jpg", "hero@2x.jpg"], parse_srcset("hero.jpg 1x, hero@2x.jpg 2x"));
+        assert_eq!(vec!["small.jpg", "large.jpg"], parse_srcset(" small.jpg,, large.jpg 1200w "));
+        assert_eq!(vec!["/img?w=100,h=50", "wide.jpg"], parse_srcset("/img?w=100,h=50 100w, wide.jpg"));
+        assert!(parse_srcset("").is_empty());
+    }
+
+    #[test]
+    fn test_link_rels_map_to_asset_kinds() {
+        assert_eq!(Some(AssetKind::Icon), AssetKind::from_link_rel("shortcut icon"));
+        assert_eq!(Some(AssetKind::Stylesheet), AssetKind::from_link_rel("Alternate StyleSheet"));
+        assert_eq!(None, AssetKind::from_link_rel("canonical"));
+    }
+}
@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::header::HeaderValue;
+
+use crate::crawler::WebCrawler;
+use crate::redirects::OffDomainRedirects;
+use crate::sections::SectionRule;
+
+pub const DEFAULT_USER_AGENT: &str = "'Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.83 Safari/537.36'";
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_WORKERS: usize = 16;
+const DEFAULT_MIN_THROUGHPUT: u64 = 1024;
+// The most a sitemap can be uncompressed, per the sitemaps protocol - far more than any real page.
+const DEFAULT_MAX_BODY_SIZE: u64 = 50 * 1024 * 1024;
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+// Far more than one site's hosts, even with subdomains included, but few enough to stop a runaway scope early.
+const DEFAULT_MAX_HOSTS: usize = 20;
+
+// How a crawl fetches pages and how far it goes. Limits left unset don't apply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrawlerOptions {
+    pub request_timeout: Duration,
+    // Bodies arriving slower than this many bytes a second are given up on - 0 waits out the request timeout instead.
+    pub min_throughput: u64,
+    // Bodies larger than this many bytes are abandoned and their URLs skipped - 0 reads them whatever their size.
+    pub max_body_size: u64,
+    // Redirects followed for one request before it's failed as too many.
+    pub max_redirects: usize,
+    pub off_domain_redirects: OffDomainRedirects,
+    pub user_agent: String,
+    // Link hops from a seed - pages at this depth are fetched, but their links aren't followed.
+    pub max_depth: Option<usize>,
+    pub max_pages: Option<usize>,
+    // Hosts links are followed onto, the seeds' included - past it, links to new hosts are skipped unless the crawler's
+    // host limit confirmation says otherwise. 0 for no limit.
+    pub max_hosts: usize,
+    // Parts of the site to crawl, each with its own depth and page limits - everything if there are none.
+    pub section_rules: Vec<SectionRule>,
+    // Pages being fetched and processed at once - each worker takes the next link from the frontier when it's done.
+    pub workers: usize,
+    pub concurrency: Option<usize>,
+    // The least time between two requests to the same host.
+    pub host_delay: Option<Duration>,
+    // The most extra time, picked at random, waited before each request.
+    pub jitter: Option<Duration>,
+}
+
+impl Default for CrawlerOptions {
+    fn default() -> Self {
+        CrawlerOptions {
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            min_throughput: DEFAULT_MIN_THROUGHPUT,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            off_domain_redirects: OffDomainRedirects::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_depth: None,
+            max_pages: None,
+            max_hosts: DEFAULT_MAX_HOSTS,
+            section_rules: Vec::new(),
+            workers: DEFAULT_WORKERS,
+            concurrency: None,
+            host_delay: None,
+            jitter: None,
+        }
+    }
+}
+
+// Collects the options first so they can be checked together, rather than a bad value surfacing halfway through a
+// crawl. Everything else is configured on the built WebCrawler with its own with_* methods.
+#[derive(Default)]
+pub struct CrawlerBuilder {
+    options: CrawlerOptions,
+    archive_dir: Option<PathBuf>,
+}
+
+impl CrawlerBuilder {
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.options.request_timeout = request_timeout;
+        self
+    }
+
+    pub fn with_min_throughput(mut self, min_throughput: u64) -> Self {
+        self.options.min_throughput = min_throughput;
+        self
+    }
+
+    pub fn with_max_body_size(mut self, max_body_size: u64) -> Self {
+        self.options.max_body_size = max_body_size;
+        self
+    }
+
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.options.max_redirects = max_redirects;
+        self
+    }
+
+    pub fn with_off_domain_redirects(mut self, off_domain_redirects: OffDomainRedirects) -> Self {
+        self.options.off_domain_redirects = off_domain_redirects;
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.options.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.options.max_pages = Some(max_pages);
+        self
+    }
+
+    pub fn with_max_hosts(mut self, max_hosts: usize) -> Self {
+        self.options.max_hosts = max_hosts;
+        self
+    }
+
+    pub fn with_section_rule(mut self, section_rule: SectionRule) -> Self {
+        self.options.section_rules.push(section_rule);
+        self
+    }
+
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.options.workers = workers;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.options.concurrency = Some(concurrency);
+        self
+    }
+
+    pub fn with_host_delay(mut self, host_delay: Duration) -> Self {
+        self.options.host_delay = Some(host_delay);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.options.jitter = Some(jitter);
+        self
+    }
+
+    // Re-runs the crawl against a local mirror (wget --mirror layout) without touching the network. The limits still
+    // apply, and the user agent still picks the robots.txt group - only the options for sending requests go unused.
+    pub fn with_archive(mut self, archive_dir: PathBuf) -> Self {
+        self.archive_dir = Some(archive_dir);
+        self
+    }
+
+    pub fn build(self) -> Result<WebCrawler, String> {
+        validate_options(&self.options)?;
+        Ok(match self.archive_dir {
+            Some(archive_dir) => WebCrawler::from_archive(archive_dir, self.options),
+            None => WebCrawler::from_options(self.options),
+        })
+    }
+}
+
+fn validate_options(options: &CrawlerOptions) -> Result<(), String> {
+    if options.request_timeout.is_zero() {
+        return Err(String::from("Request timeout must be greater than zero"));
+    }
+
+    if options.user_agent.trim().is_empty() || HeaderValue::from_str(&options.user_agent).is_err() {
+        return Err(format!("Invalid user agent '{}'", options.user_agent));
+    }
+
+    if options.max_pages == Some(0) {
+        return Err(String::from("Max pages must be at least 1"));
+    }
+
+    if let Some(section_rule) = options.section_rules.iter().find(|section_rule| section_rule.max_pages == Some(0)) {
+        return Err(format!("Max pages for section {} must be at least 1", section_rule.path_prefix));
+    }
+
+    if options.workers == 0 {
+        return Err(String::from("Workers must be at least 1"));
+    }
+
+    if options.concurrency == Some(0) {
+        return Err(String::from("Concurrency must be at least 1"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_are_valid() {
+        assert_eq!(Ok(()), validate_options(&CrawlerOptions::default()));
+    }
+
+    #[test]
+    fn test_invalid_options_are_rejected() {
+        let options = |builder: CrawlerBuilder| builder.options;
+
+        assert!(validate_options(&options(CrawlerBuilder::default().with_request_timeout(Duration::ZERO))).is_err());
+        assert!(validate_options(&options(CrawlerBuilder::default().with_user_agent(" "))).is_err());
+        assert!(validate_options(&options(CrawlerBuilder::default().with_user_agent("crawler\n1.0"))).is_err());
+        assert!(validate_options(&options(CrawlerBuilder::default().with_max_pages(0))).is_err());
+        assert!(validate_options(&options(CrawlerBuilder::default().with_workers(0))).is_err());
+        assert!(validate_options(&options(CrawlerBuilder::default().with_concurrency(0))).is_err());
+        assert_eq!(Ok(()), validate_options(&options(CrawlerBuilder::default().with_max_depth(0))));
+    }
+}
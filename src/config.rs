@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::dns::AddressFamily;
+use crate::embedding::HtmlEmbedding;
+use crate::frontier::FrontierStrategy;
+use crate::output::OutputCompression;
+use crate::redirects::OffDomainRedirects;
+
+const SEEDS_KEY: &str = "seeds";
+const OUTPUT_DIR_KEY: &str = "output-dir";
+const SITES_KEY: &str = "sites";
+const TOTAL_CONCURRENCY_KEY: &str = "total-concurrency";
+
+// Crawl settings kept in a file (crawler.toml by convention), so a crawl can be repeated without a long command line.
+// Keys are the command line's long flags, with lists for the ones that can be repeated, and flags given on the command
+// line win. Per-run inputs such as an archive or a previous run's ETags stay on the command line.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CrawlConfig {
+    // Where the crawl starts. Links are followed from every one of them.
+    pub seeds: Vec<String>,
+    pub output_dir: Option<PathBuf>,
+    pub user_agent: Option<String>,
+    pub max_depth: Option<usize>,
+    pub max_pages: Option<usize>,
+    pub max_hosts: Option<usize>,
+    pub concurrency: Option<usize>,
+    pub min_throughput: Option<u64>,
+    // Written the same way as --max-body-size, e.g. "10M".
+    pub max_body_size: Option<String>,
+    pub max_redirects: Option<usize>,
+    pub off_domain_redirects: Option<OffDomainRedirects>,
+    pub delay_ms: Option<u64>,
+    pub jitter_ms: Option<u64>,
+    // Written the same way as --section, e.g. "/blog:depth=2".
+    pub sections: Vec<String>,
+    // Written the same way as --include and --exclude, e.g. "glob:/tag/**".
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub block_hosts: Vec<String>,
+    pub strategy: Option<FrontierStrategy>,
+    pub random_seed: Option<u64>,
+    pub compress: Option<OutputCompression>,
+    pub chunk_records: Option<usize>,
+    // Written the same way as --chunk-size, e.g. "100M".
+    pub chunk_size: Option<String>,
+    pub summary_json: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    pub expected_urls: Option<PathBuf>,
+    pub sitemaps: Option<bool>,
+    pub skip_preflight: Option<bool>,
+    pub capture_headers: Vec<String>,
+    pub request_headers: BTreeMap<String, String>,
+    pub form_values: BTreeMap<String, String>,
+    pub dns_over_https: Option<String>,
+    pub ip_family: Option<AddressFamily>,
+    pub grep: Option<String>,
+    // Written the same way as --memory-limit, e.g. "512M".
+    pub memory_limit: Option<String>,
+    pub prioritize_pagination: Option<bool>,
+    pub deterministic: Option<bool>,
+    pub check_alternates: Option<bool>,
+    pub follow_directory_listings: Option<bool>,
+    pub embed_html: Option<HtmlEmbedding>,
+    pub debug_request_headers: Option<bool>,
+    pub sample_ranges: Option<bool>,
+    pub include_subdomains: Option<bool>,
+    pub strict_scheme: Option<bool>,
+    pub skip_protocol_relative: Option<bool>,
+    pub ignore_nofollow: Option<bool>,
+    pub strip_tracking_params: Option<bool>,
+    pub sort_query_params: Option<bool>,
+    // Several sites crawled at once, each a table of the keys above, e.g. [sites.docs]. See site_configs.
+    pub sites: BTreeMap<String, toml::Table>,
+    // Only for sites - the most requests in flight across all of them.
+    pub total_concurrency: Option<usize>,
+}
+
+pub fn load_config(config_path: &Path) -> Result<CrawlConfig, String> {
+    parse_config(&read_config(config_path)?, config_path)
+}
+
+// The file's contents as they are, e.g. to keep alongside a run's outputs.
+pub fn read_config(config_path: &Path) -> Result<String, String> {
+    read_to_string(config_path).map_err(|err| format!("Failed to read config from {}: {}", config_path.display(), err))
+}
+
+// The path is only used to say where an invalid config came from.
+pub fn parse_config(config: &str, config_path: &Path) -> Result<CrawlConfig, String> {
+    toml::from_str(config).map_err(|err| format!("Invalid config in {}: {}", config_path.display(), err))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SiteConfig {
+    pub name: String,
+    pub config: CrawlConfig,
+}
+
+// Each site's settings are the keys at the top of the file, with the ones in its own table replacing them. A site's
+// seeds are its own, and its outputs go to <output-dir>/<site name>, so seeds can't be shared and output-dir can't be
+// set per site. Empty when the file doesn't list any sites.
+pub fn site_configs(config: &str, config_path: &Path) -> Result<Vec<SiteConfig>, String> {
+    let invalid_config = |err: String| format!("Invalid config in {}: {}", config_path.display(), err);
+    let mut shared_keys: toml::Table = toml::from_str(config).map_err(|err| invalid_config(err.to_string()))?;
+    let sites = match shared_keys.remove(SITES_KEY) {
+        Some(toml::Value::Table(sites)) => sites,
+        Some(_) => return Err(invalid_config(String::from("sites should be a table of sites"))),
+        None => return Ok(Vec::new()),
+    };
+
+    shared_keys.remove(TOTAL_CONCURRENCY_KEY);
+    if shared_keys.contains_key(SEEDS_KEY) {
+        return Err(invalid_config(String::from("with sites, seeds go in each site's table")));
+    }
+
+    sites
+        .into_iter()
+        .map(|(site_name, site_keys)| {
+            if !is_valid_site_name(&site_name) {
+                let reason = "site names are used as directory names - letters, digits, '-', '_' and '.' only";
+                return Err(invalid_config(format!("site '{}': {}", site_name, reason)));
+            }
+
+            let toml::Value::Table(site_keys) = site_keys else {
+                return Err(invalid_config(format!("site '{}' should be a table", site_name)));
+            };
+            let shared_only_keys = [SITES_KEY, TOTAL_CONCURRENCY_KEY, OUTPUT_DIR_KEY];
+            if let Some(key) = shared_only_keys.iter().find(|key| site_keys.contains_key(**key)) {
+                return Err(invalid_config(format!("site '{}': {} can't be set per site", site_name, key)));
+            }
+
+            let mut site_config = shared_keys.clone();
+            site_config.extend(site_keys);
+            let config = toml::Value::Table(site_config)
+                .try_into()
+                .map_err(|err| invalid_config(format!("site '{}': {}", site_name, err)))?;
+
+            Ok(SiteConfig { name: site_name, config })
+        })
+        .collect()
+}
+
+fn is_valid_site_name(site_name: &str) -> bool {
+    !site_name.is_empty()
+        && !site_name.starts_with('.')
+        && site_name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_is_loaded_from_toml() {
+        let config = load_config(Path::new("resources/testing_crawler.toml")).unwrap();
+
+        assert_eq!(vec!["https://example.com/", "https://example.com/docs/"], config.seeds);
+        assert_eq!(Some(PathBuf::from("crawls/example")), config.output_dir);
+        assert_eq!(Some(String::from("example-bot/1.0")), config.user_agent);
+        assert_eq!(Some(3), config.max_depth);
+        assert_eq!(None, config.max_pages);
+        assert_eq!(Some(FrontierStrategy::ShallowPaths), config.strategy);
+        assert_eq!(Some(OutputCompression::Gzip), config.compress);
+        assert_eq!(vec!["/docs", "/blog:depth=2"], config.sections);
+        assert_eq!("Bearer token", config.request_headers["Authorization"]);
+        assert_eq!(Some(true), config.sitemaps);
+        assert_eq!(None, config.deterministic);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(toml::from_str::<CrawlConfig>("max-depth = 2\nmax_pages = 10").is_err());
+        assert!(toml::from_str::<CrawlConfig>("max-depth = \"two\"").is_err());
+        assert_eq!(CrawlConfig::default(), toml::from_str::<CrawlConfig>("").unwrap());
+    }
+
+    #[test]
+    fn test_sites_take_the_shared_keys_as_defaults() {
+        let config_path = Path::new("resources/testing_sites.toml");
+        let site_configs = site_configs(&read_config(config_path).unwrap(), config_path).unwrap();
+        let site_names: Vec<&str> = site_configs.iter().map(|site_config| site_config.name.as_str()).collect();
+        let site_configs: Vec<&CrawlConfig> = site_configs.iter().map(|site_config| &site_config.config).collect();
+
+        assert_eq!(vec!["blog", "docs"], site_names);
+        assert_eq!(vec!["https://blog.example.com/"], site_configs[0].seeds);
+        assert_eq!(Some(2), site_configs[0].max_depth);
+        assert_eq!(Some(5), site_configs[1].max_depth);
+        assert!(site_configs.iter().all(|site_config| site_config.concurrency == Some(4)));
+        assert!(site_configs.iter().all(|site_config| site_config.output_dir == Some(PathBuf::from("crawls"))));
+        assert!(load_config(config_path).unwrap().total_concurrency.is_some());
+    }
+
+    #[test]
+    fn test_invalid_sites_are_rejected() {
+        let config_path = Path::new("crawler.toml");
+
+        assert!(site_configs("max-depth = 2", config_path).unwrap().is_empty());
+        assert!(site_configs("[sites.docs]\nseeds = [\"https://example.com/\"]\nmax_depth = 2", config_path).is_err());
+        assert!(site_configs("[sites.docs]\noutput-dir = \"docs\"", config_path).is_err());
+        assert!(site_configs("seeds = [\"https://example.com/\"]\n[sites.docs]", config_path).is_err());
+        assert!(site_configs("[sites.\"../docs\"]", config_path).is_err());
+    }
+}
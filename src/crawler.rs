@@ -1,328 +1,4856 @@
 use async_trait::async_trait;
 
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, Cursor, Write};
-use std::sync::Mutex;
-use std::time::{Duration};
-
-use async_recursion::async_recursion;
-use lazy_static::lazy_static;
-use reqwest::{Client, header, Url};
-use scraper::{Html, Selector};
-use serde_json::{to_string_pretty, to_value, Value};
-
-lazy_static! {
-    static ref DISALLOWED_LINKS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
-    static ref VISITED_LINKS_SET: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
-    static ref LINKS_BY_PAGE: Mutex<HashMap<String, HashSet<String>>> = Mutex::new(HashMap::new());
-    static ref HTTP_CLIENT: Client = reqwest::Client::new();
-    static ref A_TAG_SELECTOR: Selector = Selector::parse(A_HTML_TAG).unwrap();
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, header, Method, Request, RequestBuilder, Response, StatusCode, Url};
+use scraper::{ElementRef, Html};
+use serde::de::IgnoredAny;
+use serde::Serialize;
+use tokio::sync::{mpsc, Semaphore, SemaphorePermit};
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
+
+use crate::alternates::{AlternateReport, MediaAlternate, ALTERNATE_REL};
+use crate::archive::{read_archived_page, sha256_hex, ArchivedPage, ManifestEntry};
+use crate::assets::{parse_srcset, AssetKind};
+use crate::builder::{CrawlerBuilder, CrawlerOptions};
+use crate::directives::RobotsDirectives;
+use crate::dns::{build_client, default_client, AddressFamily};
+use crate::duplicates::DuplicateSignals;
+use crate::embedding::{EmbeddedHtml, HtmlEmbedding};
+use crate::encoding::{charset_from_content_type, decode_html, media_type};
+use crate::errors::CrawlError;
+use crate::etags::{build_etag_report, load_previous_etags};
+use crate::expected::{ExpectedUrls, ExpectedUrlsReport};
+use crate::filters::{is_protocol_relative, UrlFilter, UrlPattern};
+use crate::forms::{Form, FormField};
+use crate::frontier::{Frontier, FrontierQueue, FrontierStrategy};
+use crate::graph::LinkGraph;
+use crate::host_guard::{HostAdmission, HostGuard, HostLimitConfirmation, HostLimitReached};
+use crate::grep::{find_matches, GrepMatch};
+use crate::inventory::{render_url_list, render_url_markdown};
+use crate::hints::{HintRel, ResourceHint, ResourceHintReport};
+use crate::languages::{LanguageSignals, PageLanguage};
+use crate::limits::connection_limit;
+use crate::link_header::{parse_link_header, HeaderLink};
+use crate::listings::{detect_directory_listing, ListingServer};
+use crate::memory::MemoryBudget;
+use crate::metadata::{Artifact, ArtifactHeader, CrawlMetadata, CrawlSettings};
+use crate::normalize::UrlNormalization;
+use crate::output::{print_json, print_text, read_artifact, OutputCompression, OutputRotation, RecordSink};
+use crate::pacing::HostPacer;
+use crate::pagination::{build_series, PaginationLinks};
+use crate::politeness::PolitenessTracker;
+use crate::preflight::PreflightFailure;
+use crate::ranges::{resource_size, SAMPLE_RANGE};
+use crate::redirects::{
+    audit_redirect, redirect_target, OffDomainRedirects, RedirectAudit, RedirectChain, RedirectHop, RedirectStop,
+};
+use crate::replay::RunManifest;
+use crate::robots::{is_allowed, parse_robots_txt, RobotsReport, RobotsRule, RobotsTxt};
+use crate::sections::{SectionAdmission, SectionTracker};
+use crate::session_params::SessionParamDetector;
+use crate::sitemaps::{
+    decompress_sitemap, parse_sitemap, sitemap_coverage, Sitemap, SitemapKind, SitemapReport, DEFAULT_SITEMAP_PATH,
+};
+use crate::soft404::{probe_link, NotFoundFingerprint, SoftNotFoundReport};
+use crate::stats::CrawlStats;
+use crate::summary::{build_crawl_report, build_summary_report, render_summary, should_use_color, CrawlReport};
+use crate::throughput::read_body;
+use crate::visited::VisitedLinks;
+
+// Everything a crawl records as it goes. Each WebCrawler owns its own, so several crawls can run in one process
+// without seeing each other's links.
+#[derive(Default)]
+struct CrawlState {
+    // The robots.txt rules that apply to us, by origin.
+    robots_rules: Mutex<HashMap<String, Vec<RobotsRule>>>,
+    visited_links: Mutex<VisitedLinks>,
+    etags_by_link: Mutex<BTreeMap<String, String>>,
+    failed_links: Mutex<BTreeMap<String, CrawlError>>,
+    crawl_stats: Mutex<CrawlStats>,
+    // Internally linked URLs that robots.txt keeps us out of, with the pages that link to them.
+    robots_blocked_links: Mutex<BTreeMap<String, BTreeSet<String>>>,
+    skipped_links: Mutex<BTreeMap<String, SkipReason>>,
+    // Pages and the page after them, from rel="next" (or the reverse of rel="prev").
+    next_page_by_page: Mutex<BTreeMap<String, String>>,
+    duplicate_signals: Mutex<DuplicateSignals>,
+    grep_matches: Mutex<BTreeMap<String, Vec<GrepMatch>>>,
+    resource_hints: Mutex<ResourceHintReport>,
+    robots_reports: Mutex<BTreeMap<String, RobotsReport>>,
+    sitemaps: Mutex<SitemapReport>,
+    politeness: Mutex<PolitenessTracker>,
+    session_params: Mutex<SessionParamDetector>,
+    url_normalization: Mutex<UrlNormalization>,
+    url_filter: Mutex<UrlFilter>,
+    host_guard: Mutex<HostGuard>,
+    // The scheme each host was last seen answering on, by host.
+    served_schemes: Mutex<HashMap<String, String>>,
+    // Only when the crawl is checked against a list of expected URLs.
+    expected_urls: Mutex<Option<ExpectedUrls>>,
+    directory_listings: Mutex<BTreeMap<String, ListingServer>>,
+    language_signals: Mutex<LanguageSignals>,
+    soft_not_found: Mutex<SoftNotFoundReport>,
+    // Every request sent to each link, in order - a retried page has one per attempt.
+    sent_requests: Mutex<BTreeMap<String, Vec<SentRequest>>>,
+    resource_sizes: Mutex<BTreeMap<String, u64>>,
+    forms_by_page: Mutex<BTreeMap<String, Vec<Form>>>,
+    assets_by_page: Mutex<BTreeMap<String, BTreeMap<String, AssetKind>>>,
+    // Pages asking not to be indexed, by meta robots tag or X-Robots-Tag header.
+    noindex_pages: Mutex<BTreeSet<String>>,
+    media_alternates: Mutex<AlternateReport>,
+    link_graph: Mutex<LinkGraph>,
+    redirect_audits: Mutex<BTreeMap<String, RedirectAudit>>,
+    // Links that redirected onto a page the crawl already had, with the page they landed on.
+    redirect_aliases: Mutex<BTreeMap<String, String>>,
+    // Every request that was redirected, by the link it was sent to - pages, robots.txt and sitemaps alike.
+    redirect_chains: Mutex<BTreeMap<String, RedirectChain>>,
+    archive_manifest: Mutex<BTreeMap<String, ManifestEntry>>,
+    crawl_metadata: Mutex<Option<CrawlMetadata>>,
 }
 
 const ROBOTS_TXT_PATH: &str = "/robots.txt";
-const USER_AGENT: &str = "'Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.83 Safari/537.36'";
 const HREF_ATTRIBUTE_NAME: &str = "href";
 const A_HTML_TAG: &str = "a";
-const REQUEST_TIMEOUT: u64 = 3;
+const BASE_HTML_TAG: &str = "base";
+const HTML_HTML_TAG: &str = "html";
+const TITLE_HTML_TAG: &str = "title";
+const LINK_HTML_TAG: &str = "link";
+const META_HTML_TAG: &str = "meta";
+const NOSCRIPT_HTML_TAG: &str = "noscript";
+const IMG_HTML_TAG: &str = "img";
+const SCRIPT_HTML_TAG: &str = "script";
+const IFRAME_HTML_TAG: &str = "iframe";
+const VIDEO_HTML_TAG: &str = "video";
+const AUDIO_HTML_TAG: &str = "audio";
+const SOURCE_HTML_TAG: &str = "source";
+const ASSET_HTML_TAGS: [&str; 7] =
+    [IMG_HTML_TAG, SCRIPT_HTML_TAG, LINK_HTML_TAG, IFRAME_HTML_TAG, VIDEO_HTML_TAG, AUDIO_HTML_TAG, SOURCE_HTML_TAG];
+const SRC_ATTRIBUTE_NAME: &str = "src";
+const SRCSET_ATTRIBUTE_NAME: &str = "srcset";
+const POSTER_ATTRIBUTE_NAME: &str = "poster";
+// Files commonly linked to directly - documents, archives, media, fonts and the page's own styles and scripts.
+const ASSET_EXTENSIONS: [&str; 33] = [
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "csv", "zip", "gz", "tar", "rar", "7z", "exe", "dmg", "jpg",
+    "jpeg", "png", "gif", "webp", "avif", "svg", "ico", "mp3", "mp4", "webm", "ogg", "wav", "mov", "woff", "woff2",
+    "css", "js",
+];
+const FORM_HTML_TAG: &str = "form";
+const INPUT_HTML_TAG: &str = "input";
+const SELECT_HTML_TAG: &str = "select";
+const TEXTAREA_HTML_TAG: &str = "textarea";
+const OPTION_HTML_TAG: &str = "option";
+const ACTION_ATTRIBUTE_NAME: &str = "action";
+const METHOD_ATTRIBUTE_NAME: &str = "method";
+const NAME_ATTRIBUTE_NAME: &str = "name";
+const TYPE_ATTRIBUTE_NAME: &str = "type";
+const VALUE_ATTRIBUTE_NAME: &str = "value";
+const SELECTED_ATTRIBUTE_NAME: &str = "selected";
+const HTTP_EQUIV_ATTRIBUTE_NAME: &str = "http-equiv";
+const CONTENT_ATTRIBUTE_NAME: &str = "content";
+const REFRESH_HTTP_EQUIV: &str = "refresh";
+const REFRESH_URL_PARAM: &str = "url";
+const DEFAULT_FORM_METHOD: &str = "get";
+const DEFAULT_INPUT_TYPE: &str = "text";
+const REL_ATTRIBUTE_NAME: &str = "rel";
+const NEXT_REL: &str = "next";
+const PREV_RELS: [&str; 2] = ["prev", "previous"];
+const CANONICAL_REL: &str = "canonical";
+const SITEMAP_REL: &str = "sitemap";
+const NOFOLLOW_REL: &str = "nofollow";
+const ROBOTS_META_NAME: &str = "robots";
+const X_ROBOTS_TAG_HEADER: &str = "x-robots-tag";
+const AS_ATTRIBUTE_NAME: &str = "as";
+const MEDIA_ATTRIBUTE_NAME: &str = "media";
+const HREFLANG_ATTRIBUTE_NAME: &str = "hreflang";
+const LANG_ATTRIBUTE_NAME: &str = "lang";
+const DIR_ATTRIBUTE_NAME: &str = "dir";
+const HTML_CONTENT_TYPE: &str = "text/html";
+const XHTML_CONTENT_TYPE: &str = "application/xhtml+xml";
+const XML_CONTENT_TYPES: [&str; 2] = ["text/xml", "application/xml"];
+const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+const MAX_RETRIES: usize = 3;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
 
 const ALL_LINKS_FILENAME: &str = "all_links.json";
-const LINKS_BY_PAGE_FILENAME: &str = "links_by_page.json";
+const URL_LIST_FILENAME: &str = "urls.txt";
+const URL_MARKDOWN_FILENAME: &str = "urls.md";
+const LINKS_BY_PAGE_FILENAME: &str = "links_by_page.ndjson";
+const ETAGS_FILENAME: &str = "etags.json";
+const ERRORS_FILENAME: &str = "errors.json";
+const MANIFEST_FILENAME: &str = "manifest.json";
+const ROBOTS_BLOCKED_FILENAME: &str = "robots_blocked.json";
+const SKIPPED_FILENAME: &str = "skipped.json";
+const PAGINATION_FILENAME: &str = "pagination.json";
+const DUPLICATES_FILENAME: &str = "duplicates.json";
+const GREP_MATCHES_FILENAME: &str = "grep_matches.json";
+const RESOURCE_HINTS_FILENAME: &str = "resource_hints.json";
+const ROBOTS_FILENAME: &str = "robots.json";
+const REDIRECT_AUDIT_FILENAME: &str = "redirect_audit.json";
+const REDIRECT_ALIASES_FILENAME: &str = "redirect_aliases.json";
+const REDIRECT_MAP_FILENAME: &str = "redirect_map.json";
+const CLICK_DEPTH_FILENAME: &str = "click_depth.json";
+const ALTERNATES_FILENAME: &str = "alternates.json";
+const FORMS_FILENAME: &str = "forms.json";
+const ASSETS_BY_PAGE_FILENAME: &str = "assets_by_page.json";
+const NOINDEX_FILENAME: &str = "noindex.json";
+const DIRECTORY_LISTINGS_FILENAME: &str = "directory_listings.json";
+const LANGUAGES_FILENAME: &str = "languages.json";
+const SOFT_404S_FILENAME: &str = "soft_404s.json";
+const REQUEST_HEADERS_FILENAME: &str = "request_headers.json";
+const RESOURCE_SIZES_FILENAME: &str = "resource_sizes.json";
+const SITEMAPS_FILENAME: &str = "sitemaps.json";
+const POLITENESS_FILENAME: &str = "politeness.json";
+const SESSION_PARAMS_FILENAME: &str = "session_params.json";
+const EXPECTED_URLS_FILENAME: &str = "expected_urls.json";
+const RUN_MANIFEST_FILENAME: &str = "run_manifest.json";
+const FRONTIER_SPILL_FILENAME: &str = "frontier.spill";
+const VISITED_SPILL_FILENAME: &str = "visited.spill";
+const DEFAULT_OUTPUT_DIR: &str = ".";
 
 #[async_trait]
 pub trait Crawler {
-    async fn scrape_site(&self, url_link: String) -> Option<()>;
+    async fn scrape_site(&self, url_link: String) -> Result<CrawlReport, String>;
+    async fn scrape_sites(&self, seed_urls: Vec<Url>) -> Result<CrawlReport, String>;
+    fn all_links(&self) -> BTreeSet<String>;
+    fn failed_links(&self) -> BTreeMap<String, CrawlError>;
     fn print_all_links(&self, print_to_file: bool);
-    fn print_links_by_page(&self, print_to_file: bool);
+    fn print_url_inventory(&self, print_to_file: bool);
+    fn print_etags(&self, print_to_file: bool);
+    fn print_errors(&self, print_to_file: bool);
+    fn print_manifest(&self, print_to_file: bool);
+    fn print_robots_blocked(&self, print_to_file: bool);
+    fn print_skipped(&self, print_to_file: bool);
+    fn print_pagination(&self, print_to_file: bool);
+    fn print_duplicates(&self, print_to_file: bool);
+    fn print_grep_matches(&self, print_to_file: bool);
+    fn print_resource_hints(&self, print_to_file: bool);
+    fn print_media_alternates(&self, print_to_file: bool);
+    fn print_forms(&self, print_to_file: bool);
+    fn print_assets_by_page(&self, print_to_file: bool);
+    fn print_noindex_pages(&self, print_to_file: bool);
+    fn print_directory_listings(&self, print_to_file: bool);
+    fn print_languages(&self, print_to_file: bool);
+    fn print_soft_404s(&self, print_to_file: bool);
+    fn print_request_headers(&self, print_to_file: bool);
+    fn print_resource_sizes(&self, print_to_file: bool);
+    fn print_robots(&self, print_to_file: bool);
+    fn print_sitemaps(&self, print_to_file: bool);
+    fn print_politeness(&self, print_to_file: bool);
+    fn print_session_params(&self, print_to_file: bool);
+    fn print_expected_urls(&self, print_to_file: bool);
+    fn print_redirect_audit(&self, print_to_file: bool);
+    fn print_redirect_aliases(&self, print_to_file: bool);
+    fn print_redirect_map(&self, print_to_file: bool);
+    fn print_click_depths(&self, print_to_file: bool);
+    fn print_run_manifest(&self, print_to_file: bool);
+    fn print_summary(&self);
+    fn write_summary_json(&self, summary_path: &str);
 }
 
-pub struct WebCrawler;
+// Called with every request just before it's sent, e.g. to sign CDN URLs or add per-path headers.
+pub type RequestHook = Arc<dyn Fn(RequestBuilder, &Url) -> RequestBuilder + Send + Sync>;
 
-impl WebCrawler {
-    pub fn new() -> Self {
-        WebCrawler
-    }
+// Where page content comes from - the live site, or a previously saved mirror of it.
+#[derive(Clone)]
+enum PageSource {
+    Network(NetworkClient),
+    Archive(Arc<PathBuf>),
 }
 
-#[async_trait]
-impl Crawler for WebCrawler {
-    async fn scrape_site(&self, url_link: String) -> Option<()> {
-        process_robots(&url_link).await;
-        scrape_page_recursively(url_link).await
-    }
+// The HTTP client along with everything applied to each request it sends.
+#[derive(Clone)]
+struct NetworkClient {
+    client: Client,
+    user_agent: String,
+    request_timeout: Duration,
+    min_throughput: u64,
+    max_body_size: u64,
+    max_redirects: usize,
+    off_domain_redirects: OffDomainRedirects,
+    request_hook: Option<RequestHook>,
+    // Records the headers each request actually went out with, once the request hook has had its say.
+    debug_request_headers: bool,
+    // Pages are first requested with SAMPLE_RANGE, so large files aren't downloaded just to check they're there.
+    sample_ranges: bool,
+    // Shared by every clone, so the concurrency limit covers all requests - pages, robots.txt and target checks alike.
+    request_permits: Option<Arc<Semaphore>>,
+    // Shared with other crawls too, e.g. the other sites of a multi-site crawl, for a limit across all of them.
+    shared_request_permits: Option<Arc<Semaphore>>,
+    // Shared the same way, so the per-host delay holds across workers.
+    host_pacer: Arc<HostPacer>,
+}
 
-    fn print_all_links(&self, print_to_file: bool) {
-        print_all_links(print_to_file);
+pub struct WebCrawler {
+    source: PageSource,
+    strategy: FrontierStrategy,
+    previous_etags: Option<BTreeMap<String, String>>,
+    compression: OutputCompression,
+    links_by_page_rotation: OutputRotation,
+    links_by_page_to_file: bool,
+    output_dir: PathBuf,
+    // Replaces the seed URL when set - e.g. a fixed list of pages to check, or a previous run's failures.
+    seed_links: Option<Vec<String>>,
+    follow_links: bool,
+    merge_previous_run: bool,
+    // Links already crawled elsewhere, e.g. a previous run's all_links.json - they're treated as visited.
+    seen_links_path: Option<PathBuf>,
+    expected_urls_path: Option<PathBuf>,
+    follow_sitemaps: bool,
+    preflight: bool,
+    captured_headers: Vec<String>,
+    dns_over_https: Option<Url>,
+    address_family: AddressFamily,
+    prioritize_pagination: bool,
+    grep_pattern: Option<Regex>,
+    memory_budget: Option<MemoryBudget>,
+    deterministic: bool,
+    cancellation_token: CancellationToken,
+    check_alternates: bool,
+    form_values: BTreeMap<String, Vec<String>>,
+    follow_directory_listings: bool,
+    html_embedding: Option<HtmlEmbedding>,
+    labels: BTreeMap<String, String>,
+    url_normalization: UrlNormalization,
+    url_filter: UrlFilter,
+    host_limit_confirmation: Option<HostLimitConfirmation>,
+    // Picked for every crawler, but only the random strategy uses it.
+    random_seed: u64,
+    run_manifest: RunManifest,
+    // Only set for crawl_stream.
+    page_results: Option<mpsc::Sender<PageResult>>,
+    options: CrawlerOptions,
+    state: Arc<CrawlState>,
+}
+
+// Everything a page task needs while the crawl is running, shared between all of them.
+struct CrawlContext {
+    source: PageSource,
+    links_by_page_sink: RecordSink,
+    follow_links: bool,
+    captured_headers: Vec<String>,
+    prioritize_pagination: bool,
+    grep_pattern: Option<Regex>,
+    memory_budget: Option<MemoryBudget>,
+    deterministic: bool,
+    cancellation_token: CancellationToken,
+    check_alternates: bool,
+    form_values: BTreeMap<String, Vec<String>>,
+    follow_directory_listings: bool,
+    follow_sitemaps: bool,
+    html_embedding: Option<HtmlEmbedding>,
+    page_results: Option<mpsc::Sender<PageResult>>,
+    options: CrawlerOptions,
+    state: Arc<CrawlState>,
+}
+
+impl CrawlContext {
+    // Waits while the stream's buffer is full. A stream that's been dropped has nobody left to crawl for, so the crawl
+    // is cancelled.
+    async fn send_page_result(&self, page_result: PageResult) {
+        let Some(page_results) = &self.page_results else {
+            return;
+        };
+
+        if page_results.send(page_result).await.is_err() {
+            self.cancellation_token.cancel();
+        }
     }
+}
+
+#[derive(Serialize)]
+struct SentRequest {
+    method: String,
+    headers: BTreeMap<String, String>,
+}
+
+// One line of links_by_page.ndjson, written as soon as the page has been processed.
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    page: &'a str,
+    // The seed the crawl found the page from, and the link hops from it along the route it took - not always the
+    // shortest one.
+    seed: &'a str,
+    depth: usize,
+    // Every link on the page, and how it was treated.
+    links: &'a BTreeMap<String, LinkKind>,
+    #[serde(skip_serializing_if = "RobotsDirectives::is_empty")]
+    robots_directives: RobotsDirectives,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    headers: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<EmbeddedHtml>,
+}
+
+// What crawl_stream hands over for each page, once it's been processed or has failed for good. Pages skipped before
+// they were fetched, e.g. as off-domain or disallowed by robots.txt, don't have one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum PageResult {
+    Crawled {
+        page: String,
+        seed: String,
+        depth: usize,
+        links: BTreeMap<String, LinkKind>,
+        // Only the headers asked for with with_captured_headers.
+        headers: BTreeMap<String, String>,
+    },
+    Failed {
+        page: String,
+        error: CrawlError,
+    },
+}
 
-    fn print_links_by_page(&self, print_to_file: bool) {
-        print_links_by_page(print_to_file);
+// How the crawl reached a link. Seeds are their own route, at depth 0.
+#[derive(Clone)]
+struct LinkRoute {
+    seed: Arc<str>,
+    depth: usize,
+}
+
+// With subdomains in scope, new hosts turn up partway through the crawl. Each one's robots.txt (and sitemaps) is read
+// as soon as its first link is found, and its links wait here until then, so none of its pages are fetched before its
+// rules are known. Hosts are prepared concurrently with the pages being fetched, and with each other.
+#[derive(Default)]
+struct HostPreparation {
+    enabled: bool,
+    prepared_origins: HashSet<String>,
+    held_links_by_origin: HashMap<String, Vec<HeldLink>>,
+}
+
+struct HeldLink {
+    link: Arc<str>,
+    // The page the link was found on - sitemap pages don't have one.
+    referrer: Option<Arc<str>>,
+    queue: FrontierQueue,
+}
+
+impl HostPreparation {
+    // The seeds' sites had their robots.txt read before the crawl started.
+    fn new(enabled: bool, seed_origins: impl IntoIterator<Item = String>) -> Self {
+        HostPreparation { enabled, prepared_origins: seed_origins.into_iter().collect(), ..Default::default() }
     }
 }
 
-async fn process_robots(url_link: &String) {
-    let robots_link = format!("{}{}", url_link, ROBOTS_TXT_PATH);
-    let response_result = HTTP_CLIENT.get(robots_link)
-        .header(header::USER_AGENT, USER_AGENT)
-        .send()
-        .await;
+// Links that would take the crawl past its host limit wait here while the confirmation is asked, which happens off the
+// frontier loop so pages on the hosts already admitted keep being fetched meanwhile.
+#[derive(Default)]
+struct HostConfirmation {
+    asked: bool,
+    held_links: Vec<UnconfirmedLink>,
+}
+
+struct UnconfirmedLink {
+    link: String,
+    route: LinkRoute,
+    referrer: Arc<str>,
+    queue: FrontierQueue,
+}
 
-    if let Ok(response) = response_result {
-        if let Ok(text_content) = response.text().await {
-            let cursor = Cursor::new(text_content);
-            let reader = cursor.lines();
+impl HostConfirmation {
+    // Returns the links that can be queued now. Ones on a host the crawl has been refused are skipped.
+    fn hold_unconfirmed(
+        &mut self,
+        links: impl IntoIterator<Item = String>,
+        route: &LinkRoute,
+        referrer: &Arc<str>,
+        queue: FrontierQueue,
+        in_flight: &mut JoinSet<(Arc<str>, PageOutcome)>,
+        context: &Arc<CrawlContext>,
+    ) -> Vec<String> {
+        let mut admitted_links = Vec::new();
+        for link in links {
+            let Ok(url) = Url::parse(&link) else {
+                admitted_links.push(link);
+                continue;
+            };
 
-            for line in reader {
-                if let Ok(parsed_line) = line {
-                    if parsed_line.starts_with("Disallow: ") {
-                        let path: String = parsed_line["Disallow: ".len()..].to_string();
-                        let disallowed_path = strip_to_root_path(path);
-                        disallowed_path.map(|disallowed_root|
-                            add_to_disallowed_links(disallowed_root));
+            match context.state.host_admission(&url) {
+                HostAdmission::Admitted => admitted_links.push(link),
+                HostAdmission::Refused => context.state.add_to_skipped_links(link, SkipReason::HostLimit),
+                HostAdmission::Unconfirmed(host_limit_reached) => {
+                    if !self.asked {
+                        self.asked = true;
+                        in_flight.spawn(confirm_host_limit(host_limit_reached, context.clone()));
                     }
+                    let unconfirmed_link =
+                        UnconfirmedLink { link, route: route.clone(), referrer: referrer.clone(), queue };
+                    self.held_links.push(unconfirmed_link);
                 }
             }
         }
+
+        admitted_links
     }
 }
 
-#[async_recursion]
-async fn scrape_page_recursively(link: String) -> Option<()> {
-    let html_string_content = fetch_html_content(&link).await?;
+// What a page task hands back to the frontier loop.
+enum PageOutcome {
+    Scraped(ScrapedPage),
+    // Rate limited - the page goes back into the frontier, not to be fetched until the delay has passed.
+    RetryLater(Duration),
+    Skipped,
+    Cancelled,
+    // A newly found host's robots.txt has been read, along with the pages its sitemaps list.
+    HostPrepared(Vec<String>),
+    // The crawl's host limit confirmation has been answered.
+    HostLimitConfirmed(HostLimitReached, bool),
+}
 
-    let root_domain = extract_root_domain(&link)?;
+enum FetchError {
+    // Rate limited - worth asking again once the delay has passed.
+    RetryLater(Duration),
+    Failed(CrawlError),
+}
 
-    let internal_links = generate_internal_links(html_string_content, &root_domain);
+impl From<CrawlError> for FetchError {
+    fn from(crawl_error: CrawlError) -> Self {
+        FetchError::Failed(crawl_error)
+    }
+}
 
-    add_to_links_by_page(link, internal_links.clone());
+struct ScrapedPage {
+    links: BTreeSet<String>,
+    next_page: Option<String>,
+    hint_targets: Vec<String>,
+    // Empty unless alternates are being checked.
+    alternate_targets: Vec<String>,
+    // From <link rel="sitemap"> - empty unless sitemaps are being read.
+    sitemap_links: Vec<String>,
+    // The pages those sitemaps list, once they've been read.
+    sitemap_page_links: Vec<String>,
+}
 
-    let mut thread_handles = Vec::new();
+// Why a discovered URL never had its links extracted, so gaps in coverage can be explained.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    OffDomain,
+    UnsupportedScheme,
+    RobotsDisallowed,
+    NonHtml,
+    OutsideSections,
+    Excluded,
+    ProtocolRelative,
+    TooLarge,
+    BlockedHost,
+    Nofollow,
+    // On a new host, once the crawl had reached its host limit.
+    HostLimit,
+}
 
-    for internal_link in internal_links.into_iter() {
-        let is_link_new_opt = add_to_visited_links(internal_link.clone());
+// How a link found on a page was treated, as written to the page's record.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum LinkKind {
+    InternalFollowed,
+    InternalSkipped { reason: SkipReason },
+    External,
+    // In scope and queued like any other link, but its extension says it's a file rather than a page.
+    Asset,
+    NonHttp,
+}
 
-        if let Some(is_link_new) = is_link_new_opt {
-            if is_link_new {
-                let handle = tokio::spawn(async move {
-                    scrape_page_recursively(internal_link).await;
-                });
-                thread_handles.push(handle);
-            }
+impl LinkKind {
+    // Links in scope and not skipped, which the crawl queues.
+    fn for_followed_link(link: &str) -> Self {
+        if is_asset_link(link) {
+            LinkKind::Asset
+        } else {
+            LinkKind::InternalFollowed
         }
     }
 
-    for handle in thread_handles {
-        handle.await.ok();
+    fn is_followed(&self) -> bool {
+        matches!(self, LinkKind::InternalFollowed | LinkKind::Asset)
     }
+}
 
-    Some(())
+// What validate_link made of a link: the form it's queued in, or the form it was recorded as skipped in and why.
+enum ValidatedLink {
+    Followed(String),
+    Skipped(String, SkipReason),
+    // Not a URL at all, so there's nothing to record.
+    Unresolvable,
 }
 
-async fn fetch_html_content(link: &String) -> Option<String> {
-    let response_result = HTTP_CLIENT.get(link)
-        .header(header::USER_AGENT, USER_AGENT)
-        .timeout(Duration::from_secs(REQUEST_TIMEOUT))
-        .send()
-        .await;
+impl ValidatedLink {
+    fn followed(self) -> Option<String> {
+        match self {
+            ValidatedLink::Followed(link) => Some(link),
+            ValidatedLink::Skipped(..) | ValidatedLink::Unresolvable => None,
+        }
+    }
+}
 
-    return match response_result {
-        Ok(response) => {
-            if let Some(content_type) = response.headers().get("Content-Type") {
-                let content_type_val = content_type.to_str().ok()?;
-                if content_type_val == "text/html" {
-                    return response.text().await.ok();
+struct FetchedPage {
+    html: String,
+    // Only the response headers asked for with with_captured_headers - always empty for archived pages.
+    headers: BTreeMap<String, String>,
+    content_language: Option<String>,
+    // None for archived pages, which were saved without one.
+    status: Option<u16>,
+    // From the Link response header - always empty for archived pages.
+    header_links: Vec<HeaderLink>,
+    // From the X-Robots-Tag header - the page's own meta robots tags are read when it's processed.
+    header_robots_directives: RobotsDirectives,
+}
+
+// A response that isn't HTML is skipped, but its Link header can still point at more pages, e.g. the next page of a
+// paginated JSON API.
+enum FetchedResource {
+    Page(FetchedPage),
+    HeaderLinksOnly(Vec<HeaderLink>),
+    // Redirected onto a page that's already been fetched or queued, so there's nothing new to parse.
+    RedirectAlias,
+    // Redirected off the domain, with off-domain redirects not being followed.
+    OffDomainRedirect,
+}
+
+impl NetworkClient {
+    fn new(client: Client, options: &CrawlerOptions) -> Self {
+        NetworkClient {
+            client,
+            user_agent: options.user_agent.clone(),
+            request_timeout: options.request_timeout,
+            min_throughput: options.min_throughput,
+            max_body_size: options.max_body_size,
+            max_redirects: options.max_redirects,
+            off_domain_redirects: options.off_domain_redirects,
+            request_hook: None,
+            debug_request_headers: false,
+            sample_ranges: false,
+            request_permits: options.concurrency.map(|concurrency| Arc::new(Semaphore::new(concurrency))),
+            shared_request_permits: None,
+            host_pacer: Arc::new(HostPacer::new(
+                options.host_delay.unwrap_or_default(),
+                options.jitter.unwrap_or_default(),
+            )),
+        }
+    }
+
+    // Sends the request exactly as the hook left it, once its host is due another request, noting its headers first
+    // when they're being debugged. Headers the HTTP client adds itself while sending (e.g. Host and Accept) aren't
+    // included.
+    async fn send(&self, method: Method, link: &str, state: &CrawlState) -> reqwest::Result<Response> {
+        self.execute(self.request(method, link), link, state).await
+    }
+
+    // A GET for part of the resource, e.g. "bytes=0-1023". The Range header is added after the request hook has run.
+    async fn send_range(&self, link: &str, range: &str, state: &CrawlState) -> reqwest::Result<Response> {
+        self.execute(self.request(Method::GET, link).header(header::RANGE, range), link, state).await
+    }
+
+    // Redirects are followed here, hop by hop, and the chain is recorded against the link. A chain that's stopped - too
+    // long, looping, or leaving the domain when that isn't allowed - returns the redirect it stopped at.
+    async fn execute(&self, request: RequestBuilder, link: &str, state: &CrawlState) -> reqwest::Result<Response> {
+        let mut request = request.build()?;
+        let mut hops = Vec::new();
+
+        loop {
+            let next_request = request.try_clone();
+            let response = self.execute_hop(request, link, state).await?;
+            let Some(target) = redirect_target(&response) else {
+                if !hops.is_empty() {
+                    let redirect_chain = RedirectChain { target: response.url().to_string(), hops, stopped: None };
+                    state.add_to_redirect_chains(link.to_string(), redirect_chain);
                 }
-            }
-            None
+                return Ok(response);
+            };
+
+            hops.push(RedirectHop { link: response.url().to_string(), status: response.status().as_u16() });
+            let stopped = if hops.len() > self.max_redirects {
+                Some(RedirectStop::TooManyRedirects)
+            } else if hops.iter().any(|hop| hop.link == target.as_str()) {
+                Some(RedirectStop::Loop)
+            } else if state.is_blocked_host(&target) {
+                Some(RedirectStop::BlockedHost)
+            } else if self.off_domain_redirects == OffDomainRedirects::Stop && !same_host(&target, response.url()) {
+                Some(RedirectStop::OffDomain)
+            } else {
+                None
+            };
+
+            // A request with a streamed body can't be sent again, but the crawler never sends one.
+            let Some(next_request) = next_request.filter(|_| stopped.is_none()) else {
+                let redirect_chain = RedirectChain { target: target.to_string(), hops, stopped };
+                state.add_to_redirect_chains(link.to_string(), redirect_chain);
+                return Ok(response);
+            };
+
+            request = redirected_request(next_request, response.url(), target, response.status());
         }
-        Err(err) => {
-            eprintln!("Link {} caused the following error: {:?}", link, err);
-            None
+    }
+
+    async fn execute_hop(&self, request: Request, link: &str, state: &CrawlState) -> reqwest::Result<Response> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        self.host_pacer.wait_turn(&host).await;
+        state.record_politeness_request(&host, Instant::now());
+
+        if self.debug_request_headers {
+            let headers = all_headers(request.headers());
+            state.record_sent_request(link, SentRequest { method: request.method().to_string(), headers });
         }
-    };
-}
 
-// Trailing slashes are causing unwanted mapping. Prefer a more implicit way to do this.
-fn trim_trailing_slash(mut link_to_trim: String) -> String {
-    if link_to_trim.ends_with('/') {
-        link_to_trim.truncate(link_to_trim.len() - 1)
+        let response_result = self.client.execute(request).await;
+        if let Ok(response) = &response_result {
+            state.record_politeness_status(&host, response.status().as_u16());
+        }
+
+        response_result
     }
 
-    link_to_trim
-}
+    // Long Crawl-delays are capped, so one host's robots.txt can't stall the crawl indefinitely. Returns the delay
+    // that host's requests are now paced at, if robots.txt asked for one.
+    fn apply_crawl_delay(&self, robots_link: &Url, robots_txt: &RobotsTxt) -> Option<Duration> {
+        let crawl_delay = robots_txt.crawl_delay_for(&self.user_agent)?.min(MAX_CRAWL_DELAY);
+        let host = robots_link.host_str()?;
+
+        Some(self.host_pacer.set_host_delay(host, crawl_delay))
+    }
 
-fn extract_root_domain(url_string: &String) -> Option<String> {
-    let parsed_url = Url::parse(url_string.as_str()).ok()?;
-    let base_url = format!("{}://{}", parsed_url.scheme(), parsed_url.domain()?);
-    let trimmed_url = trim_trailing_slash(base_url);
+    // Held until the response has been read, so a slow body still counts against the limit. Without a limit there's
+    // nothing to wait for. The crawl's own permit comes first, so a crawl at its own limit doesn't hold shared ones.
+    async fn request_permit(&self) -> Vec<SemaphorePermit<'_>> {
+        let mut request_permits = Vec::new();
+        for permits in [&self.request_permits, &self.shared_request_permits].into_iter().flatten() {
+            request_permits.extend(permits.acquire().await.ok());
+        }
+
+        request_permits
+    }
+
+    fn request(&self, method: Method, link: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, link)
+            .header(header::USER_AGENT, &self.user_agent)
+            .timeout(self.request_timeout);
 
-    Some(trimmed_url)
+        match (&self.request_hook, Url::parse(link)) {
+            (Some(request_hook), Ok(url)) => request_hook(request, &url),
+            _ => request,
+        }
+    }
 }
 
-fn generate_internal_links(html: String, root_domain: &String) -> HashSet<String> {
-    let parsed_html = Html::parse_document(html.as_str());
+fn same_host(url: &Url, other_url: &Url) -> bool {
+    url.host_str() == other_url.host_str()
+}
 
-    let mut internal_links = HashSet::new();
+// The next hop keeps the original request's headers, as browsers do, except credentials when it leaves the host. 303
+// always turns into a GET, and so do 301 and 302 for anything but GET and HEAD.
+fn redirected_request(mut request: Request, url: &Url, target: Url, status: StatusCode) -> Request {
+    let keeps_method = matches!(*request.method(), Method::GET | Method::HEAD);
+    if status == StatusCode::SEE_OTHER && *request.method() != Method::HEAD
+        || matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND) && !keeps_method
+    {
+        *request.method_mut() = Method::GET;
+        *request.body_mut() = None;
+    }
 
-    for element in parsed_html.select(&A_TAG_SELECTOR) {
-        if let Some(href_value) = element.value().attr(HREF_ATTRIBUTE_NAME) {
-            let processed_link_opt = validate_and_process_link(href_value, root_domain);
-            processed_link_opt.map(|processed_link| {
-                internal_links.insert(processed_link)
-            });
+    if !same_host(url, &target) {
+        for sensitive_header in [header::AUTHORIZATION, header::COOKIE, header::PROXY_AUTHORIZATION] {
+            request.headers_mut().remove(sensitive_header);
         }
     }
 
-    internal_links
+    *request.url_mut() = target;
+    request
 }
 
-fn validate_and_process_link(link: &str, root_domain: &String) -> Option<String> {
-    let validated_link = validate_link(link, root_domain);
-    return validated_link.map(|validated_link| trim_trailing_slash(validated_link));
+impl Default for WebCrawler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-fn validate_link(link: &str, root_domain: &String) -> Option<String> {
-    // Assumption: If the link doesn't start with an http/https, it's relative.
-    let url_formatted_string = if !link.starts_with("http") && link.starts_with("/") {
-        format!("{}{}", root_domain, link)
-    } else if link.starts_with("http") {
-        link.to_string()
-    } else {
-        return None;
-    };
+impl WebCrawler {
+    pub fn new() -> Self {
+        let options = CrawlerOptions::default();
+
+        WebCrawler {
+            source: PageSource::Network(NetworkClient::new(default_client(), &options)),
+            strategy: FrontierStrategy::default(),
+            previous_etags: None,
+            compression: OutputCompression::default(),
+            links_by_page_rotation: OutputRotation::default(),
+            links_by_page_to_file: true,
+            output_dir: PathBuf::from(DEFAULT_OUTPUT_DIR),
+            seed_links: None,
+            follow_links: true,
+            merge_previous_run: false,
+            seen_links_path: None,
+            expected_urls_path: None,
+            follow_sitemaps: false,
+            preflight: true,
+            captured_headers: Vec::new(),
+            dns_over_https: None,
+            address_family: AddressFamily::default(),
+            prioritize_pagination: false,
+            grep_pattern: None,
+            memory_budget: None,
+            deterministic: false,
+            cancellation_token: CancellationToken::new(),
+            check_alternates: false,
+            form_values: BTreeMap::new(),
+            follow_directory_listings: false,
+            html_embedding: None,
+            labels: BTreeMap::new(),
+            url_normalization: UrlNormalization::default(),
+            url_filter: UrlFilter::default(),
+            host_limit_confirmation: None,
+            random_seed: rand::random(),
+            run_manifest: RunManifest::default(),
+            page_results: None,
+            options,
+            state: Arc::new(CrawlState::default()),
+        }
+    }
+
+    // Crawls in the background, handing each page over as soon as it's been processed. Results wait in a buffer the
+    // size of the worker pool, and once that's full the crawl waits too, so a slow consumer slows the crawl down
+    // rather than piling results up. Dropping the stream cancels the crawl. Output files are written as usual.
+    pub fn crawl_stream(mut self, url_link: String) -> impl Stream<Item = PageResult> {
+        let (page_results, receiver) = mpsc::channel(self.options.workers.max(1));
+        self.page_results = Some(page_results);
+
+        tokio::spawn(async move {
+            if let Err(err) = self.scrape_site(url_link).await {
+                eprintln!("Failed to crawl: {}", err);
+            }
+        });
 
-    let full_url = Url::parse(&url_formatted_string).ok()?;
-    let root_url = Url::parse(root_domain).ok()?;
+        ReceiverStream::new(receiver)
+    }
 
-    if full_url.domain()? == root_url.domain()? {
-        let path_root = strip_to_root_path(full_url.path().to_string())?;
-        let is_disallowed = is_disallowed_link(path_root);
+    // Request timeout, user agent and crawl limits, checked before the crawler is created.
+    pub fn builder() -> CrawlerBuilder {
+        CrawlerBuilder::default()
+    }
 
-        if !is_disallowed {
-            return Some(full_url.to_string());
+    // Only CrawlerBuilder calls this, once it has validated the options.
+    pub(crate) fn from_options(options: CrawlerOptions) -> Self {
+        WebCrawler {
+            source: PageSource::Network(NetworkClient::new(default_client(), &options)),
+            options,
+            ..WebCrawler::new()
         }
     }
 
-    None
-}
+    pub(crate) fn from_archive(archive_dir: PathBuf, options: CrawlerOptions) -> Self {
+        WebCrawler { source: PageSource::Archive(Arc::new(archive_dir)), options, ..WebCrawler::new() }
+    }
 
-fn strip_to_root_path(link: String) -> Option<String> {
-    let mut link_parts = link.split('/').filter(|part| !part.is_empty());
+    pub fn with_frontier_strategy(mut self, strategy: FrontierStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
 
-    link_parts.next().map(|first_part| format!("/{}", first_part))
-}
+    // With the same seed, the random strategy picks from the frontier in the same order, so a run can be repeated.
+    pub fn with_random_seed(mut self, random_seed: u64) -> Self {
+        self.random_seed = random_seed;
+        self
+    }
 
-fn add_to_disallowed_links(disallowed_path: String) -> Option<bool> {
-    DISALLOWED_LINKS
-        .lock()
-        .map(|mut data| data.insert(disallowed_path))
-        .ok()
-}
+    // How the run was started, written to run_manifest.json alongside the settings it ended up with.
+    pub fn with_run_manifest(mut self, run_manifest: RunManifest) -> Self {
+        self.run_manifest = run_manifest;
+        self
+    }
 
-fn is_disallowed_link(prospective_link: String) -> bool {
-    DISALLOWED_LINKS
-        .lock()
-        .map(|data| data.contains(prospective_link.as_str()))
-        .unwrap_or(false)
-}
+    pub fn with_output_compression(mut self, compression: OutputCompression) -> Self {
+        self.compression = compression;
+        self
+    }
 
-fn add_to_visited_links(address: String) -> Option<bool> {
-    VISITED_LINKS_SET
-        .lock()
-        .map(|mut data| data.insert(address))
-        .ok()
-}
+    // Each page's record carries its HTML too, so a pipeline reading links_by_page.ndjson needn't fetch it again.
+    pub fn with_embedded_html(mut self, html_embedding: HtmlEmbedding) -> Self {
+        self.html_embedding = Some(html_embedding);
+        self
+    }
 
-fn add_to_links_by_page(page_link: String, links_in_page: HashSet<String>) {
-    LINKS_BY_PAGE
-        .lock()
-        .map(|mut link_map| link_map.insert(page_link.to_string(), links_in_page))
-        .expect("Failed to add value to set.");
-}
+    // Links by page are streamed out while crawling, so where they go has to be decided up front.
+    pub fn with_links_by_page_to_file(mut self, print_to_file: bool) -> Self {
+        self.links_by_page_to_file = print_to_file;
+        self
+    }
 
-fn print_all_links(print_to_file: bool) {
-    VISITED_LINKS_SET
-        .lock()
-        .map(|link_set| {
-            let json_value: Value = to_value(&*link_set).expect("Failed to convert to JSON");
-            let json_string = to_string_pretty(&json_value).expect("Failed to convert to string.");
+    // Splits links_by_page.ndjson into numbered chunks, e.g. links_by_page-0001.ndjson.gz, so a large crawl's records
+    // can be loaded or uploaded a piece at a time. Each chunk starts with the crawl's metadata.
+    pub fn with_links_by_page_rotation(mut self, rotation: OutputRotation) -> Self {
+        self.links_by_page_rotation = rotation;
+        self
+    }
 
-            if print_to_file {
-                let mut file = File::create(ALL_LINKS_FILENAME).expect("Failed to convert to file.");
-                file.write_all(json_string.as_bytes()).unwrap();
-            } else {
-                println!("{}", json_string);
-            }
-        }).expect("Failed to print all links.");
-}
+    // The outputs go here instead of the working directory. It's created if it doesn't exist yet.
+    pub fn with_output_dir(mut self, output_dir: &Path) -> Self {
+        if let Err(err) = std::fs::create_dir_all(output_dir) {
+            eprintln!("Failed to create output directory {}: {:?}", output_dir.display(), err);
+        }
 
-fn print_links_by_page(print_to_file: bool) {
-    LINKS_BY_PAGE
-        .lock()
-        .map(|link_map| {
-            let json_value: Value = to_value(&*link_map).expect("Failed to convert to JSON");
-            let json_string = to_string_pretty(&json_value).expect("Failed to convert to string.");
+        self.output_dir = output_dir.to_path_buf();
+        self
+    }
 
-            if print_to_file {
-                let mut file = File::create(LINKS_BY_PAGE_FILENAME).expect("Failed to convert to file.");
-                file.write_all(json_string.as_bytes()).unwrap();
-            } else {
-                println!("{}", json_string);
+    // Only the URLs that failed in a previous run are fetched, with no further link discovery. Results are
+    // merged into that run's output directory, and its errors.json is replaced with whatever still fails.
+    pub fn with_only_failed(mut self, errors_path: &Path) -> Self {
+        match read_artifact::<BTreeMap<String, IgnoredAny>>(errors_path) {
+            Some(failed_links) => self.seed_links = Some(failed_links.into_keys().collect()),
+            None => eprintln!("Failed to read previous errors from {}", errors_path.display()),
+        }
+
+        self.follow_links = false;
+        self.merge_previous_run = true;
+
+        if let Some(previous_run_dir) = errors_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            self.output_dir = previous_run_dir.to_path_buf();
+        }
+
+        self
+    }
+
+    // Fetches exactly the URLs listed in the file (one per line) and nothing they link to, while still
+    // extracting and reporting on each page as normal.
+    pub fn with_url_list(mut self, url_list_path: &Path) -> Self {
+        match read_url_list(url_list_path) {
+            Ok(links) => self.seed_links = Some(links),
+            Err(err) => eprintln!("Failed to read URL list from {}: {:?}", url_list_path.display(), err),
+        }
+
+        self.follow_links = false;
+        self
+    }
+
+    // A lightweight incremental crawl - the seeds are still fetched, but only links not in the file are followed. The
+    // file is either an all_links.json from an earlier run or a plain list of URLs, one per line.
+    pub fn with_seen_links(mut self, seen_links_path: &Path) -> Self {
+        self.seen_links_path = Some(seen_links_path.to_path_buf());
+        self
+    }
+
+    // Checks the crawl against the URLs that should exist, e.g. from a CMS export, to verify a deployment. The file is
+    // read the same way as for with_seen_links, and expected_urls.json says which were reachable, failed, never
+    // fetched or never linked to, along with the pages that weren't expected.
+    pub fn with_expected_urls(mut self, expected_urls_path: &Path) -> Self {
+        self.expected_urls_path = Some(expected_urls_path.to_path_buf());
+        self
+    }
+
+    // Seeds the crawl with the pages listed in the site's sitemaps, so pages nothing links to are still found. The
+    // sitemaps are the ones robots.txt lists, or /sitemap.xml if it lists none.
+    pub fn with_sitemaps(mut self, follow_sitemaps: bool) -> Self {
+        self.follow_sitemaps = follow_sitemaps;
+        self
+    }
+
+    // Checks each seed's site is reachable before crawling - its DNS, TLS, the seed itself and robots.txt - so a site
+    // that's down fails the crawl with the reason, rather than leaving empty output files. On by default, and never
+    // run for archives.
+    pub fn with_preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    // Response headers copied verbatim into each page's record, e.g. x-cache or cf-ray for CDN audits.
+    pub fn with_captured_headers(mut self, header_names: Vec<String>) -> Self {
+        self.captured_headers = header_names.into_iter().map(|name| name.to_ascii_lowercase()).collect();
+        self
+    }
+
+    // Hostnames are resolved through a DNS-over-HTTPS provider, for networks where local DNS is unreliable or
+    // filtered. Offline runs don't resolve anything, so this has no effect on them.
+    pub fn with_dns_over_https(mut self, provider: &str) -> Self {
+        match Url::parse(provider) {
+            Ok(provider_url) => self.dns_over_https = Some(provider_url),
+            Err(err) => eprintln!("Invalid DNS-over-HTTPS provider {}: {:?}", provider, err),
+        }
+
+        self.rebuild_network_client();
+        self
+    }
+
+    // Some sites have broken AAAA records that only show up as timeouts, so one family can be preferred or forced.
+    pub fn with_address_family(mut self, address_family: AddressFamily) -> Self {
+        self.address_family = address_family;
+        self.rebuild_network_client();
+        self
+    }
+
+    // The next page of a paginated series is fetched ahead of everything else, so long archives are crawled in order.
+    pub fn with_pagination_priority(mut self, prioritize_pagination: bool) -> Self {
+        self.prioritize_pagination = prioritize_pagination;
+        self
+    }
+
+    // Every page's HTML is searched for the pattern as it's crawled, e.g. for a deprecated tracking snippet.
+    pub fn with_grep_pattern(mut self, grep_pattern: Regex) -> Self {
+        self.grep_pattern = Some(grep_pattern);
+        self
+    }
+
+    // Near the budget the crawler fetches fewer pages at once, flushes what it has buffered, and moves queued and
+    // visited links out to disk, rather than growing until it's killed.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    // Pages are fetched one at a time in exactly the frontier's order, so two runs over an unchanged site produce the
+    // same outputs apart from the crawl ID and timestamps - useful for test snapshots and run-to-run diffs.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    // Media alternates (usually m-dot mobile pages) are always recorded, but only requested when asked for, since
+    // they're normally on another host.
+    pub fn with_alternate_checks(mut self, check_alternates: bool) -> Self {
+        self.check_alternates = check_alternates;
+        self
+    }
+
+    // GET forms with a field of the same name are expanded into links with each value, e.g. a site search with a
+    // few fixed queries, so pages only reachable through a form still get crawled.
+    pub fn with_form_values(mut self, form_values: Vec<(String, String)>) -> Self {
+        for (name, value) in form_values {
+            self.form_values.entry(name).or_default().push(value);
+        }
+
+        self
+    }
+
+    // Tags the run, e.g. env=prod or release=v1.2.3. The labels go in every output's metadata, so crawl data can be
+    // sliced by deployment downstream. A later value for the same key replaces the earlier one.
+    pub fn with_labels(mut self, labels: Vec<(String, String)>) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+
+    // Directory listings are always reported, but their links are only followed when asked for - an exposed listing
+    // is usually a mistake, and crawling it tends to pull in every file the server holds.
+    pub fn with_directory_listings_followed(mut self, follow_directory_listings: bool) -> Self {
+        self.follow_directory_listings = follow_directory_listings;
+        self
+    }
+
+    // Cancelling the token stops the crawl early: nothing new is dispatched, downloads in progress are dropped, and
+    // scrape_site returns with the outputs covering every page finished before that.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    // The hook runs after the crawler's own headers are set, so it can override them. Offline runs send no
+    // requests, so it has no effect on them.
+    pub fn with_request_hook(mut self, request_hook: RequestHook) -> Self {
+        if let PageSource::Network(network_client) = &mut self.source {
+            network_client.request_hook = Some(request_hook);
+        }
+
+        self
+    }
+
+    // Requests also wait for one of these permits, which other crawls can hold too - several crawls given the same
+    // semaphore never have more requests in flight between them than it has permits.
+    pub fn with_shared_request_budget(mut self, request_budget: Arc<Semaphore>) -> Self {
+        if let PageSource::Network(network_client) = &mut self.source {
+            network_client.shared_request_permits = Some(request_budget);
+        }
+
+        self
+    }
+
+    pub fn with_request_headers_debug(mut self, debug_request_headers: bool) -> Self {
+        if let PageSource::Network(network_client) = &mut self.source {
+            network_client.debug_request_headers = debug_request_headers;
+        }
+
+        self
+    }
+
+    // Name resolution is part of the HTTP client, so it's replaced whenever a resolution setting changes.
+    fn rebuild_network_client(&mut self) {
+        if let PageSource::Network(network_client) = &mut self.source {
+            match build_client(self.dns_over_https.clone(), self.address_family) {
+                Ok(client) => network_client.client = client,
+                Err(err) => eprintln!("Failed to create HTTP client: {:?}", err),
             }
-        }).expect("Failed to print links by page.");
-}
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Links are normalised before they're compared with the ones already seen, so the same page reached through
+    // differently written links is only fetched once.
+    pub fn with_url_normalization(mut self, url_normalization: UrlNormalization) -> Self {
+        self.url_normalization = url_normalization;
+        self
+    }
 
-    #[test]
-    fn test_valid_html_links_total() {
-        let html_string = include_str!("../resources/testing_links.html").to_string();
-        let root_domain = String::from("https://example.com");
+    // Only links matching one of these are queued, e.g. "/docs/" or "glob:/docs/**". Seeds are always fetched.
+    pub fn with_include_patterns(mut self, include_patterns: Vec<UrlPattern>) -> Self {
+        self.url_filter.includes = include_patterns;
+        self
+    }
 
-        let internal_links = generate_internal_links(html_string, &root_domain);
+    // Blocked hosts' subdomains are blocked too. Links and redirects onto them are skipped, and a seed on one stops the
+    // crawl before it starts.
+    pub fn with_blocked_hosts(mut self, blocked_hosts: Vec<String>) -> Self {
+        self.url_filter.blocked_hosts = blocked_hosts;
+        self
+    }
 
-        assert_eq!(3, internal_links.len());
+    // Without one, links to new hosts past the host limit are skipped without asking.
+    pub fn with_host_limit_confirmation(mut self, host_limit_confirmation: HostLimitConfirmation) -> Self {
+        self.host_limit_confirmation = Some(host_limit_confirmation);
+        self
     }
 
-    #[test]
-    fn test_valid_html_links_relative_link() {
-        let html_string = include_str!("../resources/testing_links.html").to_string();
-        let root_domain = String::from("https://example.com");
+    // Links to other subdomains of the site (blog.example.com when crawling www.example.com) are followed too, as long
+    // as they share its registrable domain.
+    pub fn with_subdomains(mut self, include_subdomains: bool) -> Self {
+        self.url_filter.include_subdomains = include_subdomains;
+        self
+    }
 
-        let internal_links = generate_internal_links(html_string, &root_domain);
+    // Keeps http:// and https:// apart: links are only followed on the scheme of the page they're found on, as written.
+    // Otherwise a link on either scheme is taken as the same page, on whichever scheme its host serves.
+    pub fn with_strict_scheme(mut self, strict_scheme: bool) -> Self {
+        self.url_filter.strict_scheme = strict_scheme;
+        self
+    }
 
-        assert_eq!(true, internal_links.contains("https://example.com/goodLink"));
+    // Skips links written without a scheme ("//example.com/page") rather than following them on the page's scheme.
+    // They're listed in skipped.json as protocol-relative.
+    pub fn with_protocol_relative_links_skipped(mut self, skip_protocol_relative: bool) -> Self {
+        self.url_filter.skip_protocol_relative = skip_protocol_relative;
+        self
     }
 
-    #[test]
-    fn test_valid_html_links_trimmed_link() {
-        let html_string = include_str!("../resources/testing_links.html").to_string();
-        let root_domain = String::from("https://example.com");
+    // Links marked rel="nofollow", and every link on a page whose meta robots tag or X-Robots-Tag header says nofollow,
+    // are skipped unless this is set. They're listed in skipped.json as nofollow.
+    pub fn with_nofollow_ignored(mut self, ignore_nofollow: bool) -> Self {
+        self.url_filter.ignore_nofollow = ignore_nofollow;
+        self
+    }
 
-        let internal_links = generate_internal_links(html_string, &root_domain);
+    // Links matching any of these are never queued, e.g. "/tag/" to skip tag archives.
+    pub fn with_exclude_patterns(mut self, exclude_patterns: Vec<UrlPattern>) -> Self {
+        self.url_filter.excludes = exclude_patterns;
+        self
+    }
+
+    // Meant for check-links lists of files: each costs a small range request instead of a full download. HTML pages
+    // still have to be read in full for their links, so a server that honours the range is asked for them twice.
+    pub fn with_range_sampling(mut self, sample_ranges: bool) -> Self {
+        if let PageSource::Network(network_client) = &mut self.source {
+            network_client.sample_ranges = sample_ranges;
+        }
 
-        assert_eq!(true, internal_links.contains("https://example.com/goodLinkTrimMe"));
+        self
     }
 
-    #[test]
-    fn test_valid_html_links_full_link_internal() {
-        let html_string = include_str!("../resources/testing_links.html").to_string();
-        let root_domain = String::from("https://example.com");
+    fn range_sampling(&self) -> bool {
+        matches!(&self.source, PageSource::Network(network_client) if network_client.sample_ranges)
+    }
 
-        let internal_links = generate_internal_links(html_string, &root_domain);
+    fn debug_request_headers(&self) -> bool {
+        matches!(&self.source, PageSource::Network(network_client) if network_client.debug_request_headers)
+    }
 
-        assert_eq!(true, internal_links.contains("https://example.com/goodInternalLink"));
+    fn output_path(&self, filename: &str) -> PathBuf {
+        self.output_dir.join(filename)
     }
 
-    #[test]
-    fn test_valid_html_links_full_link_external() {
-        let html_string = include_str!("../resources/testing_links.html").to_string();
-        let root_domain = String::from("https://facade.com");
+    fn settings(&self) -> CrawlSettings {
+        let archive_dir = match &self.source {
+            PageSource::Network(_) => None,
+            PageSource::Archive(archive_dir) => Some(archive_dir.display().to_string()),
+        };
+
+        CrawlSettings {
+            strategy: self.strategy,
+            random_seed: (self.strategy == FrontierStrategy::Random).then_some(self.random_seed),
+            compression: self.compression,
+            links_by_page_rotation: self.links_by_page_rotation,
+            archive_dir,
+            previous_etags: self.previous_etags.is_some(),
+            follow_links: self.follow_links,
+            merge_previous_run: self.merge_previous_run,
+            seen_links: self.seen_links_path.as_ref().map(|seen_links_path| seen_links_path.display().to_string()),
+            expected_urls: self.expected_urls_path.as_ref().map(|expected_path| expected_path.display().to_string()),
+            sitemaps: self.follow_sitemaps,
+            preflight: self.preflight,
+            captured_headers: self.captured_headers.clone(),
+            dns_over_https: self.dns_over_https.as_ref().map(Url::to_string),
+            address_family: self.address_family,
+            prioritize_pagination: self.prioritize_pagination,
+            grep_pattern: self.grep_pattern.as_ref().map(|pattern| pattern.as_str().to_string()),
+            memory_limit_bytes: self.memory_budget.map(|memory_budget| memory_budget.limit_bytes),
+            deterministic: self.deterministic,
+            check_alternates: self.check_alternates,
+            form_values: self.form_values.clone(),
+            follow_directory_listings: self.follow_directory_listings,
+            embed_html: self.html_embedding,
+            debug_request_headers: self.debug_request_headers(),
+            range_sampling: self.range_sampling(),
+            url_normalization: self.url_normalization,
+            include_subdomains: self.url_filter.include_subdomains,
+            strict_scheme: self.url_filter.strict_scheme,
+            skip_protocol_relative: self.url_filter.skip_protocol_relative,
+            ignore_nofollow: self.url_filter.ignore_nofollow,
+            include_patterns: self.url_filter.include_patterns(),
+            exclude_patterns: self.url_filter.exclude_patterns(),
+            blocked_hosts: self.url_filter.blocked_hosts.clone(),
+            user_agent: self.options.user_agent.clone(),
+            request_timeout_ms: self.options.request_timeout.as_millis() as u64,
+            min_throughput: self.options.min_throughput,
+            max_body_size: self.options.max_body_size,
+            max_redirects: self.options.max_redirects,
+            off_domain_redirects: self.options.off_domain_redirects,
+            max_depth: self.options.max_depth,
+            max_pages: self.options.max_pages,
+            max_hosts: self.options.max_hosts,
+            section_rules: self.options.section_rules.clone(),
+            workers: self.options.workers,
+            concurrency: self.options.concurrency,
+            host_delay_ms: self.options.host_delay.map(|host_delay| host_delay.as_millis() as u64),
+            jitter_ms: self.options.jitter.map(|jitter| jitter.as_millis() as u64),
+        }
+    }
+
+    // Compares this run's ETags against an earlier run's etags.json to flag unchanged pages.
+    pub fn with_previous_etags(mut self, report_path: &Path) -> Self {
+        self.previous_etags = load_previous_etags(report_path);
+
+        if self.previous_etags.is_none() {
+            eprintln!("Failed to read previous ETags from {}", report_path.display());
+        }
+
+        self
+    }
+}
+
+#[async_trait]
+impl Crawler for WebCrawler {
+    // Only fails if the crawl can't start at all - pages that fail are counted in the report instead.
+    async fn scrape_site(&self, url_link: String) -> Result<CrawlReport, String> {
+        let seed_url = Url::parse(&url_link).map_err(|err| format!("Invalid seed URL {}: {}", url_link, err))?;
+        self.scrape_sites(vec![seed_url]).await
+    }
+
+    // One crawl from several seeds, e.g. a site's sections or a handful of related sites. Pages reachable from more
+    // than one seed are only fetched once, and each page's record says which seed the crawl found it from.
+    async fn scrape_sites(&self, seed_urls: Vec<Url>) -> Result<CrawlReport, String> {
+        if seed_urls.is_empty() {
+            return Err(String::from("No seed URLs to crawl"));
+        }
+
+        if let Some(blocked_seed) = seed_urls.iter().find(|seed_url| self.url_filter.is_blocked(seed_url)) {
+            return Err(format!("The seed {} is on a blocked host", blocked_seed));
+        }
+
+        let scrape_start = Instant::now();
+        let seed_url_links: Vec<String> = seed_urls.iter().map(Url::to_string).collect();
+        self.state.start_crawl_metadata(&seed_url_links, self.settings(), self.labels.clone());
+        self.state.set_url_normalization(self.url_normalization);
+        self.state.set_url_filter(self.url_filter.clone());
+        seed_urls.iter().for_each(|seed_url| self.state.record_served_scheme(seed_url));
+
+        if self.preflight {
+            preflight_seeds(&seed_urls, &self.source, &self.state).await?;
+        }
+
+        // Each site's robots.txt is read once, however many of the seeds are on it. With subdomains included, the other
+        // hosts' are read as they're found.
+        let mut robots_sitemaps_by_origin = BTreeMap::new();
+        for seed_url in seed_urls.iter() {
+            if let Entry::Vacant(entry) = robots_sitemaps_by_origin.entry(seed_url.origin().ascii_serialization()) {
+                let robots_sitemaps =
+                    process_robots(seed_url.as_str(), &self.source, &self.options.user_agent, &self.state).await;
+                entry.insert((seed_url.as_str(), robots_sitemaps));
+            }
+        }
+
+        let seed_links = self.seed_links.clone().unwrap_or(seed_url_links);
+        // Listed pages' links aren't followed, so they can't take the crawl onto new hosts.
+        let max_hosts = if self.follow_links { self.options.max_hosts } else { 0 };
+        let seed_hosts = seed_urls
+            .iter()
+            .cloned()
+            .chain(seed_links.iter().filter_map(|seed_link| Url::parse(seed_link).ok()))
+            .filter_map(|seed_url| seed_url.host_str().map(str::to_string));
+        self.state.set_host_guard(HostGuard::new(max_hosts, self.host_limit_confirmation.clone(), seed_hosts));
+        probe_not_found_pages(&seed_links, &self.source, &self.state).await;
+
+        if self.merge_previous_run {
+            let all_links_path = self.output_path(ALL_LINKS_FILENAME);
+            self.state.merge_previous_run(&all_links_path, &self.output_path(ETAGS_FILENAME), self.compression);
+        }
+
+        if let Some(seen_links_path) = &self.seen_links_path {
+            match read_seen_links(seen_links_path) {
+                Ok(seen_links) => {
+                    self.state.add_to_visited_links(seen_links.iter().map(String::as_str));
+                }
+                Err(err) => eprintln!("Failed to read seen links from {}: {:?}", seen_links_path.display(), err),
+            }
+        }
+
+        if let Some(expected_urls_path) = &self.expected_urls_path {
+            match read_seen_links(expected_urls_path) {
+                Ok(expected_links) => self.state.start_expected_urls(ExpectedUrls::new(expected_links)),
+                Err(err) => eprintln!("Failed to read expected URLs from {}: {:?}", expected_urls_path.display(), err),
+            }
+        }
+
+        // Marked as visited here, so pages that are also linked to aren't queued a second time. They're queued like
+        // seeds, but aren't seeds of the link graph - the crawl only reached them because a sitemap listed them.
+        let host_preparation =
+            HostPreparation::new(self.url_filter.include_subdomains, robots_sitemaps_by_origin.keys().cloned());
+        let mut sitemap_page_links = Vec::new();
+        for (seed_link, robots_sitemaps) in robots_sitemaps_by_origin.into_values().filter(|_| self.follow_sitemaps) {
+            let sitemap_links = sitemaps_to_read(robots_sitemaps, seed_link);
+            let page_links = read_sitemaps(sitemap_links, seed_link, &self.source, &self.state).await;
+            let unseen_links = self.state.add_to_visited_links(page_links.iter().map(String::as_str));
+            sitemap_page_links.extend(unseen_links.iter().map(|link| link.to_string()));
+        }
+
+        let links_by_page_path = self.output_path(LINKS_BY_PAGE_FILENAME);
+        let sink_result = RecordSink::new(
+            &links_by_page_path,
+            self.links_by_page_to_file,
+            self.compression,
+            self.links_by_page_rotation,
+            self.merge_previous_run,
+        );
+
+        let links_by_page_sink = match sink_result {
+            Ok(sink) => sink,
+            Err(err) => return Err(format!("Failed to create {}: {:?}", links_by_page_path.display(), err)),
+        };
+
+        links_by_page_sink.write_header(&ArtifactHeader { metadata: self.state.current_crawl_metadata() });
+        let context = Arc::new(CrawlContext {
+            source: self.source.clone(),
+            links_by_page_sink,
+            follow_links: self.follow_links,
+            captured_headers: self.captured_headers.clone(),
+            prioritize_pagination: self.prioritize_pagination,
+            grep_pattern: self.grep_pattern.clone(),
+            memory_budget: self.memory_budget,
+            deterministic: self.deterministic,
+            cancellation_token: self.cancellation_token.clone(),
+            check_alternates: self.check_alternates,
+            form_values: self.form_values.clone(),
+            follow_directory_listings: self.follow_directory_listings,
+            follow_sitemaps: self.follow_sitemaps,
+            html_embedding: self.html_embedding,
+            page_results: self.page_results.clone(),
+            options: self.options.clone(),
+            state: self.state.clone(),
+        });
+
+        let crawl_start = Instant::now();
+        let frontier = Frontier::new(self.strategy).with_random_seed(self.random_seed);
+        crawl_frontier(seed_links, sitemap_page_links, host_preparation, context.clone(), frontier).await;
+        self.state.record_crawl_duration(crawl_start.elapsed());
+
+        if self.follow_sitemaps {
+            self.state.record_sitemap_coverage();
+        }
+
+        if self.cancellation_token.is_cancelled() {
+            eprintln!("Crawl cancelled - outputs only cover the pages finished before that.");
+        }
+        self.state.finish_crawl_metadata();
+
+        context.links_by_page_sink.finish();
+        Ok(self.state.crawl_report(scrape_start.elapsed(), self.cancellation_token.is_cancelled()))
+    }
+
+    // Every link seen so far, for library users who'd rather not read all_links.json back in.
+    fn all_links(&self) -> BTreeSet<String> {
+        let all_links = self
+            .state
+            .visited_links
+            .lock()
+            .map(|visited_links| visited_links.all())
+            .expect("Failed to read all links.");
+
+        all_links.unwrap_or_else(|err| {
+            eprintln!("Failed to read spilled links back: {:?}", err);
+            BTreeSet::new()
+        })
+    }
+
+    // The links that failed so far, with why - the same as errors.json.
+    fn failed_links(&self) -> BTreeMap<String, CrawlError> {
+        self.state
+            .failed_links
+            .lock()
+            .map(|failed_links| failed_links.clone())
+            .expect("Failed to read failed links.")
+    }
+
+    fn print_all_links(&self, print_to_file: bool) {
+        self.state.print_all_links(&self.output_path(ALL_LINKS_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_url_inventory(&self, print_to_file: bool) {
+        let url_list_path = self.output_path(URL_LIST_FILENAME);
+        let url_markdown_path = self.output_path(URL_MARKDOWN_FILENAME);
+        self.state.print_url_inventory(&url_list_path, &url_markdown_path, print_to_file, self.compression);
+    }
+
+    fn print_etags(&self, print_to_file: bool) {
+        let etags_path = self.output_path(ETAGS_FILENAME);
+        self.state.print_etags(self.previous_etags.as_ref(), &etags_path, print_to_file, self.compression);
+    }
+
+    fn print_errors(&self, print_to_file: bool) {
+        self.state.print_errors(&self.output_path(ERRORS_FILENAME), print_to_file, self.compression);
+    }
+
+    // Only offline runs read mirrored files, so there's nothing to list for a network crawl.
+    fn print_manifest(&self, print_to_file: bool) {
+        if let PageSource::Archive(_) = self.source {
+            self.state.print_manifest(&self.output_path(MANIFEST_FILENAME), print_to_file, self.compression);
+        }
+    }
+
+    fn print_robots_blocked(&self, print_to_file: bool) {
+        self.state.print_robots_blocked(&self.output_path(ROBOTS_BLOCKED_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_skipped(&self, print_to_file: bool) {
+        self.state.print_skipped(&self.output_path(SKIPPED_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_pagination(&self, print_to_file: bool) {
+        self.state.print_pagination(&self.output_path(PAGINATION_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_duplicates(&self, print_to_file: bool) {
+        self.state.print_duplicates(&self.output_path(DUPLICATES_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_grep_matches(&self, print_to_file: bool) {
+        if self.grep_pattern.is_some() {
+            self.state.print_grep_matches(&self.output_path(GREP_MATCHES_FILENAME), print_to_file, self.compression);
+        }
+    }
+
+    fn print_resource_hints(&self, print_to_file: bool) {
+        self.state.print_resource_hints(&self.output_path(RESOURCE_HINTS_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_media_alternates(&self, print_to_file: bool) {
+        self.state.print_media_alternates(&self.output_path(ALTERNATES_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_forms(&self, print_to_file: bool) {
+        self.state.print_forms(&self.output_path(FORMS_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_assets_by_page(&self, print_to_file: bool) {
+        self.state.print_assets_by_page(&self.output_path(ASSETS_BY_PAGE_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_noindex_pages(&self, print_to_file: bool) {
+        self.state.print_noindex_pages(&self.output_path(NOINDEX_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_directory_listings(&self, print_to_file: bool) {
+        let directory_listings_path = self.output_path(DIRECTORY_LISTINGS_FILENAME);
+        self.state.print_directory_listings(&directory_listings_path, print_to_file, self.compression);
+    }
+
+    fn print_languages(&self, print_to_file: bool) {
+        self.state.print_languages(&self.output_path(LANGUAGES_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_soft_404s(&self, print_to_file: bool) {
+        self.state.print_soft_404s(&self.output_path(SOFT_404S_FILENAME), print_to_file, self.compression);
+    }
+
+    // Nothing is recorded unless request headers are being debugged, so there's no file otherwise.
+    fn print_request_headers(&self, print_to_file: bool) {
+        if self.debug_request_headers() {
+            let request_headers_path = self.output_path(REQUEST_HEADERS_FILENAME);
+            self.state.print_request_headers(&request_headers_path, print_to_file, self.compression);
+        }
+    }
+
+    // Sizes are only recorded when sampling ranges, so there's no file otherwise.
+    fn print_resource_sizes(&self, print_to_file: bool) {
+        if self.range_sampling() {
+            let resource_sizes_path = self.output_path(RESOURCE_SIZES_FILENAME);
+            self.state.print_resource_sizes(&resource_sizes_path, print_to_file, self.compression);
+        }
+    }
+
+    fn print_robots(&self, print_to_file: bool) {
+        self.state.print_robots(&self.output_path(ROBOTS_FILENAME), print_to_file, self.compression);
+    }
+
+    // Sitemaps are only read when asked for, so there's no file otherwise.
+    fn print_sitemaps(&self, print_to_file: bool) {
+        if self.follow_sitemaps {
+            self.state.print_sitemaps(&self.output_path(SITEMAPS_FILENAME), print_to_file, self.compression);
+        }
+    }
+
+    fn print_politeness(&self, print_to_file: bool) {
+        self.state.print_politeness(&self.output_path(POLITENESS_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_session_params(&self, print_to_file: bool) {
+        self.state.print_session_params(&self.output_path(SESSION_PARAMS_FILENAME), print_to_file, self.compression);
+    }
+
+    // Links that were found but not fetched count as found, so they're read from the visited set.
+    fn print_expected_urls(&self, print_to_file: bool) {
+        if self.expected_urls_path.is_some() {
+            let expected_urls_path = self.output_path(EXPECTED_URLS_FILENAME);
+            self.state.print_expected_urls(&expected_urls_path, print_to_file, self.compression);
+        }
+    }
+
+    fn print_redirect_audit(&self, print_to_file: bool) {
+        self.state.print_redirect_audit(&self.output_path(REDIRECT_AUDIT_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_redirect_aliases(&self, print_to_file: bool) {
+        let output_path = self.output_path(REDIRECT_ALIASES_FILENAME);
+        self.state.print_redirect_aliases(&output_path, print_to_file, self.compression);
+    }
+
+    fn print_redirect_map(&self, print_to_file: bool) {
+        self.state.print_redirect_map(&self.output_path(REDIRECT_MAP_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_click_depths(&self, print_to_file: bool) {
+        self.state.print_click_depths(&self.output_path(CLICK_DEPTH_FILENAME), print_to_file, self.compression);
+    }
+
+    fn print_run_manifest(&self, print_to_file: bool) {
+        let run_manifest_path = self.output_path(RUN_MANIFEST_FILENAME);
+        self.state.print_artifact(&self.run_manifest, &run_manifest_path, print_to_file, self.compression);
+    }
+
+    fn print_summary(&self) {
+        self.state.print_summary();
+    }
+
+    fn write_summary_json(&self, summary_path: &str) {
+        self.state.write_summary_json(Path::new(summary_path));
+    }
+}
+
+// Returns the sitemaps robots.txt lists, for when they're being read.
+async fn process_robots(url_link: &str, source: &PageSource, user_agent: &str, state: &CrawlState) -> Vec<String> {
+    // Seed URLs are normalised with a trailing slash, so joining avoids asking for "//robots.txt".
+    let Some(robots_link) = Url::parse(url_link).and_then(|url| url.join(ROBOTS_TXT_PATH)).ok() else {
+        return Vec::new();
+    };
+
+    let Some(text_content) = fetch_robots(robots_link.as_str(), source, state).await else {
+        return Vec::new();
+    };
+
+    let robots_txt = parse_robots_txt(&text_content);
+    let sitemap_links = robots_txt.sitemaps.clone();
+
+    // Offline runs don't send any requests, so there's nothing to pace.
+    let honored_crawl_delay = match source {
+        PageSource::Network(client) => client.apply_crawl_delay(&robots_link, &robots_txt),
+        PageSource::Archive(_) => None,
+    };
+
+    if let (Some(host), Some(crawl_delay)) = (robots_link.host_str(), honored_crawl_delay) {
+        state.record_politeness_crawl_delay(host, crawl_delay);
+    }
+
+    record_robots(&robots_link, robots_txt, user_agent, honored_crawl_delay, state);
+    sitemap_links
+}
+
+// The sitemaps robots.txt lists, or the one at the conventional path if it doesn't list any.
+fn sitemaps_to_read(robots_sitemaps: Vec<String>, site_link: &str) -> Vec<String> {
+    if !robots_sitemaps.is_empty() {
+        return robots_sitemaps;
+    }
+
+    let default_sitemap = Url::parse(site_link).and_then(|url| url.join(DEFAULT_SITEMAP_PATH));
+    default_sitemap.map(String::from).into_iter().collect()
+}
+
+// Indexes are followed down to the sitemaps they list. Listed pages are scoped and checked against robots.txt the same
+// way as links found on the page that pointed at the sitemap. Each sitemap is only read once per crawl, however many
+// pages point at it.
+async fn read_sitemaps(
+    sitemap_links: Vec<String>,
+    page_url: &str,
+    source: &PageSource,
+    state: &CrawlState,
+) -> Vec<String> {
+    let mut pending_sitemaps: VecDeque<String> = sitemap_links.into();
+    let mut page_links = Vec::new();
+
+    while let Some(sitemap_link) = pending_sitemaps.pop_front() {
+        if !state.start_sitemap_read(&sitemap_link) {
+            continue;
+        }
+
+        let xml = match fetch_sitemap(&sitemap_link, source, state).await {
+            Ok(xml) => xml,
+            Err(reason) => {
+                state.record_sitemap_failure(sitemap_link, reason);
+                continue;
+            }
+        };
+
+        match parse_sitemap(&xml) {
+            Sitemap::Index(child_sitemaps) => {
+                state.record_sitemap(sitemap_link, SitemapKind::Index, child_sitemaps.len(), &[]);
+                pending_sitemaps.extend(child_sitemaps);
+            }
+            Sitemap::UrlSet(listed_links) => {
+                let links_in_scope: Vec<String> = listed_links
+                    .iter()
+                    .filter_map(|listed_link| validate_and_process_link(listed_link, page_url, state))
+                    .collect();
+                state.record_sitemap(sitemap_link, SitemapKind::UrlSet, listed_links.len(), &links_in_scope);
+                page_links.extend(links_in_scope);
+            }
+        }
+    }
+
+    page_links
+}
+
+async fn fetch_sitemap(sitemap_link: &str, source: &PageSource, state: &CrawlState) -> Result<String, String> {
+    match source {
+        PageSource::Network(client) => {
+            let _request_permit = client.request_permit().await;
+            let response = client
+                .send(Method::GET, sitemap_link, state)
+                .await
+                .and_then(Response::error_for_status)
+                .map_err(|err| err.to_string())?;
+            let body = read_body(response, client.min_throughput, client.max_body_size)
+                .await
+                .map_err(|err| err.to_string())?;
+
+            decompress_sitemap(&body).ok_or_else(|| String::from("Failed to decompress sitemap"))
+        }
+        PageSource::Archive(archive_dir) => read_archived_page(archive_dir, sitemap_link)
+            .map(|archived_page| state.add_to_manifest(archived_page))
+            .ok_or_else(|| String::from("Not in the archive")),
+    }
+}
+
+// Each seed's site is checked once, however many of the seeds are on it. A site that fails is only reported while
+// another seed's site passes, as its pages will be counted as failures like any other - the crawl is only stopped when
+// there's nothing reachable to crawl at all.
+async fn preflight_seeds(seed_urls: &[Url], source: &PageSource, state: &CrawlState) -> Result<(), String> {
+    let PageSource::Network(client) = source else {
+        return Ok(());
+    };
+
+    let mut failures = Vec::new();
+    let mut checked_origins = HashSet::new();
+    for seed_url in seed_urls {
+        if checked_origins.insert(seed_url.origin().ascii_serialization()) {
+            if let Err(failure) = preflight_seed(seed_url, client, state).await {
+                failures.push(format!("Pre-flight check failed for {}: {}", seed_url, failure));
+            }
+        }
+    }
+
+    if failures.len() == checked_origins.len() {
+        return Err(failures.join("\n"));
+    }
+
+    failures.iter().for_each(|failure| eprintln!("{}", failure));
+    Ok(())
+}
+
+// The seed is only requested here, not read - it's fetched again as the crawl's first page. robots.txt only has to
+// answer: a missing one is the same as one allowing everything.
+async fn preflight_seed(seed_url: &Url, client: &NetworkClient, state: &CrawlState) -> Result<(), PreflightFailure> {
+    let host = seed_url.host_str().unwrap_or_default();
+
+    let _request_permit = client.request_permit().await;
+    let seed_response = client
+        .send(Method::GET, seed_url.as_str(), state)
+        .await
+        .map_err(|err| PreflightFailure::from_request_error(host, &err))?;
+
+    if seed_response.status().is_server_error() {
+        return Err(PreflightFailure::SeedStatus { status: seed_response.status().as_u16() });
+    }
+    drop(seed_response);
+
+    let Ok(robots_link) = seed_url.join(ROBOTS_TXT_PATH) else {
+        return Ok(());
+    };
+
+    let robots_result = client.send(Method::GET, robots_link.as_str(), state).await;
+    robots_result.map(drop).map_err(|err| {
+        let message = PreflightFailure::from_request_error(host, &err).to_string();
+        PreflightFailure::Robots { message }
+    })
+}
+
+// Asks each seed's site for a page that can't exist, so pages that come back looking like its error page can be
+// flagged even when the site answers them with a 200.
+async fn probe_not_found_pages(seed_links: &[String], source: &PageSource, state: &CrawlState) {
+    let PageSource::Network(client) = source else {
+        return;
+    };
+
+    let origins: BTreeSet<Url> = seed_links
+        .iter()
+        .filter_map(|seed_link| Url::parse(seed_link).ok())
+        .filter_map(|seed_url| Url::parse(&seed_url.origin().ascii_serialization()).ok())
+        .collect();
+
+    for origin in origins {
+        let Some(probe_link) = probe_link(&origin) else {
+            continue;
+        };
+
+        let _request_permit = client.request_permit().await;
+        let Ok(response) = client.send(Method::GET, &probe_link, state).await else {
+            continue;
+        };
+
+        let status = response.status().as_u16();
+        let content_type = response.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        let header_charset = content_type.and_then(charset_from_content_type).map(str::to_string);
+        let Ok(body) = response.bytes().await else {
+            continue;
+        };
+
+        // Decoded the same way as pages, so the fingerprint compares like with like.
+        let html = decode_html(&body, header_charset.as_deref());
+
+        let page_text = extract_page_text(&Html::parse_document(&html));
+        let fingerprint = NotFoundFingerprint::new(probe_link, status, &page_text);
+        state.record_not_found_fingerprint(origin.origin().ascii_serialization(), fingerprint);
+    }
+}
+
+async fn fetch_robots(robots_link: &str, source: &PageSource, state: &CrawlState) -> Option<String> {
+    match source {
+        PageSource::Network(client) => fetch_robots_txt(client, robots_link, state).await,
+        PageSource::Archive(archive_dir) => {
+            read_archived_page(archive_dir, robots_link).map(|archived_page| state.add_to_manifest(archived_page))
+        }
+    }
+}
+
+async fn fetch_robots_txt(client: &NetworkClient, robots_link: &str, state: &CrawlState) -> Option<String> {
+    let _request_permit = client.request_permit().await;
+    let response = client.send(Method::GET, robots_link, state)
+        .await
+        .and_then(Response::error_for_status)
+        .ok()?;
+
+    response.text().await.ok()
+}
+
+// Only the groups written for our user agent (or the "*" groups, if none are) are honoured.
+fn record_robots(
+    robots_link: &Url,
+    robots_txt: RobotsTxt,
+    user_agent: &str,
+    honored_crawl_delay: Option<Duration>,
+    state: &CrawlState,
+) {
+    let honored_rules = robots_txt.rules_for(user_agent);
+    state.set_robots_rules(robots_link.origin().ascii_serialization(), honored_rules.clone());
+
+    let robots_report = RobotsReport {
+        robots_link: robots_link.to_string(),
+        robots_txt,
+        honored_rules,
+        honored_crawl_delay_secs: honored_crawl_delay.map(|crawl_delay| crawl_delay.as_secs_f64()),
+    };
+
+    state
+        .robots_reports
+        .lock()
+        .map(|mut robots_reports| robots_reports.insert(robots_link.origin().ascii_serialization(), robots_report))
+        .expect("Failed to add value to map.");
+}
+
+// Pages are fetched concurrently, but the frontier strategy decides the order they are dispatched in.
+async fn crawl_frontier(
+    seed_links: Vec<String>,
+    sitemap_page_links: Vec<String>,
+    mut host_preparation: HostPreparation,
+    context: Arc<CrawlContext>,
+    mut frontier: Frontier,
+) {
+    let mut in_flight = JoinSet::new();
+    let mut host_confirmation = HostConfirmation::default();
+    let mut route_by_link: HashMap<Arc<str>, LinkRoute> = HashMap::new();
+    let mut queued_seeds = HashSet::new();
+    for seed_link in seed_links {
+        // A seed is marked as visited the way links are written, so pages linking back to it don't queue it again -
+        // whether or not it was already seen, since seeds are always fetched. Seeds given twice are fetched once.
+        let visited_seed_link = trim_trailing_slash(context.state.normalize_link(&seed_link));
+        context.state.add_to_visited_links([visited_seed_link.as_str()]);
+        if !queued_seeds.insert(visited_seed_link) {
+            continue;
+        }
+
+        context.state.record_seed(&seed_link);
+        let seed_link: Arc<str> = Arc::from(seed_link);
+        route_by_link.insert(seed_link.clone(), LinkRoute { seed: seed_link.clone(), depth: 0 });
+        frontier.push(seed_link);
+    }
+
+    // Already marked as visited when the sitemaps were read.
+    for sitemap_page_link in sitemap_page_links {
+        let sitemap_page_link: Arc<str> = Arc::from(sitemap_page_link);
+        route_by_link.insert(sitemap_page_link.clone(), LinkRoute { seed: sitemap_page_link.clone(), depth: 0 });
+        let held_link = HeldLink { link: sitemap_page_link, referrer: None, queue: FrontierQueue::Sitemap };
+        queue_link(held_link, &mut frontier, &mut host_preparation, &mut in_flight, &context);
+    }
+
+    let mut retries_by_link: HashMap<Arc<str>, usize> = HashMap::new();
+    let mut pages_dispatched = 0;
+    let mut sections = SectionTracker::new(&context.options.section_rules, context.options.max_depth);
+    // A fixed pool of workers, so a large site can't turn into a task per queued link. It's halved each time the
+    // memory budget is approached. How many of them have a request out at once is up to the concurrency limit.
+    let mut max_in_flight = if context.deterministic { 1 } else { context.options.workers };
+
+    // Past the open files limit, new connections fail with "Too many open files" instead of waiting their turn.
+    if let Some(connection_limit) = connection_limit().filter(|connection_limit| *connection_limit < max_in_flight) {
+        eprintln!(
+            "{} workers is over what the open files limit allows, fetching at most {} pages at once",
+            max_in_flight, connection_limit
+        );
+        max_in_flight = connection_limit;
+    }
+
+    loop {
+        if context.memory_budget.as_ref().is_some_and(MemoryBudget::is_under_pressure) {
+            let reduced_max_in_flight = (in_flight.len().min(max_in_flight) / 2).max(1);
+            if reduced_max_in_flight < max_in_flight {
+                eprintln!("Memory use is near the limit, fetching at most {} pages at once", reduced_max_in_flight);
+            }
+
+            max_in_flight = reduced_max_in_flight;
+            relieve_memory_pressure(&mut frontier, &context);
+        }
+
+        let mut has_page_budget = context.options.max_pages.is_none_or(|max_pages| pages_dispatched < max_pages);
+        while in_flight.len() < max_in_flight && has_page_budget && !context.cancellation_token.is_cancelled() {
+            let Some(link) = frontier.pop() else {
+                break;
+            };
+            let route = route_of(&route_by_link, &link);
+            // Queued before its session ID was spotted, so it's fetched without it - unless that page already has been.
+            let Some(link) = context.state.collapse_session_params(link) else {
+                continue;
+            };
+            route_by_link.entry(link.clone()).or_insert(route.clone());
+
+            // A rate-limited page coming back round has already been counted.
+            if !retries_by_link.contains_key(&link) {
+                pages_dispatched += 1;
+                has_page_budget = context.options.max_pages.is_none_or(|max_pages| pages_dispatched < max_pages);
+            }
+            in_flight.spawn(scrape_page(link, route, context.clone()));
+        }
+
+        // Rate-limited pages wait in the frontier rather than in a worker, so the loop also wakes when the first of
+        // them is due and there's a worker free to take it. Once the crawl's cancelled they're left unfetched.
+        let can_dispatch = in_flight.len() < max_in_flight && has_page_budget;
+        let next_retry = frontier
+            .next_not_before()
+            .filter(|_| can_dispatch && !context.cancellation_token.is_cancelled());
+        let next_retry_at = tokio::time::Instant::from_std(next_retry.unwrap_or_else(Instant::now));
+        let page_outcome = tokio::select! {
+            page_outcome = in_flight.join_next(), if !in_flight.is_empty() => page_outcome,
+            _ = tokio::time::sleep_until(next_retry_at), if next_retry.is_some() => continue,
+            _ = context.cancellation_token.cancelled(), if next_retry.is_some() => continue,
+            else => None,
+        };
+
+        match page_outcome {
+            Some(Ok((link, PageOutcome::Scraped(scraped_page)))) if context.follow_links => {
+                let page_route = route_of(&route_by_link, &link);
+                let link_route = LinkRoute { seed: page_route.seed, depth: page_route.depth + 1 };
+
+                // A link that's both listed in a sitemap and linked to is queued as a sitemap page.
+                let next_page = scraped_page.next_page.filter(|_| context.prioritize_pagination);
+                let found_links = [
+                    (Vec::from_iter(next_page), FrontierQueue::Priority),
+                    (scraped_page.sitemap_page_links, FrontierQueue::Sitemap),
+                    (Vec::from_iter(scraped_page.links), FrontierQueue::Strategy),
+                ];
+                for (links, queue) in found_links {
+                    let links =
+                        host_confirmation.hold_unconfirmed(links, &link_route, &link, queue, &mut in_flight, &context);
+                    let links = links.iter().map(String::as_str);
+                    for found_link in admit_links(links, link_route.depth, &mut sections, &context.state) {
+                        route_by_link.insert(found_link.clone(), link_route.clone());
+                        let held_link = HeldLink { link: found_link, referrer: Some(link.clone()), queue };
+                        queue_link(held_link, &mut frontier, &mut host_preparation, &mut in_flight, &context);
+                    }
+                }
+            }
+            Some(Ok((_, PageOutcome::HostLimitConfirmed(host_limit_reached, confirmed)))) => {
+                context.state.record_host_confirmation(&host_limit_reached, confirmed);
+
+                // Now the answer's known, each held link is either queued the way it would have been, or skipped.
+                for held_link in std::mem::take(&mut host_confirmation.held_links) {
+                    let UnconfirmedLink { link, route, referrer, queue } = held_link;
+                    let links =
+                        host_confirmation.hold_unconfirmed([link], &route, &referrer, queue, &mut in_flight, &context);
+                    let links = links.iter().map(String::as_str);
+                    for found_link in admit_links(links, route.depth, &mut sections, &context.state) {
+                        route_by_link.insert(found_link.clone(), route.clone());
+                        let held_link = HeldLink { link: found_link, referrer: Some(referrer.clone()), queue };
+                        queue_link(held_link, &mut frontier, &mut host_preparation, &mut in_flight, &context);
+                    }
+                }
+            }
+            Some(Ok((origin, PageOutcome::HostPrepared(page_links)))) => {
+                host_preparation.prepared_origins.insert(origin.to_string());
+                let held_links = host_preparation.held_links_by_origin.remove(origin.as_ref()).unwrap_or_default();
+                release_held_links(held_links, &mut frontier, &context.state);
+
+                // Queued like the seeds' sitemap pages, though they may be on hosts that still need preparing.
+                let unseen_links = context.state.add_to_visited_links(page_links.iter().map(String::as_str));
+                for page_link in unseen_links {
+                    route_by_link.insert(page_link.clone(), LinkRoute { seed: page_link.clone(), depth: 0 });
+                    let held_link = HeldLink { link: page_link, referrer: None, queue: FrontierQueue::Sitemap };
+                    queue_link(held_link, &mut frontier, &mut host_preparation, &mut in_flight, &context);
+                }
+            }
+            Some(Ok((link, PageOutcome::RetryLater(delay)))) => {
+                let retries = retries_by_link.entry(link.clone()).or_default();
+
+                if *retries < MAX_RETRIES {
+                    *retries += 1;
+                    context.state.record_requeued_request();
+                    frontier.push_not_before(link, Instant::now() + delay);
+                } else {
+                    let crawl_error = CrawlError::RateLimited { retries: MAX_RETRIES };
+                    context.state.add_to_failed_links(link.to_string(), crawl_error.clone());
+                    context.send_page_result(PageResult::Failed { page: link.to_string(), error: crawl_error }).await;
+                }
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    // Whatever was left in the frontier is still in all_links.json, just never fetched.
+    if context.options.max_pages.is_some_and(|max_pages| pages_dispatched >= max_pages) {
+        eprintln!("Stopped after {} pages, the most this crawl was allowed to fetch", pages_dispatched);
+    }
+}
+
+// A link on a host whose robots.txt hasn't been read yet is held until it has, and the first one found sets it being
+// read.
+fn queue_link(
+    held_link: HeldLink,
+    frontier: &mut Frontier,
+    host_preparation: &mut HostPreparation,
+    in_flight: &mut JoinSet<(Arc<str>, PageOutcome)>,
+    context: &Arc<CrawlContext>,
+) {
+    let origin = Url::parse(&held_link.link).map(|url| url.origin().ascii_serialization());
+    let origin = match origin {
+        Ok(origin) if host_preparation.enabled && !host_preparation.prepared_origins.contains(&origin) => origin,
+        _ => return push_link(held_link, frontier),
+    };
+
+    let held_links = host_preparation.held_links_by_origin.entry(origin.clone()).or_default();
+    if held_links.is_empty() {
+        in_flight.spawn(prepare_host(origin, context.clone()));
+    }
+    held_links.push(held_link);
+}
+
+fn push_link(held_link: HeldLink, frontier: &mut Frontier) {
+    frontier.push_to(held_link.link, held_link.queue);
+}
+
+// The host's rules weren't known when its links were found, so they're checked against robots.txt now.
+fn release_held_links(held_links: Vec<HeldLink>, frontier: &mut Frontier, state: &CrawlState) {
+    for held_link in held_links {
+        let is_allowed = Url::parse(&held_link.link).is_ok_and(|url| state.is_allowed_by_robots(&url));
+        if is_allowed {
+            push_link(held_link, frontier);
+            continue;
+        }
+
+        let blocked_link = trim_trailing_slash(held_link.link.to_string());
+        state.add_to_skipped_links(blocked_link.clone(), SkipReason::RobotsDisallowed);
+        if let Some(referrer) = held_link.referrer {
+            state.add_to_robots_blocked_links(blocked_link, referrer.to_string());
+        }
+    }
+}
+
+async fn prepare_host(origin: String, context: Arc<CrawlContext>) -> (Arc<str>, PageOutcome) {
+    let preparation = async {
+        let CrawlContext { source, state, options, .. } = context.as_ref();
+        let robots_sitemaps = process_robots(&origin, source, &options.user_agent, state).await;
+
+        if !context.follow_sitemaps {
+            return Vec::new();
+        }
+        read_sitemaps(sitemaps_to_read(robots_sitemaps, &origin), &origin, source, state).await
+    };
+
+    tokio::select! {
+        _ = context.cancellation_token.cancelled() => (Arc::from(origin.as_str()), PageOutcome::Cancelled),
+        page_links = preparation => (Arc::from(origin.as_str()), PageOutcome::HostPrepared(page_links)),
+    }
+}
+
+// On a blocking thread, since the confirmation can wait on a prompt for as long as it takes to be answered.
+async fn confirm_host_limit(
+    host_limit_reached: HostLimitReached,
+    context: Arc<CrawlContext>,
+) -> (Arc<str>, PageOutcome) {
+    let new_host = Arc::from(host_limit_reached.new_host.as_str());
+    let Some(confirmation) = context.state.host_limit_confirmation() else {
+        return (new_host, PageOutcome::HostLimitConfirmed(host_limit_reached, false));
+    };
+
+    let asked_host_limit_reached = host_limit_reached.clone();
+    let confirmed = tokio::task::spawn_blocking(move || confirmation(&asked_host_limit_reached));
+    tokio::select! {
+        _ = context.cancellation_token.cancelled() => (new_host, PageOutcome::Cancelled),
+        confirmed = confirmed => {
+            (new_host, PageOutcome::HostLimitConfirmed(host_limit_reached, confirmed.unwrap_or(false)))
+        }
+    }
+}
+
+// Every queued link has a route, but a link missing one is treated as a seed rather than dropped.
+fn route_of(route_by_link: &HashMap<Arc<str>, LinkRoute>, link: &Arc<str>) -> LinkRoute {
+    route_by_link.get(link).cloned().unwrap_or_else(|| LinkRoute { seed: link.clone(), depth: 0 })
+}
+
+// Links too deep or outside every section are left unvisited, so a shorter route to them can still queue them later.
+// Of the rest, only the ones not seen before are queued, and each of those takes up a page of its section's budget.
+fn admit_links<'a>(
+    links: impl IntoIterator<Item = &'a str>,
+    link_depth: usize,
+    sections: &mut SectionTracker,
+    state: &CrawlState,
+) -> Vec<Arc<str>> {
+    let links_in_scope: Vec<&str> = links
+        .into_iter()
+        .filter(|link| match sections.classify(link, link_depth) {
+            SectionAdmission::Admitted => true,
+            SectionAdmission::OutsideSections => {
+                state.add_to_skipped_links(link.to_string(), SkipReason::OutsideSections);
+                false
+            }
+            SectionAdmission::TooDeep | SectionAdmission::OverBudget => false,
+        })
+        .collect();
+
+    state
+        .add_to_visited_links(links_in_scope)
+        .into_iter()
+        .filter(|link| sections.admit(link, link_depth))
+        .collect()
+}
+
+fn relieve_memory_pressure(frontier: &mut Frontier, context: &CrawlContext) {
+    context.links_by_page_sink.flush();
+
+    let crawl_id = context.state.current_crawl_metadata().map(|metadata| metadata.crawl_id).unwrap_or_default();
+    let spilled_frontier = frontier.spill(&spill_path(&crawl_id, FRONTIER_SPILL_FILENAME));
+    let spilled_visited = context
+        .state
+        .visited_links
+        .lock()
+        .map(|mut visited_links| visited_links.spill(&spill_path(&crawl_id, VISITED_SPILL_FILENAME)))
+        .expect("Failed to spill visited links.");
+
+    if let Err(err) = spilled_frontier.and(spilled_visited) {
+        eprintln!("Failed to move links to disk: {:?}", err);
+    }
+}
+
+// Kept out of the output directory - they're removed once read back, and only matter while the crawl is running. Each
+// crawl gets its own, since several can run in one process.
+fn spill_path(crawl_id: &str, filename: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("crawler-{}-{}", crawl_id, filename))
+}
+
+async fn scrape_page(link: Arc<str>, route: LinkRoute, context: Arc<CrawlContext>) -> (Arc<str>, PageOutcome) {
+    let fetch_result = tokio::select! {
+        _ = context.cancellation_token.cancelled() => return (link, PageOutcome::Cancelled),
+        fetch_result = fetch_page(&link, &context) => fetch_result,
+    };
+
+    // A link put back to retry hasn't really been fetched yet.
+    if !matches!(fetch_result, Err(FetchError::RetryLater(_))) {
+        context.state.record_expected_url_fetched(&link);
+    }
+
+    let outcome = match fetch_result {
+        // A page that arrives after cancellation isn't parsed or written out, so no record is left half-reported.
+        Ok(_) if context.cancellation_token.is_cancelled() => PageOutcome::Cancelled,
+        Ok(FetchedResource::Page(fetched_page)) => {
+            let (mut scraped_page, page_result) = process_page(&link, &route, fetched_page, &context);
+            if let Some(page_result) = page_result {
+                context.send_page_result(page_result).await;
+            }
+            check_hint_targets(&scraped_page.hint_targets, &context).await;
+            check_alternate_targets(&scraped_page.alternate_targets, &context).await;
+
+            // The pages a sitemap lists are queued as if this page linked to them.
+            let sitemap_links = std::mem::take(&mut scraped_page.sitemap_links);
+            scraped_page.sitemap_page_links =
+                read_sitemaps(sitemap_links, &link, &context.source, &context.state).await;
+            PageOutcome::Scraped(scraped_page)
+        }
+        Ok(FetchedResource::HeaderLinksOnly(header_links)) => {
+            PageOutcome::Scraped(process_header_links(&link, &header_links, &context.state))
+        }
+        Ok(FetchedResource::RedirectAlias | FetchedResource::OffDomainRedirect) => PageOutcome::Skipped,
+        Err(FetchError::Failed(crawl_error)) => {
+            context.state.add_to_failed_links(link.to_string(), crawl_error.clone());
+            context.send_page_result(PageResult::Failed { page: link.to_string(), error: crawl_error }).await;
+            PageOutcome::Skipped
+        }
+        Err(FetchError::RetryLater(delay)) => PageOutcome::RetryLater(delay),
+    };
+
+    (link, outcome)
+}
+
+// The page's result is only built for crawl_stream.
+fn process_page(
+    link: &str,
+    route: &LinkRoute,
+    fetched_page: FetchedPage,
+    context: &CrawlContext,
+) -> (ScrapedPage, Option<PageResult>) {
+    let parsed_html = Html::parse_document(&fetched_page.html);
+    let state = &context.state;
+    // Before the page's links are read, so the page that gives a session ID away has it stripped from its links too.
+    state.detect_session_params(link, &fetched_page.html);
+    let mut page_links = classify_page_links(&parsed_html, link, state);
+    let mut internal_links = followed_links(&page_links);
+    let mut pagination = extract_pagination(&parsed_html, link, state);
+    let header_pagination = extract_header_pagination(&fetched_page.header_links, link, state);
+    pagination.next = pagination.next.or(header_pagination.next);
+    pagination.prev = pagination.prev.or(header_pagination.prev);
+    internal_links.extend(header_link_targets(&fetched_page.header_links, &[ALTERNATE_REL], link, state));
+
+    let page_language = PageLanguage {
+        content_language: fetched_page.content_language.clone(),
+        html_lang: extract_html_attribute(&parsed_html, LANG_ATTRIBUTE_NAME),
+        html_dir: extract_html_attribute(&parsed_html, DIR_ATTRIBUTE_NAME).map(|dir| dir.to_ascii_lowercase()),
+    };
+
+    let canonical =
+        extract_canonical(&parsed_html, link).or_else(|| extract_header_canonical(&fetched_page.header_links, link));
+    state.record_duplicate_signals(link, canonical, &fetched_page.html, page_language.language_tag());
+
+    let resource_hints = extract_resource_hints(&parsed_html, link);
+    let hint_targets = resource_hints
+        .iter()
+        .filter(|hint| hint.rel.has_fetchable_target())
+        .map(|hint| hint.href.clone())
+        .collect();
+    state.record_resource_hints(link, resource_hints);
+
+    let media_alternates = extract_media_alternates(&parsed_html, link);
+    let alternate_targets = if context.check_alternates {
+        media_alternates.iter().map(|alternate| alternate.href.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    state.record_media_alternates(link, media_alternates);
+
+    let page_text = extract_page_text(&parsed_html);
+    state.record_page_language(link, page_language, extract_hreflangs(&parsed_html, link), &page_text);
+
+    // Error statuses are already in errors.json - only pages claiming success can be soft 404s.
+    if fetched_page.status.is_some_and(|status| (200..300).contains(&status)) {
+        state.check_soft_not_found(link, &page_text);
+    }
+
+    if let Some(grep_pattern) = &context.grep_pattern {
+        state.record_grep_matches(link, find_matches(grep_pattern, &fetched_page.html));
+    }
+
+    // <link rel="next"> isn't an anchor, so without this it would never be followed.
+    internal_links.extend(pagination.next.iter().chain(pagination.prev.iter()).cloned());
+    internal_links.extend(extract_meta_refresh(&parsed_html, link, state));
+    state.record_pagination(link, &pagination);
+
+    let mut forms = extract_forms(&parsed_html, link);
+    for form in forms.iter_mut() {
+        form.expanded_links = form
+            .expand(&context.form_values)
+            .iter()
+            .filter_map(|expanded_link| validate_and_process_link(expanded_link, link, state))
+            .collect();
+        internal_links.extend(form.expanded_links.iter().cloned());
+    }
+    state.record_forms(link, forms);
+    state.record_assets(link, extract_assets(&parsed_html, link));
+
+    let listing_server = detect_directory_listing(&extract_title(&parsed_html), &fetched_page.html);
+    if let Some(listing_server) = listing_server {
+        state.record_directory_listing(link, listing_server);
+    }
+
+    let robots_directives = extract_robots_directives(&parsed_html).combine(fetched_page.header_robots_directives);
+    if robots_directives.noindex {
+        state.record_noindex(link);
+    }
+
+    // Every link a nofollow page would have queued is recorded as skipped instead - pagination and form links too.
+    let nofollow = robots_directives.nofollow && !state.ignores_nofollow();
+    if nofollow {
+        for nofollow_link in std::mem::take(&mut internal_links) {
+            state.add_to_skipped_links(nofollow_link.clone(), SkipReason::Nofollow);
+            page_links.insert(nofollow_link, LinkKind::InternalSkipped { reason: SkipReason::Nofollow });
+        }
+    }
+
+    state.record_link_graph(link, &internal_links);
+
+    // Links from pagination, meta refresh, forms and Link headers are followed the same way as anchors.
+    for internal_link in &internal_links {
+        page_links.entry(internal_link.clone()).or_insert_with(|| LinkKind::for_followed_link(internal_link));
+    }
+
+    context.links_by_page_sink.write_record(&PageRecord {
+        page: link,
+        seed: &route.seed,
+        depth: route.depth,
+        links: &page_links,
+        robots_directives,
+        headers: &fetched_page.headers,
+        next: pagination.next.as_deref(),
+        prev: pagination.prev.as_deref(),
+        html: context.html_embedding.map(|html_embedding| html_embedding.embed(&fetched_page.html)),
+    });
+    state.record_page_processed(link);
+
+    let page_result = context.page_results.as_ref().map(|_| PageResult::Crawled {
+        page: link.to_string(),
+        seed: route.seed.to_string(),
+        depth: route.depth,
+        links: page_links.clone(),
+        headers: fetched_page.headers.clone(),
+    });
+
+    // The listing's links are still recorded above, just not queued.
+    if listing_server.is_some() && !context.follow_directory_listings {
+        internal_links.clear();
+    }
+
+    let sitemap_links = if context.follow_sitemaps { extract_sitemap_links(&parsed_html, link) } else { Vec::new() };
+
+    let next_page = pagination.next.filter(|_| !nofollow);
+    let scraped_page = ScrapedPage {
+        links: internal_links,
+        next_page,
+        hint_targets,
+        alternate_targets,
+        sitemap_links,
+        sitemap_page_links: Vec::new(),
+    };
+    (scraped_page, page_result)
+}
+
+// Only the Link header is left to go on - the page itself isn't recorded, as it was skipped as non-HTML.
+fn process_header_links(link: &str, header_links: &[HeaderLink], state: &CrawlState) -> ScrapedPage {
+    let pagination = extract_header_pagination(header_links, link, state);
+    let mut internal_links: BTreeSet<String> =
+        header_link_targets(header_links, &[ALTERNATE_REL], link, state).collect();
+    internal_links.extend(pagination.next.iter().chain(pagination.prev.iter()).cloned());
+
+    if let Some(canonical) = extract_header_canonical(header_links, link) {
+        state.record_canonical(link, canonical);
+    }
+    state.record_pagination(link, &pagination);
+    state.record_link_graph(link, &internal_links);
+
+    ScrapedPage {
+        links: internal_links,
+        next_page: pagination.next,
+        hint_targets: Vec::new(),
+        alternate_targets: Vec::new(),
+        sitemap_links: Vec::new(),
+        sitemap_page_links: Vec::new(),
+    }
+}
+
+// Each hinted resource is requested once, however many pages hint at it, so broken hints show up in the report.
+async fn check_hint_targets(hint_targets: &[String], context: &CrawlContext) {
+    let PageSource::Network(client) = &context.source else {
+        return;
+    };
+
+    for hint_target in hint_targets {
+        if !context.state.start_hint_target_check(hint_target) {
+            continue;
+        }
+
+        let Some(status) = head_status(client, hint_target, &context.cancellation_token, &context.state).await else {
+            return;
+        };
+
+        context.state.record_hint_target_status(hint_target, status);
+    }
+}
+
+async fn check_alternate_targets(alternate_targets: &[String], context: &CrawlContext) {
+    let PageSource::Network(client) = &context.source else {
+        return;
+    };
+
+    for alternate_target in alternate_targets {
+        if !context.state.start_alternate_target_check(alternate_target) {
+            continue;
+        }
+
+        let head_status = head_status(client, alternate_target, &context.cancellation_token, &context.state).await;
+        let Some(status) = head_status else {
+            return;
+        };
+
+        context.state.record_alternate_target_status(alternate_target, status);
+    }
+}
+
+// The status line or the error for a HEAD request to the target - None if the crawl was cancelled while waiting.
+async fn head_status(
+    client: &NetworkClient,
+    target: &str,
+    cancellation_token: &CancellationToken,
+    state: &CrawlState,
+) -> Option<String> {
+    let request = async {
+        let _request_permit = client.request_permit().await;
+        client.send(Method::HEAD, target, state).await
+    };
+
+    let response_result = tokio::select! {
+        _ = cancellation_token.cancelled() => return None,
+        response_result = request => response_result,
+    };
+
+    match response_result {
+        Ok(response) => Some(format!("HTTP {}", response.status())),
+        Err(err) => Some(err.to_string()),
+    }
+}
+
+async fn fetch_page(link: &str, context: &CrawlContext) -> Result<FetchedResource, FetchError> {
+    match &context.source {
+        PageSource::Network(client) => {
+            fetch_html_content(client, link, &context.captured_headers, &context.state).await
+        }
+        PageSource::Archive(archive_dir) => {
+            let archived_page = read_archived_page(archive_dir, link).ok_or(CrawlError::NotArchived)?;
+            Ok(FetchedResource::Page(FetchedPage {
+                html: context.state.add_to_manifest(archived_page),
+                headers: BTreeMap::new(),
+                content_language: None,
+                status: None,
+                header_links: Vec::new(),
+                header_robots_directives: RobotsDirectives::default(),
+            }))
+        }
+    }
+}
+
+async fn fetch_html_content(
+    client: &NetworkClient,
+    link: &str,
+    captured_headers: &[String],
+    state: &CrawlState,
+) -> Result<FetchedResource, FetchError> {
+    let _request_permit = client.request_permit().await;
+    let request_start = Instant::now();
+    let response_result = if client.sample_ranges {
+        sample_resource(client, link, state).await
+    } else {
+        client.send(Method::GET, link, state).await
+    };
+
+    match response_result {
+        Ok(response) => {
+            let status = response.status();
+            state.record_response(link, status.as_u16(), request_start.elapsed());
+            state.record_served_scheme(response.url());
+
+            // The response is the redirect the chain stopped at, so there's no page to parse.
+            if let Some((redirect_stop, target)) = state.stopped_redirect(link).filter(|_| status.is_redirection()) {
+                return match redirect_stop {
+                    RedirectStop::TooManyRedirects => {
+                        Err(FetchError::Failed(CrawlError::TooManyRedirects { max_redirects: client.max_redirects }))
+                    }
+                    RedirectStop::Loop => Err(FetchError::Failed(CrawlError::RedirectLoop)),
+                    RedirectStop::OffDomain | RedirectStop::BlockedHost => {
+                        let target_url = Url::parse(&target);
+                        let redirect_audit = Url::parse(link)
+                            .ok()
+                            .zip(target_url.ok())
+                            .and_then(|(url, target_url)| audit_redirect(&url, &target_url));
+                        if let Some(redirect_audit) = redirect_audit {
+                            state.add_to_redirect_audits(link.to_string(), redirect_audit);
+                        }
+                        let skip_reason = match redirect_stop {
+                            RedirectStop::BlockedHost => SkipReason::BlockedHost,
+                            _ => SkipReason::OffDomain,
+                        };
+                        state.add_to_skipped_links(target, skip_reason);
+                        Ok(FetchedResource::OffDomainRedirect)
+                    }
+                };
+            }
+
+            // The hops themselves were recorded in the redirect chain as execute followed them. Here it's only whether
+            // the chain ended somewhere other than the link - trailing slash and normalization aside - that matters.
+            let final_link = trim_trailing_slash(state.normalize_link(response.url().as_str()));
+            let redirected = final_link != trim_trailing_slash(link.to_string());
+            if redirected {
+                let redirect_audit = Url::parse(link).ok().and_then(|url| audit_redirect(&url, response.url()));
+                if let Some(redirect_audit) = redirect_audit {
+                    state.add_to_redirect_audits(link.to_string(), redirect_audit);
+                }
+                state.record_redirect(link, final_link.clone());
+            }
+
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(FetchError::RetryLater(retry_after_delay(response.headers())));
+            }
+
+            // The page a redirect lands on is marked as visited, so it isn't fetched again when a link to it turns up
+            // later. If it was already there, it's parsed under its own URL instead of a second time under this one.
+            let stays_in_scope = Url::parse(link).is_ok_and(|url| state.is_in_scope(response.url(), &url));
+            if redirected && stays_in_scope && state.add_to_visited_links([final_link.as_str()]).is_empty() {
+                state.add_to_redirect_aliases(link.to_string(), final_link);
+                return Ok(FetchedResource::RedirectAlias);
+            }
+
+            if status.is_client_error() || status.is_server_error() {
+                state.add_to_failed_links(link.to_string(), CrawlError::HttpStatus { status: status.as_u16() });
+            }
+
+            if let Some(etag) = response.headers().get(header::ETAG) {
+                if let Ok(etag_val) = etag.to_str() {
+                    state.add_to_etags(link.to_string(), etag_val.to_string());
+                }
+            }
+
+            Ok(read_html_body(link, response, client, captured_headers, state).await?)
+        }
+        Err(err) => {
+            state.record_request_failure(link);
+            Err(CrawlError::from_request_error(&err).into())
+        }
+    }
+}
+
+// The full size of the resource is recorded from the sample. A page sampled this way is fetched again in full, since
+// its links are wanted too.
+async fn sample_resource(client: &NetworkClient, link: &str, state: &CrawlState) -> reqwest::Result<Response> {
+    let response = client.send_range(link, SAMPLE_RANGE, state).await?;
+    if let Some(size) = resource_size(response.status(), response.headers()) {
+        state.record_resource_size(link, size);
+    }
+
+    // An empty resource has no first byte to send, so the status that matters is the one for a plain GET.
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return client.send(Method::GET, link, state).await;
+    }
+
+    let content_type = response.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    if response.status() == StatusCode::PARTIAL_CONTENT && is_markup_content_type(content_type.unwrap_or_default()) {
+        return client.send(Method::GET, link, state).await;
+    }
+
+    Ok(response)
+}
+
+// HTML, and XML that might turn out to be XHTML once its body has been read. Parameters such as the charset don't
+// matter here.
+fn is_markup_content_type(content_type: &str) -> bool {
+    let media_type = media_type(content_type);
+    media_type == HTML_CONTENT_TYPE || media_type == XHTML_CONTENT_TYPE || is_xml_content_type(&media_type)
+}
+
+fn is_xml_content_type(content_type: &str) -> bool {
+    XML_CONTENT_TYPES.contains(&media_type(content_type).as_str())
+}
+
+async fn read_html_body(
+    link: &str,
+    response: Response,
+    client: &NetworkClient,
+    captured_headers: &[String],
+    state: &CrawlState,
+) -> Result<FetchedResource, CrawlError> {
+    // The body read below consumes the response, so keep the headers around for capturing afterwards.
+    let response_headers = response.headers().clone();
+    let status = response.status().as_u16();
+    let header_links: Vec<HeaderLink> = response_headers
+        .get_all(header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(parse_link_header)
+        .collect();
+    let content_type = response_headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    let content_type_val = content_type.unwrap_or_default().to_string();
+    let links_only = |header_links: Vec<HeaderLink>| {
+        if header_links.is_empty() {
+            Err(CrawlError::NonHtml { content_type: content_type_val.clone() })
+        } else {
+            Ok(FetchedResource::HeaderLinksOnly(header_links))
+        }
+    };
+
+    let is_xml = is_xml_content_type(&content_type_val);
+
+    if !is_markup_content_type(&content_type_val) {
+        // The body is never read, so its declared length is the best there is.
+        state.record_bytes(link, response.content_length().unwrap_or_default());
+        state.add_to_skipped_links(link.to_string(), SkipReason::NonHtml);
+        return links_only(header_links);
+    }
+
+    let body = read_body(response, client.min_throughput, client.max_body_size).await.inspect_err(|err| {
+        if matches!(err, CrawlError::TooLarge { .. }) {
+            state.add_to_skipped_links(link.to_string(), SkipReason::TooLarge);
+        }
+    })?;
+    state.record_bytes(link, body.len() as u64);
+    let html_content = decode_html(&body, charset_from_content_type(&content_type_val));
+
+    // Plain XML is only worth parsing when it's actually an XHTML document.
+    if is_xml && !html_content.contains(XHTML_NAMESPACE) {
+        state.add_to_skipped_links(link.to_string(), SkipReason::NonHtml);
+        return links_only(header_links);
+    }
+
+    let headers = capture_headers(&response_headers, captured_headers);
+    let content_language = response_headers
+        .get(header::CONTENT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let header_robots_directives = response_headers
+        .get_all(X_ROBOTS_TAG_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(RobotsDirectives::parse_header_value)
+        .fold(RobotsDirectives::default(), RobotsDirectives::combine);
+
+    Ok(FetchedResource::Page(FetchedPage {
+        html: html_content,
+        headers,
+        content_language,
+        status: Some(status),
+        header_links,
+        header_robots_directives,
+    }))
+}
+
+// Retry-After is either a number of seconds or an HTTP date. Without one a short default is used, and long waits are
+// capped so a single host can't hold up the end of the crawl indefinitely.
+fn retry_after_delay(response_headers: &HeaderMap) -> Duration {
+    let retry_after = response_headers
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim);
+
+    let delay = retry_after.and_then(|value| {
+        value.parse::<u64>().map(Duration::from_secs).ok().or_else(|| {
+            let retry_at = httpdate::parse_http_date(value).ok()?;
+            Some(retry_at.duration_since(SystemTime::now()).unwrap_or_default())
+        })
+    });
+
+    delay.unwrap_or(DEFAULT_RETRY_DELAY).min(MAX_RETRY_DELAY)
+}
+
+// Repeated headers (e.g. several server-timing entries) are joined the same way HTTP allows them to be folded.
+fn capture_headers(response_headers: &HeaderMap, header_names: &[String]) -> BTreeMap<String, String> {
+    header_names
+        .iter()
+        .filter_map(|header_name| {
+            let values: Vec<&str> = response_headers
+                .get_all(header_name.as_str())
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+
+            (!values.is_empty()).then(|| (header_name.clone(), values.join(", ")))
+        })
+        .collect()
+}
+
+fn all_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let header_names: Vec<String> = headers.keys().map(|header_name| header_name.to_string()).collect();
+    capture_headers(headers, &header_names)
+}
+
+// Trailing slashes are causing unwanted mapping. Prefer a more implicit way to do this.
+fn trim_trailing_slash(mut link_to_trim: String) -> String {
+    if link_to_trim.ends_with('/') {
+        link_to_trim.truncate(link_to_trim.len() - 1)
+    }
+
+    link_to_trim
+}
+
+// Every link on the page the crawler would follow, resolved and normalised the same way as during a crawl. Outside a
+// crawl there's no robots.txt to honour, so only the scope is checked.
+pub fn extract_links(html: &str, page_url: &str) -> BTreeSet<String> {
+    generate_internal_links(&Html::parse_document(html), page_url, &CrawlState::default())
+}
+
+// An href as the crawler would queue it, or None if it wouldn't be followed from that page.
+pub fn resolve_link(href: &str, page_url: &str) -> Option<String> {
+    validate_and_process_link(href, page_url, &CrawlState::default())
+}
+
+fn generate_internal_links(parsed_html: &Html, page_url: &str, state: &CrawlState) -> BTreeSet<String> {
+    followed_links(&classify_page_links(parsed_html, page_url, state))
+}
+
+fn followed_links(page_links: &BTreeMap<String, LinkKind>) -> BTreeSet<String> {
+    page_links.iter().filter(|(_, link_kind)| link_kind.is_followed()).map(|(link, _)| link.clone()).collect()
+}
+
+// Every anchor on the page, followed or not.
+fn classify_page_links(parsed_html: &Html, page_url: &str, state: &CrawlState) -> BTreeMap<String, LinkKind> {
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return BTreeMap::new();
+    };
+
+    collect_page_links(parsed_html, &base_url, page_url, state)
+}
+
+fn collect_page_links(
+    parsed_html: &Html,
+    base_url: &Url,
+    page_url: &str,
+    state: &CrawlState,
+) -> BTreeMap<String, LinkKind> {
+    let mut page_links = BTreeMap::new();
+
+    for element in elements_named(parsed_html, &[A_HTML_TAG]) {
+        let Some(href_value) = element.value().attr(HREF_ATTRIBUTE_NAME) else {
+            continue;
+        };
+
+        let validated_link = match validate_href(href_value, base_url, page_url, state) {
+            ValidatedLink::Followed(link) if has_nofollow_rel(&element) && !state.ignores_nofollow() => {
+                skip_link(trim_trailing_slash(link), SkipReason::Nofollow, state)
+            }
+            validated_link => validated_link,
+        };
+
+        match validated_link {
+            ValidatedLink::Followed(link) => {
+                let link = trim_trailing_slash(link);
+                let link_kind = LinkKind::for_followed_link(&link);
+                page_links.insert(link, link_kind);
+            }
+            ValidatedLink::Skipped(link, reason) => {
+                let link_kind = match reason {
+                    SkipReason::OffDomain => LinkKind::External,
+                    SkipReason::UnsupportedScheme => LinkKind::NonHttp,
+                    _ => LinkKind::InternalSkipped { reason },
+                };
+                page_links.insert(link, link_kind);
+            }
+            ValidatedLink::Unresolvable => {}
+        }
+    }
+
+    // Pages are parsed as a browser with scripting would, which leaves <noscript> contents as plain text. Sites often
+    // put a crawlable nav in there for exactly the clients that can't run their JS, so it's parsed separately.
+    for noscript_element in elements_named(parsed_html, &[NOSCRIPT_HTML_TAG]) {
+        let noscript_html = Html::parse_fragment(&noscript_element.text().collect::<String>());
+        page_links.extend(collect_page_links(&noscript_html, base_url, page_url, state));
+    }
+
+    page_links
+}
+
+// Only rel="nofollow" itself - ugc and sponsored links are followed, as they usually carry nofollow as well when
+// they're meant not to be.
+fn has_nofollow_rel(element: &ElementRef) -> bool {
+    let rel_value = element.value().attr(REL_ATTRIBUTE_NAME).unwrap_or_default();
+    rel_value.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(NOFOLLOW_REL))
+}
+
+// <meta name="robots"> tags aimed at a single crawler, e.g. <meta name="googlebot">, are left to that crawler.
+fn extract_robots_directives(parsed_html: &Html) -> RobotsDirectives {
+    elements_named(parsed_html, &[META_HTML_TAG])
+        .filter(|element| {
+            let name = element.value().attr(NAME_ATTRIBUTE_NAME).unwrap_or_default();
+            name.trim().eq_ignore_ascii_case(ROBOTS_META_NAME)
+        })
+        .filter_map(|element| element.value().attr(CONTENT_ATTRIBUTE_NAME))
+        .map(RobotsDirectives::parse)
+        .fold(RobotsDirectives::default(), RobotsDirectives::combine)
+}
+
+// Judged by the extension of the path's last segment, since the content type isn't known until it's fetched.
+fn is_asset_link(link: &str) -> bool {
+    let Ok(url) = Url::parse(link) else {
+        return false;
+    };
+
+    let last_segment = url.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or_default();
+    last_segment
+        .rsplit_once('.')
+        .is_some_and(|(_, extension)| ASSET_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+// rel="next" on a <link> in the head is the usual form, but some sites only mark up their pagination anchors.
+fn extract_pagination(parsed_html: &Html, page_url: &str, state: &CrawlState) -> PaginationLinks {
+    let mut pagination = PaginationLinks::default();
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return pagination;
+    };
+
+    for element in elements_named(parsed_html, &[LINK_HTML_TAG, A_HTML_TAG]) {
+        let (Some(rel_value), Some(href_value)) =
+            (element.value().attr(REL_ATTRIBUTE_NAME), element.value().attr(HREF_ATTRIBUTE_NAME))
+        else {
+            continue;
+        };
+
+        for rel in rel_value.split_ascii_whitespace().map(str::to_ascii_lowercase) {
+            let relation = if rel == NEXT_REL {
+                &mut pagination.next
+            } else if PREV_RELS.contains(&rel.as_str()) {
+                &mut pagination.prev
+            } else {
+                continue;
+            };
+
+            if relation.is_none() {
+                *relation = validate_and_process_href(href_value, &base_url, page_url, state);
+            }
+        }
+    }
+
+    pagination
+}
+
+// The same relations from the Link response header, for APIs and CMSes that paginate outside the markup.
+fn extract_header_pagination(header_links: &[HeaderLink], page_url: &str, state: &CrawlState) -> PaginationLinks {
+    PaginationLinks {
+        next: header_link_targets(header_links, &[NEXT_REL], page_url, state).next(),
+        prev: header_link_targets(header_links, &PREV_RELS, page_url, state).next(),
+    }
+}
+
+// Header links with any of the relations, resolved against the page and scoped like anchors.
+fn header_link_targets<'a>(
+    header_links: &'a [HeaderLink],
+    rels: &'a [&str],
+    page_url: &'a str,
+    state: &'a CrawlState,
+) -> impl Iterator<Item = String> + 'a {
+    header_links
+        .iter()
+        .filter(|header_link| rels.iter().any(|rel| header_link.has_rel(rel)))
+        .filter_map(|header_link| validate_and_process_link(&header_link.href, page_url, state))
+}
+
+// Hints commonly point at other origins (CDNs, font hosts), so like canonical URLs they're resolved but not scoped.
+fn extract_resource_hints(parsed_html: &Html, page_url: &str) -> Vec<ResourceHint> {
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return Vec::new();
+    };
+
+    elements_named(parsed_html, &[LINK_HTML_TAG])
+        .filter_map(|element| {
+            let rel_value = element.value().attr(REL_ATTRIBUTE_NAME)?;
+            let rel = rel_value.split_ascii_whitespace().find_map(HintRel::from_rel)?;
+            let href = base_url.join(element.value().attr(HREF_ATTRIBUTE_NAME)?).ok()?;
+            let destination = element.value().attr(AS_ATTRIBUTE_NAME).map(str::to_string);
+
+            Some(ResourceHint { rel, href: href.to_string(), destination })
+        })
+        .collect()
+}
+
+fn extract_title(parsed_html: &Html) -> String {
+    elements_named(parsed_html, &[TITLE_HTML_TAG])
+        .next()
+        .map(|title_element| title_element.text().collect())
+        .unwrap_or_default()
+}
+
+fn extract_page_text(parsed_html: &Html) -> String {
+    parsed_html.root_element().text().collect::<Vec<_>>().join(" ")
+}
+
+// The static files a page loads - images, scripts, stylesheets, icons, frames and media. Like canonical URLs they're
+// resolved but not scoped, as they're often on a CDN. Each is listed once per page, as whatever first loads it.
+fn extract_assets(parsed_html: &Html, page_url: &str) -> BTreeMap<String, AssetKind> {
+    let mut assets = BTreeMap::new();
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return assets;
+    };
+
+    for element in elements_named(parsed_html, &ASSET_HTML_TAGS) {
+        let attribute = |name: &str| element.value().attr(name);
+        let srcset = || attribute(SRCSET_ATTRIBUTE_NAME).map(parse_srcset).unwrap_or_default();
+
+        let sources: Vec<(&str, AssetKind)> = match local_name(element.value().name()) {
+            IMG_HTML_TAG => attribute(SRC_ATTRIBUTE_NAME).into_iter().chain(srcset()).map(image_asset).collect(),
+            SCRIPT_HTML_TAG => attribute(SRC_ATTRIBUTE_NAME).map(|src| (src, AssetKind::Script)).into_iter().collect(),
+            LINK_HTML_TAG => attribute(REL_ATTRIBUTE_NAME)
+                .and_then(AssetKind::from_link_rel)
+                .zip(attribute(HREF_ATTRIBUTE_NAME))
+                .map(|(asset_kind, href)| (href, asset_kind))
+                .into_iter()
+                .collect(),
+            IFRAME_HTML_TAG => attribute(SRC_ATTRIBUTE_NAME).map(|src| (src, AssetKind::Iframe)).into_iter().collect(),
+            VIDEO_HTML_TAG => attribute(SRC_ATTRIBUTE_NAME)
+                .map(|src| (src, AssetKind::Video))
+                .into_iter()
+                .chain(attribute(POSTER_ATTRIBUTE_NAME).map(image_asset))
+                .collect(),
+            AUDIO_HTML_TAG => attribute(SRC_ATTRIBUTE_NAME).map(|src| (src, AssetKind::Audio)).into_iter().collect(),
+            // A <source> is whatever its parent plays - in a <picture>, an image with a srcset.
+            SOURCE_HTML_TAG => {
+                let parent = element.parent().and_then(ElementRef::wrap);
+                let asset_kind = match parent.map(|parent| local_name(parent.value().name())) {
+                    Some(VIDEO_HTML_TAG) => AssetKind::Video,
+                    Some(AUDIO_HTML_TAG) => AssetKind::Audio,
+                    _ => AssetKind::Image,
+                };
+                attribute(SRC_ATTRIBUTE_NAME).into_iter().chain(srcset()).map(|src| (src, asset_kind)).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        for (source, asset_kind) in sources {
+            let Ok(mut asset_url) = base_url.join(source.trim()) else {
+                continue;
+            };
+
+            // data: URIs are inline, so there's nothing to audit.
+            if matches!(asset_url.scheme(), "http" | "https") {
+                asset_url.set_fragment(None);
+                assets.entry(asset_url.to_string()).or_insert(asset_kind);
+            }
+        }
+    }
+
+    assets
+}
+
+fn image_asset(src: &str) -> (&str, AssetKind) {
+    (src, AssetKind::Image)
+}
+
+// A form without an action submits to the page it's on - not the <base href> - and one without a method is a GET.
+fn extract_forms(parsed_html: &Html, page_url: &str) -> Vec<Form> {
+    let (Ok(page_url), Some(base_url)) = (Url::parse(page_url), document_base_url(parsed_html, page_url)) else {
+        return Vec::new();
+    };
+
+    elements_named(parsed_html, &[FORM_HTML_TAG])
+        .filter_map(|form_element| {
+            let action = match form_element.value().attr(ACTION_ATTRIBUTE_NAME).filter(|action| !action.is_empty()) {
+                Some(action) => base_url.join(action).ok()?,
+                None => page_url.clone(),
+            };
+            let method = form_element.value().attr(METHOD_ATTRIBUTE_NAME).unwrap_or(DEFAULT_FORM_METHOD);
+
+            let fields = form_element
+                .descendants()
+                .filter_map(ElementRef::wrap)
+                .filter_map(extract_form_field)
+                .collect();
+
+            Some(Form {
+                action: action.to_string(),
+                method: method.to_ascii_lowercase(),
+                fields,
+                expanded_links: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+// A field's value is what the form would submit if nobody touched it.
+fn extract_form_field(element: ElementRef) -> Option<FormField> {
+    let (field_type, value) = match local_name(element.value().name()) {
+        INPUT_HTML_TAG => {
+            let input_type = element.value().attr(TYPE_ATTRIBUTE_NAME).unwrap_or(DEFAULT_INPUT_TYPE);
+            (input_type.to_ascii_lowercase(), element.value().attr(VALUE_ATTRIBUTE_NAME).map(str::to_string))
+        }
+        SELECT_HTML_TAG => {
+            let selected_option = element
+                .descendants()
+                .filter_map(ElementRef::wrap)
+                .filter(|option| local_name(option.value().name()) == OPTION_HTML_TAG)
+                .find(|option| option.value().attr(SELECTED_ATTRIBUTE_NAME).is_some());
+            let selected_value = selected_option.map(|option| match option.value().attr(VALUE_ATTRIBUTE_NAME) {
+                Some(option_value) => option_value.to_string(),
+                None => option.text().collect(),
+            });
+            (SELECT_HTML_TAG.to_string(), selected_value)
+        }
+        TEXTAREA_HTML_TAG => (TEXTAREA_HTML_TAG.to_string(), Some(element.text().collect())),
+        _ => return None,
+    };
+
+    let name = element.value().attr(NAME_ATTRIBUTE_NAME)?.to_string();
+    Some(FormField { name, field_type, value })
+}
+
+// Like canonicals, alternates are usually on another host, so they're resolved but not checked against the scope.
+fn extract_media_alternates(parsed_html: &Html, page_url: &str) -> Vec<MediaAlternate> {
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return Vec::new();
+    };
+
+    elements_named(parsed_html, &[LINK_HTML_TAG])
+        .filter(|element| {
+            let rel_value = element.value().attr(REL_ATTRIBUTE_NAME).unwrap_or_default();
+            rel_value.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(ALTERNATE_REL))
+        })
+        .filter_map(|element| {
+            let media = element.value().attr(MEDIA_ATTRIBUTE_NAME)?.trim().to_string();
+            let href = base_url.join(element.value().attr(HREF_ATTRIBUTE_NAME)?).ok()?;
+
+            Some(MediaAlternate { href: href.to_string(), media })
+        })
+        .collect()
+}
+
+// lang and dir on the root element describe the whole document.
+fn extract_html_attribute(parsed_html: &Html, attribute_name: &str) -> Option<String> {
+    elements_named(parsed_html, &[HTML_HTML_TAG])
+        .find_map(|html_element| html_element.value().attr(attribute_name))
+        .map(|attribute_value| attribute_value.trim().to_string())
+        .filter(|attribute_value| !attribute_value.is_empty())
+}
+
+// Each hreflang with the page it points at, normalised the same way as crawled links so the two can be matched up.
+fn extract_hreflangs(parsed_html: &Html, page_url: &str) -> BTreeMap<String, String> {
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return BTreeMap::new();
+    };
+
+    elements_named(parsed_html, &[LINK_HTML_TAG])
+        .filter(|element| {
+            let rel_value = element.value().attr(REL_ATTRIBUTE_NAME).unwrap_or_default();
+            rel_value.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(ALTERNATE_REL))
+        })
+        .filter_map(|element| {
+            let hreflang = element.value().attr(HREFLANG_ATTRIBUTE_NAME)?.trim().to_ascii_lowercase();
+            let mut href = base_url.join(element.value().attr(HREF_ATTRIBUTE_NAME)?).ok()?;
+            href.set_fragment(None);
+
+            Some((hreflang, trim_trailing_slash(href.to_string())))
+        })
+        .collect()
+}
+
+// Unlike links, a canonical URL can point anywhere, so it's only resolved - not checked against the crawl's scope.
+fn extract_canonical(parsed_html: &Html, page_url: &str) -> Option<String> {
+    let base_url = document_base_url(parsed_html, page_url)?;
+
+    elements_named(parsed_html, &[LINK_HTML_TAG])
+        .filter(|element| {
+            let rel_value = element.value().attr(REL_ATTRIBUTE_NAME).unwrap_or_default();
+            rel_value.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(CANONICAL_REL))
+        })
+        .find_map(|element| base_url.join(element.value().attr(HREF_ATTRIBUTE_NAME)?).ok())
+        .map(|mut canonical_url| {
+            canonical_url.set_fragment(None);
+            trim_trailing_slash(canonical_url.to_string())
+        })
+}
+
+// Sitemaps are often served from another host (e.g. a CDN), so like canonical URLs they're resolved but not scoped.
+fn extract_sitemap_links(parsed_html: &Html, page_url: &str) -> Vec<String> {
+    let Some(base_url) = document_base_url(parsed_html, page_url) else {
+        return Vec::new();
+    };
+
+    elements_named(parsed_html, &[LINK_HTML_TAG])
+        .filter(|element| {
+            let rel_value = element.value().attr(REL_ATTRIBUTE_NAME).unwrap_or_default();
+            rel_value.split_ascii_whitespace().any(|rel| rel.eq_ignore_ascii_case(SITEMAP_REL))
+        })
+        .filter_map(|element| base_url.join(element.value().attr(HREF_ATTRIBUTE_NAME)?).ok())
+        .map(String::from)
+        .collect()
+}
+
+// <meta http-equiv="refresh" content="5; url=/new-page"> sends browsers on to another page, so its target is followed
+// like a link. A refresh without a URL only reloads the page.
+fn extract_meta_refresh(parsed_html: &Html, page_url: &str, state: &CrawlState) -> Option<String> {
+    let content = elements_named(parsed_html, &[META_HTML_TAG])
+        .filter(|element| {
+            let http_equiv = element.value().attr(HTTP_EQUIV_ATTRIBUTE_NAME).unwrap_or_default();
+            http_equiv.trim().eq_ignore_ascii_case(REFRESH_HTTP_EQUIV)
+        })
+        .find_map(|element| element.value().attr(CONTENT_ATTRIBUTE_NAME))?;
+
+    // Browsers accept a comma in place of the semicolon, and the URL is sometimes quoted.
+    let (_, target) = content.split_once([';', ','])?;
+    let (param, refresh_link) = target.split_once('=')?;
+    if !param.trim().eq_ignore_ascii_case(REFRESH_URL_PARAM) {
+        return None;
+    }
+
+    let refresh_link = refresh_link.trim().trim_matches(['\'', '"']);
+    validate_and_process_href(refresh_link, &document_base_url(parsed_html, page_url)?, page_url, state)
+}
+
+fn extract_header_canonical(header_links: &[HeaderLink], page_url: &str) -> Option<String> {
+    let page_url = Url::parse(page_url).ok()?;
+
+    header_links
+        .iter()
+        .filter(|header_link| header_link.has_rel(CANONICAL_REL))
+        .find_map(|header_link| page_url.join(&header_link.href).ok())
+        .map(|mut canonical_url| {
+            canonical_url.set_fragment(None);
+            trim_trailing_slash(canonical_url.to_string())
+        })
+}
+
+// XHTML served as XML can prefix its elements (<x:a>), which the parser keeps as part of the tag name.
+fn elements_named<'a>(parsed_html: &'a Html, tags: &'a [&str]) -> impl Iterator<Item = ElementRef<'a>> {
+    parsed_html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| tags.contains(&local_name(element.value().name())))
+}
+
+// Without any namespace prefix, e.g. "a" for "html:a" in XHTML.
+fn local_name(tag_name: &str) -> &str {
+    tag_name.rsplit_once(':').map_or(tag_name, |(_, local_name)| local_name)
+}
+
+// Relative URLs in the markup resolve against the first <base href>, as a browser would, or the page itself if there's
+// none. Link headers aren't part of the document, so they keep resolving against the page.
+fn document_base_url(parsed_html: &Html, page_url: &str) -> Option<Url> {
+    let page_url = Url::parse(page_url).ok()?;
+    let base_url = elements_named(parsed_html, &[BASE_HTML_TAG])
+        .find_map(|element| element.value().attr(HREF_ATTRIBUTE_NAME))
+        .and_then(|base_href| page_url.join(base_href.trim()).ok())
+        .filter(|base_url| base_url.scheme() == "http" || base_url.scheme() == "https");
+
+    Some(base_url.unwrap_or(page_url))
+}
+
+// Resolved against the base, but scoped against the page that links out - a base on a CDN doesn't widen the crawl.
+fn validate_and_process_href(href: &str, base_url: &Url, page_url: &str, state: &CrawlState) -> Option<String> {
+    validate_href(href, base_url, page_url, state).followed().map(trim_trailing_slash)
+}
+
+// A protocol-relative href resolves to the base's scheme. It's only skipped once it's known it would otherwise have
+// been followed, so one pointing off the site is still reported as external.
+fn validate_href(href: &str, base_url: &Url, page_url: &str, state: &CrawlState) -> ValidatedLink {
+    let Ok(href_url) = base_url.join(href) else {
+        return ValidatedLink::Unresolvable;
+    };
+
+    match validate_link(href_url.as_str(), page_url, state) {
+        ValidatedLink::Followed(link) if state.skips_protocol_relative() && is_protocol_relative(href) => {
+            skip_link(trim_trailing_slash(link), SkipReason::ProtocolRelative, state)
+        }
+        validated_link => validated_link,
+    }
+}
+
+fn validate_and_process_link(link: &str, page_url: &str, state: &CrawlState) -> Option<String> {
+    let validated_link = validate_link(link, page_url, state).followed();
+    validated_link.map(trim_trailing_slash)
+}
+
+fn validate_link(link: &str, page_url: &str, state: &CrawlState) -> ValidatedLink {
+    // Relative hrefs (including "?page=2" and "#top") resolve against the page they're on, per RFC 3986.
+    let Ok(page_url) = Url::parse(page_url) else {
+        return ValidatedLink::Unresolvable;
+    };
+    let Ok(mut full_url) = page_url.join(link) else {
+        return ValidatedLink::Unresolvable;
+    };
+
+    if full_url.scheme() != "http" && full_url.scheme() != "https" {
+        return skip_link(full_url.to_string(), SkipReason::UnsupportedScheme, state);
+    }
+
+    // Fragments never reach the server, so "#top" is just the page itself.
+    state.normalize_url(&mut full_url);
+
+    if let Some(stripped_link) = state.strip_session_params(full_url.as_str()) {
+        let Ok(stripped_url) = Url::parse(&stripped_link) else {
+            return ValidatedLink::Unresolvable;
+        };
+        full_url = stripped_url;
+    }
+
+    if !state.is_in_scope(&full_url, &page_url) {
+        return skip_link(trim_trailing_slash(full_url.to_string()), SkipReason::OffDomain, state);
+    }
+
+    if state.is_blocked_host(&full_url) {
+        return skip_link(trim_trailing_slash(full_url.to_string()), SkipReason::BlockedHost, state);
+    }
+
+    // Before the link can reach the visited set, so excluded links aren't counted as discovered.
+    if !state.is_allowed_by_filter(&full_url) {
+        return skip_link(trim_trailing_slash(full_url.to_string()), SkipReason::Excluded, state);
+    }
+
+    // One on a host still waiting for the limit to be confirmed goes on to the frontier loop, which holds it till then.
+    if state.host_admission(&full_url) == HostAdmission::Refused {
+        return skip_link(trim_trailing_slash(full_url.to_string()), SkipReason::HostLimit, state);
+    }
+
+    if state.is_allowed_by_robots(&full_url) {
+        return ValidatedLink::Followed(full_url.to_string());
+    }
+
+    let blocked_link = trim_trailing_slash(full_url.to_string());
+    state.add_to_robots_blocked_links(blocked_link.clone(), page_url.to_string());
+    skip_link(blocked_link, SkipReason::RobotsDisallowed, state)
+}
+
+fn skip_link(link: String, reason: SkipReason, state: &CrawlState) -> ValidatedLink {
+    state.add_to_skipped_links(link.clone(), reason);
+    ValidatedLink::Skipped(link, reason)
+}
+
+// Blank lines and #-comments are skipped, as are lines that aren't valid URLs.
+fn read_url_list(url_list_path: &Path) -> std::io::Result<Vec<String>> {
+    let url_list = std::fs::read_to_string(url_list_path)?;
+
+    let links = url_list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match Url::parse(line) {
+            Ok(parsed_url) => Some(parsed_url.to_string()),
+            Err(err) => {
+                eprintln!("Skipping invalid URL {} in list: {:?}", line, err);
+                None
+            }
+        })
+        .collect();
+
+    Ok(links)
+}
+
+// Links in all_links.json are already in the visited set's form. Listed URLs are parsed, which adds a trailing slash
+// to bare hosts, so it's trimmed back off to match.
+fn read_seen_links(seen_links_path: &Path) -> std::io::Result<Vec<String>> {
+    if let Some(all_links) = read_artifact::<Vec<String>>(seen_links_path) {
+        return Ok(all_links);
+    }
+
+    Ok(read_url_list(seen_links_path)?.into_iter().map(trim_trailing_slash).collect())
+}
+
+impl CrawlState {
+    fn start_expected_urls(&self, expected_urls: ExpectedUrls) {
+        self.expected_urls
+            .lock()
+            .map(|mut current_expected_urls| *current_expected_urls = Some(expected_urls))
+            .expect("Failed to record expected URLs.");
+    }
+
+    fn record_expected_url_fetched(&self, link: &str) {
+        self.expected_urls
+            .lock()
+            .map(|mut expected_urls| expected_urls.as_mut().map(|expected_urls| expected_urls.record_fetched(link)))
+            .expect("Failed to record fetched link.");
+    }
+
+    fn record_politeness_request(&self, host: &str, sent_at: Instant) {
+        self.politeness
+            .lock()
+            .map(|mut politeness| politeness.record_request(host, sent_at))
+            .expect("Failed to record request.");
+    }
+
+    fn record_politeness_status(&self, host: &str, status: u16) {
+        self.politeness
+            .lock()
+            .map(|mut politeness| politeness.record_status(host, status))
+            .expect("Failed to record response status.");
+    }
+
+    fn record_politeness_crawl_delay(&self, host: &str, crawl_delay: Duration) {
+        self.politeness
+            .lock()
+            .map(|mut politeness| politeness.record_crawl_delay(host, crawl_delay))
+            .expect("Failed to record crawl delay.");
+    }
+
+    fn detect_session_params(&self, link: &str, html: &str) {
+        let detected_params = self
+            .session_params
+            .lock()
+            .map(|mut session_params| session_params.observe(link, html))
+            .expect("Failed to check for session parameters.");
+
+        for detected_param in detected_params {
+            eprintln!("Treating \"{}\" as a session ID and dropping it from links", detected_param);
+        }
+    }
+
+    fn set_url_normalization(&self, url_normalization: UrlNormalization) {
+        self.url_normalization
+            .lock()
+            .map(|mut current_normalization| *current_normalization = url_normalization)
+            .expect("Failed to set URL normalization.");
+    }
+
+    fn set_url_filter(&self, url_filter: UrlFilter) {
+        self.url_filter
+            .lock()
+            .map(|mut current_filter| *current_filter = url_filter)
+            .expect("Failed to set URL filter.");
+    }
+
+    fn is_in_scope(&self, url: &Url, page_url: &Url) -> bool {
+        self.url_filter.lock().map(|url_filter| url_filter.in_scope(url, page_url)).expect("Failed to check URL scope.")
+    }
+
+    fn is_allowed_by_filter(&self, url: &Url) -> bool {
+        self.url_filter.lock().map(|url_filter| url_filter.allows(url)).expect("Failed to check URL filter.")
+    }
+
+    fn is_blocked_host(&self, url: &Url) -> bool {
+        self.url_filter.lock().map(|url_filter| url_filter.is_blocked(url)).expect("Failed to check blocked hosts.")
+    }
+
+    fn set_host_guard(&self, host_guard: HostGuard) {
+        self.host_guard
+            .lock()
+            .map(|mut current_guard| *current_guard = host_guard)
+            .expect("Failed to set host guard.");
+    }
+
+    // Never asks the confirmation itself, so the guard isn't held while it's answered.
+    fn host_admission(&self, url: &Url) -> HostAdmission {
+        let Some(host) = url.host_str() else {
+            return HostAdmission::Admitted;
+        };
+
+        self.host_guard.lock().map(|mut host_guard| host_guard.admit(host)).expect("Failed to check host limit.")
+    }
+
+    fn host_limit_confirmation(&self) -> Option<HostLimitConfirmation> {
+        self.host_guard.lock().map(|host_guard| host_guard.confirmation()).expect("Failed to check host limit.")
+    }
+
+    fn record_host_confirmation(&self, host_limit_reached: &HostLimitReached, confirmed: bool) {
+        self.host_guard
+            .lock()
+            .map(|mut host_guard| host_guard.record_confirmation(host_limit_reached, confirmed))
+            .expect("Failed to record host limit confirmation.");
+    }
+
+    fn ignores_nofollow(&self) -> bool {
+        self.url_filter.lock().map(|url_filter| url_filter.ignore_nofollow).expect("Failed to check URL filter.")
+    }
+
+    fn skips_protocol_relative(&self) -> bool {
+        self.url_filter.lock().map(|url_filter| url_filter.skip_protocol_relative).expect("Failed to check URL filter.")
+    }
+
+    fn normalize_url(&self, url: &mut Url) {
+        self.url_normalization
+            .lock()
+            .map(|url_normalization| url_normalization.normalize(url))
+            .expect("Failed to normalize URL.");
+        self.apply_served_scheme(url);
+    }
+
+    fn record_served_scheme(&self, url: &Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        self.served_schemes
+            .lock()
+            .map(|mut served_schemes| served_schemes.insert(host.to_string(), url.scheme().to_string()))
+            .expect("Failed to record served scheme.");
+    }
+
+    // A link on the other scheme is the same page, so it's rewritten before it can reach the visited set. Hosts that
+    // haven't answered yet are left as written - if they redirect, the redirect is caught as an alias of the page.
+    fn apply_served_scheme(&self, url: &mut Url) {
+        let is_strict =
+            self.url_filter.lock().map(|url_filter| url_filter.strict_scheme).expect("Failed to read URL filter.");
+        if is_strict || !matches!(url.scheme(), "http" | "https") {
+            return;
+        }
+
+        let served_scheme = self
+            .served_schemes
+            .lock()
+            .map(|served_schemes| url.host_str().and_then(|host| served_schemes.get(host).cloned()))
+            .expect("Failed to read served schemes.");
+
+        if let Some(served_scheme) = served_scheme.filter(|served_scheme| served_scheme != url.scheme()) {
+            // Only fails between special and non-special schemes, which both of these are.
+            let _ = url.set_scheme(&served_scheme);
+        }
+    }
+
+    // Links that don't parse are left as they are.
+    fn normalize_link(&self, link: &str) -> String {
+        let Ok(mut url) = Url::parse(link) else {
+            return link.to_string();
+        };
+
+        self.normalize_url(&mut url);
+        url.to_string()
+    }
+
+    fn strip_session_params(&self, link: &str) -> Option<String> {
+        self.session_params.lock().ok().and_then(|session_params| session_params.strip(link))
+    }
+
+    // Returns None if the link without its session parameters has already been seen.
+    fn collapse_session_params(&self, link: Arc<str>) -> Option<Arc<str>> {
+        match self.strip_session_params(&link) {
+            Some(stripped_link) => self.add_to_visited_links([trim_trailing_slash(stripped_link).as_str()]).pop(),
+            None => Some(link),
+        }
+    }
+
+    fn start_sitemap_read(&self, sitemap_link: &str) -> bool {
+        self.sitemaps
+            .lock()
+            .map(|mut sitemaps| sitemaps.start_read(sitemap_link))
+            .expect("Failed to start sitemap read.")
+    }
+
+    fn record_sitemap(&self, sitemap_link: String, kind: SitemapKind, entries: usize, seeded_links: &[String]) {
+        self.sitemaps
+            .lock()
+            .map(|mut sitemaps| {
+                sitemaps.record_sitemap(sitemap_link, kind, entries);
+                sitemaps.seeded_links.extend(seeded_links.iter().cloned());
+            })
+            .expect("Failed to record sitemap.");
+    }
+
+    // Pages are reached by following links if the link graph has a route to them from a seed.
+    fn record_sitemap_coverage(&self) {
+        let click_depth_report = self.link_graph
+            .lock()
+            .map(|link_graph| link_graph.build_click_depth_report())
+            .expect("Failed to read link graph.");
+        let linked_pages = click_depth_report.depth_by_page.keys().map(String::as_str);
+        let coverage = self.sitemaps
+            .lock()
+            .map(|sitemaps| sitemap_coverage(&sitemaps.seeded_links, linked_pages))
+            .expect("Failed to read sitemaps.");
+
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.sitemap_coverage = coverage)
+            .expect("Failed to record sitemap coverage.");
+    }
+
+    fn record_sitemap_failure(&self, sitemap_link: String, reason: String) {
+        self.sitemaps
+            .lock()
+            .map(|mut sitemaps| sitemaps.record_failure(sitemap_link, reason))
+            .expect("Failed to record sitemap.");
+    }
+
+    fn set_robots_rules(&self, origin: String, robots_rules: Vec<RobotsRule>) {
+        self.robots_rules
+            .lock()
+            .map(|mut robots_rules_by_origin| robots_rules_by_origin.insert(origin, robots_rules))
+            .expect("Failed to set robots rules.");
+    }
+
+    // A host whose robots.txt wasn't read has nothing disallowed.
+    fn is_allowed_by_robots(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        self.robots_rules
+            .lock()
+            .map(|robots_rules_by_origin| {
+                let origin = url.origin().ascii_serialization();
+                robots_rules_by_origin.get(&origin).is_none_or(|robots_rules| is_allowed(robots_rules, &path))
+            })
+            .unwrap_or(true)
+    }
+
+    fn add_to_robots_blocked_links(&self, link: String, referrer: String) {
+        self.robots_blocked_links
+            .lock()
+            .map(|mut blocked_links| blocked_links.entry(link).or_default().insert(referrer))
+            .expect("Failed to add value to map.");
+    }
+
+    fn add_to_skipped_links(&self, link: String, reason: SkipReason) {
+        self.skipped_links
+            .lock()
+            .map(|mut skipped_links| skipped_links.insert(link, reason))
+            .expect("Failed to add value to map.");
+    }
+
+    fn record_seed(&self, seed_link: &str) {
+        self.link_graph
+            .lock()
+            .map(|mut link_graph| link_graph.record_seed(seed_link))
+            .expect("Failed to record seed.");
+    }
+
+    fn record_link_graph(&self, link: &str, internal_links: &BTreeSet<String>) {
+        self.link_graph
+            .lock()
+            .map(|mut link_graph| link_graph.record_links(link, internal_links.iter().map(String::as_str)))
+            .expect("Failed to record links.");
+    }
+
+    fn record_pagination(&self, link: &str, pagination: &PaginationLinks) {
+        let mut next_page_by_page = self.next_page_by_page.lock().expect("Failed to record pagination.");
+
+        if let Some(next_page) = &pagination.next {
+            next_page_by_page.insert(link.to_string(), next_page.clone());
+        }
+
+        if let Some(prev_page) = &pagination.prev {
+            next_page_by_page.entry(prev_page.clone()).or_insert_with(|| link.to_string());
+        }
+    }
+
+    fn record_duplicate_signals(&self, link: &str, canonical: Option<String>, html: &str, language_tag: String) {
+        if let Some(canonical) = canonical {
+            self.record_canonical(link, canonical);
+        }
+
+        let content_hash = sha256_hex(html.as_bytes());
+        self.duplicate_signals
+            .lock()
+            .map(|mut signals| signals.record_content_hash(link.to_string(), content_hash, language_tag))
+            .expect("Failed to record duplicate signals.");
+    }
+
+    fn record_canonical(&self, link: &str, canonical: String) {
+        self.duplicate_signals
+            .lock()
+            .map(|mut duplicate_signals| duplicate_signals.record_canonical(link.to_string(), canonical))
+            .expect("Failed to record duplicate signals.");
+    }
+
+    fn record_grep_matches(&self, link: &str, grep_matches: Vec<GrepMatch>) {
+        if grep_matches.is_empty() {
+            return;
+        }
+
+        self.grep_matches
+            .lock()
+            .map(|mut matches_by_page| matches_by_page.insert(link.to_string(), grep_matches))
+            .expect("Failed to add value to map.");
+    }
+
+    fn record_resource_hints(&self, link: &str, resource_hints: Vec<ResourceHint>) {
+        self.resource_hints
+            .lock()
+            .map(|mut report| report.record_hints(link.to_string(), resource_hints))
+            .expect("Failed to record resource hints.");
+    }
+
+    fn record_directory_listing(&self, link: &str, listing_server: ListingServer) {
+        self.directory_listings
+            .lock()
+            .map(|mut directory_listings| directory_listings.insert(link.to_string(), listing_server))
+            .expect("Failed to record directory listing.");
+    }
+
+    fn record_page_language(
+        &self,
+        link: &str,
+        page_language: PageLanguage,
+        hreflangs: BTreeMap<String, String>,
+        page_text: &str,
+    ) {
+        self.language_signals
+            .lock()
+            .map(|mut signals| signals.record_page(link.to_string(), page_language, hreflangs, page_text))
+            .expect("Failed to record page language.");
+    }
+
+    fn record_not_found_fingerprint(&self, origin: String, fingerprint: NotFoundFingerprint) {
+        self.soft_not_found
+            .lock()
+            .map(|mut report| report.record_fingerprint(origin, fingerprint))
+            .expect("Failed to record not found fingerprint.");
+    }
+
+    fn check_soft_not_found(&self, link: &str, page_text: &str) {
+        let Ok(page_url) = Url::parse(link) else {
+            return;
+        };
+
+        self.soft_not_found
+            .lock()
+            .map(|mut report| report.check_page(&page_url.origin().ascii_serialization(), link, page_text))
+            .expect("Failed to check for soft 404.");
+    }
+
+    fn record_sent_request(&self, link: &str, sent_request: SentRequest) {
+        self.sent_requests
+            .lock()
+            .map(|mut sent_requests| sent_requests.entry(link.to_string()).or_default().push(sent_request))
+            .expect("Failed to record sent request.");
+    }
+
+    fn record_noindex(&self, link: &str) {
+        self.noindex_pages
+            .lock()
+            .map(|mut noindex_pages| noindex_pages.insert(link.to_string()))
+            .expect("Failed to record noindex page.");
+    }
+
+    fn record_assets(&self, link: &str, assets: BTreeMap<String, AssetKind>) {
+        if assets.is_empty() {
+            return;
+        }
+
+        self.assets_by_page
+            .lock()
+            .map(|mut assets_by_page| assets_by_page.insert(link.to_string(), assets))
+            .expect("Failed to record assets.");
+    }
+
+    fn record_forms(&self, link: &str, forms: Vec<Form>) {
+        if forms.is_empty() {
+            return;
+        }
+
+        self.forms_by_page
+            .lock()
+            .map(|mut forms_by_page| forms_by_page.insert(link.to_string(), forms))
+            .expect("Failed to record forms.");
+    }
+
+    fn record_media_alternates(&self, link: &str, media_alternates: Vec<MediaAlternate>) {
+        self.media_alternates
+            .lock()
+            .map(|mut report| report.record_alternates(link.to_string(), media_alternates))
+            .expect("Failed to record media alternates.");
+    }
+
+    fn start_alternate_target_check(&self, alternate_target: &str) -> bool {
+        self.media_alternates
+            .lock()
+            .map(|mut report| report.start_target_check(alternate_target))
+            .expect("Failed to record media alternates.")
+    }
+
+    fn record_alternate_target_status(&self, alternate_target: &str, status: String) {
+        self.media_alternates
+            .lock()
+            .map(|mut report| report.record_target_status(alternate_target.to_string(), status))
+            .expect("Failed to record media alternates.");
+    }
+
+    fn start_hint_target_check(&self, hint_target: &str) -> bool {
+        self.resource_hints
+            .lock()
+            .map(|mut report| report.start_target_check(hint_target))
+            .expect("Failed to record resource hints.")
+    }
+
+    fn record_hint_target_status(&self, hint_target: &str, status: String) {
+        self.resource_hints
+            .lock()
+            .map(|mut report| report.record_target_status(hint_target.to_string(), status))
+            .expect("Failed to record resource hints.");
+    }
+
+    fn record_redirect(&self, link: &str, final_link: String) {
+        self.duplicate_signals
+            .lock()
+            .map(|mut duplicate_signals| duplicate_signals.record_redirect(link.to_string(), final_link))
+            .expect("Failed to record redirect.");
+    }
+
+    fn add_to_redirect_aliases(&self, link: String, final_link: String) {
+        self.redirect_aliases
+            .lock()
+            .map(|mut redirect_aliases| redirect_aliases.insert(link, final_link))
+            .expect("Failed to record redirect alias.");
+    }
+
+    fn add_to_redirect_chains(&self, link: String, redirect_chain: RedirectChain) {
+        self.redirect_chains
+            .lock()
+            .map(|mut redirect_chains| redirect_chains.insert(link, redirect_chain))
+            .expect("Failed to record redirect chain.");
+    }
+
+    // Why the link's redirects weren't followed to the end, and the redirect they stopped at.
+    fn stopped_redirect(&self, link: &str) -> Option<(RedirectStop, String)> {
+        self.redirect_chains
+            .lock()
+            .map(|redirect_chains| {
+                let redirect_chain = redirect_chains.get(link)?;
+                Some((redirect_chain.stopped?, redirect_chain.target.clone()))
+            })
+            .expect("Failed to read redirect chains.")
+    }
+
+    fn add_to_redirect_audits(&self, link: String, redirect_audit: RedirectAudit) {
+        self.redirect_audits
+            .lock()
+            .map(|mut redirect_audits| redirect_audits.insert(link, redirect_audit))
+            .expect("Failed to record redirect audit.");
+    }
+
+    // Returns the links that hadn't been seen before, each shared between the visited set and the frontier. The lock is
+    // taken once per page rather than once per link.
+    fn add_to_visited_links<'a>(&self, links: impl IntoIterator<Item = &'a str>) -> Vec<Arc<str>> {
+        self.visited_links
+            .lock()
+            .map(|mut visited_links| links.into_iter().filter_map(|link| visited_links.insert(link)).collect())
+            .unwrap_or_default()
+    }
+
+    fn record_resource_size(&self, link: &str, size: u64) {
+        self.resource_sizes
+            .lock()
+            .map(|mut resource_sizes| resource_sizes.insert(link.to_string(), size))
+            .expect("Failed to record resource size.");
+    }
+
+    fn add_to_etags(&self, link: String, etag: String) {
+        self.etags_by_link
+            .lock()
+            .map(|mut etag_map| etag_map.insert(link, etag))
+            .expect("Failed to add value to map.");
+    }
+
+    // Records the file in the manifest and hands back its contents.
+    fn add_to_manifest(&self, archived_page: ArchivedPage) -> String {
+        self.archive_manifest
+            .lock()
+            .map(|mut manifest| manifest.insert(archived_page.file_path, archived_page.manifest_entry))
+            .expect("Failed to add value to map.");
+
+        archived_page.html
+    }
+
+    // Non-HTML responses are already in skipped.json, so only actual failures are kept.
+    fn add_to_failed_links(&self, link: String, crawl_error: CrawlError) {
+        if !crawl_error.is_failure() {
+            return;
+        }
+
+        self.failed_links
+            .lock()
+            .map(|mut failed_links| failed_links.insert(link, crawl_error))
+            .expect("Failed to add value to map.");
+    }
+
+    // Seeds the visited set and ETags with a previous run's results, so its outputs are rewritten as a superset.
+    fn merge_previous_run(&self, all_links_path: &Path, etags_path: &Path, compression: OutputCompression) {
+        if let Some(previous_links) = read_artifact::<HashSet<String>>(&compression.output_filename(all_links_path)) {
+            self.add_to_visited_links(previous_links.iter().map(String::as_str));
+        }
+
+        if let Some(previous_etags) = load_previous_etags(&compression.output_filename(etags_path)) {
+            previous_etags.into_iter().for_each(|(link, etag)| self.add_to_etags(link, etag));
+        }
+    }
+
+    fn record_response(&self, link: &str, status: u16, elapsed: Duration) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.record_response(link, status, elapsed))
+            .expect("Failed to record response.");
+    }
+
+    fn record_request_failure(&self, link: &str) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.record_request_failure(link))
+            .expect("Failed to record error.");
+    }
+
+    fn record_requeued_request(&self) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.requeued_requests += 1)
+            .expect("Failed to record requeue.");
+    }
+
+    fn record_page_processed(&self, link: &str) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.record_page_processed(link))
+            .expect("Failed to record page.");
+    }
+
+    fn record_bytes(&self, link: &str, bytes: u64) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.record_bytes(link, bytes))
+            .expect("Failed to record bytes.");
+    }
+
+    fn record_crawl_duration(&self, crawl_duration: Duration) {
+        self.crawl_stats
+            .lock()
+            .map(|mut stats| stats.crawl_duration = crawl_duration)
+            .expect("Failed to record crawl duration.");
+    }
+
+    fn start_crawl_metadata(&self, seed_urls: &[String], settings: CrawlSettings, labels: BTreeMap<String, String>) {
+        self.crawl_metadata
+            .lock()
+            .map(|mut metadata| *metadata = Some(CrawlMetadata::start(seed_urls, settings, labels)))
+            .expect("Failed to record crawl metadata.");
+    }
+
+    fn finish_crawl_metadata(&self) {
+        self.crawl_metadata
+            .lock()
+            .map(|mut metadata| metadata.as_mut().map(CrawlMetadata::finish))
+            .expect("Failed to record crawl metadata.");
+    }
+
+    fn current_crawl_metadata(&self) -> Option<CrawlMetadata> {
+        self.crawl_metadata
+            .lock()
+            .map(|metadata| metadata.clone())
+            .expect("Failed to read crawl metadata.")
+    }
+
+    fn print_all_links(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        let all_links = self.visited_links
+            .lock()
+            .map(|visited_links| visited_links.all())
+            .expect("Failed to print all links.");
+
+        match all_links {
+            Ok(all_links) => self.print_artifact(&all_links, output_path, print_to_file, compression),
+            Err(err) => eprintln!("Failed to read spilled links back: {:?}", err),
+        }
+    }
+
+    fn print_url_inventory(
+        &self,
+        url_list_path: &Path,
+        url_markdown_path: &Path,
+        print_to_file: bool,
+        compression: OutputCompression,
+    ) {
+        let all_links = self.visited_links
+            .lock()
+            .map(|visited_links| visited_links.all())
+            .expect("Failed to print URL inventory.");
+
+        match all_links {
+            Ok(all_links) => {
+                print_text(&render_url_list(&all_links), url_list_path, print_to_file, compression);
+                print_text(&render_url_markdown(&all_links), url_markdown_path, print_to_file, compression);
+            }
+            Err(err) => eprintln!("Failed to read spilled links back: {:?}", err),
+        }
+    }
+
+    fn print_etags(
+        &self,
+        previous_etags: Option<&BTreeMap<String, String>>,
+        output_path: &Path,
+        print_to_file: bool,
+        compression: OutputCompression,
+    ) {
+        self.etags_by_link
+            .lock()
+            .map(|etag_map| {
+                let etag_report = build_etag_report(etag_map.clone(), previous_etags);
+                self.print_artifact(&etag_report, output_path, print_to_file, compression)
+            })
+            .expect("Failed to print ETags.");
+    }
+
+    fn print_errors(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.failed_links
+            .lock()
+            .map(|failed_links| self.print_artifact(&*failed_links, output_path, print_to_file, compression))
+            .expect("Failed to print errors.");
+    }
+
+    fn print_manifest(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.archive_manifest
+            .lock()
+            .map(|manifest| self.print_artifact(&*manifest, output_path, print_to_file, compression))
+            .expect("Failed to print manifest.");
+    }
+
+    fn print_robots_blocked(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.robots_blocked_links
+            .lock()
+            .map(|blocked_links| self.print_artifact(&*blocked_links, output_path, print_to_file, compression))
+            .expect("Failed to print robots blocked links.");
+    }
+
+    fn print_skipped(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.skipped_links
+            .lock()
+            .map(|skipped_links| self.print_artifact(&*skipped_links, output_path, print_to_file, compression))
+            .expect("Failed to print skipped links.");
+    }
+
+    // Each paginated series in reading order.
+    fn print_pagination(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.next_page_by_page
+            .lock()
+            .map(|next_page_by_page| build_series(&next_page_by_page))
+            .map(|series| self.print_artifact(&series, output_path, print_to_file, compression))
+            .expect("Failed to print pagination.");
+    }
+
+    fn print_duplicates(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.duplicate_signals
+            .lock()
+            .map(|duplicate_signals| duplicate_signals.build_duplicate_groups())
+            .map(|groups| self.print_artifact(&groups, output_path, print_to_file, compression))
+            .expect("Failed to print duplicates.");
+    }
+
+    fn print_grep_matches(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.grep_matches
+            .lock()
+            .map(|matches_by_page| self.print_artifact(&*matches_by_page, output_path, print_to_file, compression))
+            .expect("Failed to print grep matches.");
+    }
+
+    fn print_resource_hints(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.resource_hints
+            .lock()
+            .map(|report| self.print_artifact(&*report, output_path, print_to_file, compression))
+            .expect("Failed to print resource hints.");
+    }
+
+    fn print_forms(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.forms_by_page
+            .lock()
+            .map(|forms_by_page| self.print_artifact(&*forms_by_page, output_path, print_to_file, compression))
+            .expect("Failed to print forms.");
+    }
+
+    fn print_noindex_pages(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.noindex_pages
+            .lock()
+            .map(|noindex_pages| self.print_artifact(&*noindex_pages, output_path, print_to_file, compression))
+            .expect("Failed to print noindex pages.");
+    }
+
+    fn print_assets_by_page(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.assets_by_page
+            .lock()
+            .map(|assets_by_page| self.print_artifact(&*assets_by_page, output_path, print_to_file, compression))
+            .expect("Failed to print assets.");
+    }
+
+    // Auto-index pages found during the crawl, with the server that appears to have generated each one.
+    fn print_directory_listings(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.directory_listings
+            .lock()
+            .map(|listings| self.print_artifact(&*listings, output_path, print_to_file, compression))
+            .expect("Failed to print directory listings.");
+    }
+
+    // Each page's declared language, and the pages whose hreflang annotations say otherwise.
+    fn print_languages(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.language_signals
+            .lock()
+            .map(|language_signals| {
+                let language_report = language_signals.build_report();
+                self.print_artifact(&language_report, output_path, print_to_file, compression)
+            })
+            .expect("Failed to print languages.");
+    }
+
+    // Each site's not-found fingerprint, and the pages that came back successfully but looked just like it.
+    fn print_soft_404s(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.soft_not_found
+            .lock()
+            .map(|report| self.print_artifact(&*report, output_path, print_to_file, compression))
+            .expect("Failed to print soft 404s.");
+    }
+
+    fn print_resource_sizes(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.resource_sizes
+            .lock()
+            .map(|resource_sizes| self.print_artifact(&*resource_sizes, output_path, print_to_file, compression))
+            .expect("Failed to print resource sizes.");
+    }
+
+    fn print_request_headers(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.sent_requests
+            .lock()
+            .map(|sent_requests| self.print_artifact(&*sent_requests, output_path, print_to_file, compression))
+            .expect("Failed to print request headers.");
+    }
+
+    fn print_media_alternates(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.media_alternates
+            .lock()
+            .map(|report| self.print_artifact(&*report, output_path, print_to_file, compression))
+            .expect("Failed to print media alternates.");
+    }
+
+    // Exactly what was read from each host's robots.txt during the run.
+    fn print_robots(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.robots_reports
+            .lock()
+            .map(|robots_reports| self.print_artifact(&*robots_reports, output_path, print_to_file, compression))
+            .expect("Failed to print robots report.");
+    }
+
+    // Per host, so site owners asking whether the crawl was well-behaved can be shown how it treated their host.
+    fn print_politeness(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.politeness
+            .lock()
+            .map(|politeness| self.print_artifact(&politeness.report(), output_path, print_to_file, compression))
+            .expect("Failed to print politeness report.");
+    }
+
+    fn print_session_params(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.session_params
+            .lock()
+            .map(|session_params| {
+                self.print_artifact(session_params.session_params(), output_path, print_to_file, compression)
+            })
+            .expect("Failed to print session parameters.");
+    }
+
+    fn print_expected_urls(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        let Some(expected_urls_report) = self.expected_urls_report() else {
+            return;
+        };
+
+        self.print_artifact(&expected_urls_report, output_path, print_to_file, compression);
+    }
+
+    fn expected_urls_report(&self) -> Option<ExpectedUrlsReport> {
+        let discovered_links = self.visited_links
+            .lock()
+            .map(|visited_links| visited_links.all())
+            .expect("Failed to read visited links.");
+        let discovered_links = discovered_links
+            .map_err(|err| eprintln!("Failed to read spilled links back: {:?}", err))
+            .unwrap_or_default();
+        let failed_links = self.failed_links.lock().expect("Failed to read failed links.");
+
+        self.expected_urls
+            .lock()
+            .map(|expected_urls| {
+                expected_urls.as_ref().map(|expected_urls| expected_urls.build_report(&discovered_links, &failed_links))
+            })
+            .expect("Failed to read expected URLs.")
+    }
+
+    fn print_sitemaps(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.sitemaps
+            .lock()
+            .map(|sitemaps| self.print_artifact(&*sitemaps, output_path, print_to_file, compression))
+            .expect("Failed to print sitemaps.");
+    }
+
+    // Internal links that redirect off the domain or from HTTPS down to HTTP, with where each one ends up.
+    fn print_redirect_audit(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.redirect_audits
+            .lock()
+            .map(|redirect_audits| self.print_artifact(&*redirect_audits, output_path, print_to_file, compression))
+            .expect("Failed to print redirect audit.");
+    }
+
+    fn print_redirect_aliases(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.redirect_aliases
+            .lock()
+            .map(|redirect_aliases| self.print_artifact(&*redirect_aliases, output_path, print_to_file, compression))
+            .expect("Failed to print redirect aliases.");
+    }
+
+    // Each redirected link, with every hop's status code and where the chain ended up.
+    fn print_redirect_map(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        self.redirect_chains
+            .lock()
+            .map(|redirect_chains| self.print_artifact(&*redirect_chains, output_path, print_to_file, compression))
+            .expect("Failed to print redirect map.");
+    }
+
+    fn print_click_depths(&self, output_path: &Path, print_to_file: bool, compression: OutputCompression) {
+        let click_depth_report = self.link_graph
+            .lock()
+            .map(|link_graph| link_graph.build_click_depth_report())
+            .expect("Failed to print click depths.");
+
+        self.print_artifact(&click_depth_report, output_path, print_to_file, compression);
+    }
+
+    fn print_summary(&self) {
+        self.crawl_stats
+            .lock()
+            .map(|stats| print!("{}", render_summary(&stats, should_use_color())))
+            .expect("Failed to print summary.");
+    }
+
+    fn crawl_report(&self, elapsed: Duration, cancelled: bool) -> CrawlReport {
+        let links_discovered = self.visited_links
+            .lock()
+            .map(|visited_links| visited_links.len())
+            .expect("Failed to count links.");
+        let errors = self.failed_links.lock().map(|failed_links| failed_links.clone()).expect("Failed to read errors.");
+
+        self.crawl_stats
+            .lock()
+            .map(|stats| build_crawl_report(&stats, links_discovered, errors, elapsed, cancelled))
+            .expect("Failed to build crawl report.")
+    }
+
+    fn write_summary_json(&self, summary_path: &Path) {
+        let links_discovered = self.visited_links
+            .lock()
+            .map(|visited_links| visited_links.len())
+            .expect("Failed to count links.");
+
+        self.crawl_stats
+            .lock()
+            .map(|stats| {
+                let summary_report = build_summary_report(&stats, links_discovered);
+                self.print_artifact(&summary_report, summary_path, true, OutputCompression::None)
+            })
+            .expect("Failed to write summary.");
+    }
+
+    fn print_artifact<T: Serialize + ?Sized>(
+        &self,
+        data: &T,
+        path: &Path,
+        print_to_file: bool,
+        compression: OutputCompression,
+    ) {
+        let artifact = Artifact { metadata: self.current_crawl_metadata(), data };
+        print_json(&artifact, path, print_to_file, compression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robots::RuleKind;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn test_crawlers_do_not_share_state() {
+        let first_crawler = WebCrawler::new();
+        let second_crawler = WebCrawler::new();
+
+        first_crawler.state.add_to_visited_links(["https://example.com/a"]);
+        let crawl_error = CrawlError::HttpStatus { status: 500 };
+        first_crawler.state.add_to_failed_links(String::from("https://example.com/b"), crawl_error);
+
+        assert_eq!(1, first_crawler.all_links().len());
+        assert!(second_crawler.all_links().is_empty());
+        assert!(second_crawler.failed_links().is_empty());
+    }
+
+    fn archive_crawler() -> WebCrawler {
+        let crawler = WebCrawler::builder().with_archive(PathBuf::from("resources/archive")).build().unwrap();
+        crawler.with_links_by_page_to_file(false)
+    }
+
+    #[tokio::test]
+    async fn test_pages_reachable_from_several_seeds_are_fetched_once() {
+        let crawler = archive_crawler();
+        let seed_urls = ["https://example.com/", "https://example.com/goodLink", "https://example.com/"]
+            .map(|seed_link| Url::parse(seed_link).unwrap());
+
+        let crawl_report = crawler.scrape_sites(seed_urls.to_vec()).await.unwrap();
+
+        // The home page and goodLink link to each other, and the home page is given twice.
+        assert_eq!(2, crawl_report.pages_visited);
+        assert!(crawler.all_links().contains("https://example.com"));
+        assert!(crawler.scrape_sites(Vec::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_page_results_are_streamed_as_pages_are_crawled() {
+        let crawler = archive_crawler();
+
+        let page_results: Vec<PageResult> = crawler.crawl_stream(String::from("https://example.com/")).collect().await;
+
+        let crawled_pages: BTreeSet<&str> = page_results
+            .iter()
+            .filter_map(|page_result| match page_result {
+                PageResult::Crawled { page, .. } => Some(page.as_str()),
+                PageResult::Failed { .. } => None,
+            })
+            .collect();
+        assert_eq!(BTreeSet::from(["https://example.com/", "https://example.com/goodLink"]), crawled_pages);
+    }
+
+    // Answers each request with the response for its path, closing the connection after each one.
+    async fn serve_responses(listener: TcpListener, responses: HashMap<&'static str, String>) {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 1024];
+            let read = stream.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let response = responses.get(path).cloned().unwrap_or_else(|| String::from("HTTP/1.1 200 OK\r\n"));
+            let response = format!("{}Content-Length: 0\r\nConnection: close\r\n\r\n", response);
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_chains_are_followed_and_recorded() {
+        let redirect = |status: &str, location: &str| format!("HTTP/1.1 {}\r\nLocation: {}\r\n", status, location);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let site_link = format!("http://{}", listener.local_addr().unwrap());
+        let responses = HashMap::from([
+            ("/a", redirect("301 Moved Permanently", "/b")),
+            ("/b", redirect("302 Found", "/c")),
+            ("/loop", redirect("302 Found", "/loop-back")),
+            ("/loop-back", redirect("302 Found", "/loop")),
+        ]);
+        tokio::spawn(serve_responses(listener, responses));
+
+        let state = CrawlState::default();
+        let client = NetworkClient::new(default_client(), &CrawlerOptions::default());
+        let response = client.send(Method::GET, &format!("{}/a", site_link), &state).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let loop_link = format!("{}/loop", site_link);
+        let response = client.send(Method::GET, &loop_link, &state).await.unwrap();
+        assert_eq!(StatusCode::FOUND, response.status());
+        assert_eq!(Some((RedirectStop::Loop, loop_link.clone())), state.stopped_redirect(&loop_link));
+
+        let redirect_chains = state.redirect_chains.lock().unwrap();
+        let redirect_chain = &redirect_chains[&format!("{}/a", site_link)];
+        assert_eq!(format!("{}/c", site_link), redirect_chain.target);
+        assert_eq!(vec![301, 302], redirect_chain.hops.iter().map(|hop| hop.status).collect::<Vec<_>>());
+        assert_eq!(None, redirect_chain.stopped);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_seeds_fail_the_preflight() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let site_url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let responses = HashMap::from([("/down", String::from("HTTP/1.1 503 Service Unavailable\r\n"))]);
+        tokio::spawn(serve_responses(listener, responses));
+
+        let closed_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = Url::parse(&format!("http://{}/", closed_listener.local_addr().unwrap())).unwrap();
+        drop(closed_listener);
+
+        let state = CrawlState::default();
+        let source = PageSource::Network(NetworkClient::new(default_client(), &CrawlerOptions::default()));
+        let closed_failure = preflight_seeds(std::slice::from_ref(&closed_url), &source, &state).await.unwrap_err();
+        assert!(closed_failure.starts_with(&format!("Pre-flight check failed for {}: couldn't connect", closed_url)));
+
+        let down_url = site_url.join("/down").unwrap();
+        let down_failure = preflight_seeds(&[down_url], &source, &state).await.unwrap_err();
+        assert!(down_failure.ends_with("the seed answered with a server error (HTTP 503)"));
+
+        assert_eq!(Ok(()), preflight_seeds(&[closed_url.clone(), site_url.clone()], &source, &state).await);
+        let archive = PageSource::Archive(Arc::new(PathBuf::from("resources/archive")));
+        assert_eq!(Ok(()), preflight_seeds(&[closed_url], &archive, &state).await);
+    }
+
+    #[test]
+    fn test_blocked_hosts_and_hosts_past_the_limit_are_skipped() {
+        let state = CrawlState::default();
+        let blocked_hosts = vec![String::from("ads.example.com")];
+        state.set_url_filter(UrlFilter { include_subdomains: true, blocked_hosts, ..Default::default() });
+        state.set_host_guard(HostGuard::new(2, None, [String::from("www.example.com")]));
+        let skip_reason = |link| match validate_link(link, "https://www.example.com/", &state) {
+            ValidatedLink::Skipped(_, skip_reason) => Some(skip_reason),
+            _ => None,
+        };
+
+        assert_eq!(Some(SkipReason::BlockedHost), skip_reason("https://cdn.ads.example.com/banner.js"));
+        assert_eq!(None, skip_reason("https://blog.example.com/"));
+        assert_eq!(Some(SkipReason::HostLimit), skip_reason("https://shop.example.com/"));
+        assert_eq!(None, skip_reason("https://blog.example.com/first-post"));
+        assert_eq!(None, skip_reason("https://www.example.com/about"));
+    }
+
+    #[test]
+    fn test_see_other_redirects_turn_into_gets_without_credentials() {
+        let client = default_client();
+        let request = client.post("https://example.com/form").header(header::AUTHORIZATION, "Bearer abc").build();
+        let url = Url::parse("https://example.com/form").unwrap();
+
+        let same_host_target = Url::parse("https://example.com/done").unwrap();
+        let request = redirected_request(request.unwrap(), &url, same_host_target, StatusCode::SEE_OTHER);
+        assert_eq!(Method::GET, *request.method());
+        assert!(request.headers().contains_key(header::AUTHORIZATION));
+
+        let other_host_target = Url::parse("https://login.example.net/").unwrap();
+        let request = redirected_request(request, &url, other_host_target, StatusCode::TEMPORARY_REDIRECT);
+        assert!(!request.headers().contains_key(header::AUTHORIZATION));
+    }
+
+    #[tokio::test]
+    async fn test_request_permits_follow_the_concurrency_limit() {
+        let limited_options = CrawlerOptions { concurrency: Some(2), ..Default::default() };
+        let limited_client = NetworkClient::new(Client::new(), &limited_options);
+        let request_permits = limited_client.request_permits.clone().unwrap();
+
+        let first_permit = limited_client.request_permit().await;
+        let second_permit = limited_client.request_permit().await;
+        assert!(first_permit.len() == 1 && second_permit.len() == 1);
+        assert_eq!(0, request_permits.available_permits());
+
+        drop(first_permit);
+        assert_eq!(1, request_permits.available_permits());
+
+        let unlimited_client = NetworkClient::new(Client::new(), &CrawlerOptions::default());
+        assert!(unlimited_client.request_permit().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shared_request_budget_covers_every_crawl_given_it() {
+        let request_budget = Arc::new(Semaphore::new(1));
+        let shared_client = |client: NetworkClient| NetworkClient {
+            shared_request_permits: Some(request_budget.clone()),
+            ..client
+        };
+        let first_client = shared_client(NetworkClient::new(Client::new(), &CrawlerOptions::default()));
+        let second_client = shared_client(NetworkClient::new(Client::new(), &CrawlerOptions::default()));
+
+        let first_permit = first_client.request_permit().await;
+        assert_eq!(1, first_permit.len());
+        assert_eq!(0, request_budget.available_permits());
+        let second_permit = tokio::time::timeout(Duration::from_millis(50), second_client.request_permit()).await;
+        assert!(second_permit.is_err());
+
+        drop(first_permit);
+        assert_eq!(1, second_client.request_permit().await.len());
+    }
+
+    #[test]
+    fn test_markup_content_types_are_matched_by_media_type() {
+        let markup_content_types = [
+            "text/html",
+            "text/html; charset=utf-8",
+            "text/html;charset=ISO-8859-1",
+            "TEXT/HTML; Charset=\"UTF-8\"",
+            "application/xhtml+xml; charset=utf-8",
+            "application/xml; charset=utf-8",
+        ];
+        for content_type in markup_content_types {
+            assert!(is_markup_content_type(content_type), "{}", content_type);
+        }
+
+        for content_type in ["text/plain; charset=utf-8", "application/json", "image/png", ""] {
+            assert!(!is_markup_content_type(content_type), "{}", content_type);
+        }
+    }
+
+    #[test]
+    fn test_public_link_helpers_match_the_crawl() {
+        let html = r#"<a href="/boots/">Boots</a><a href="https://elsewhere.com">Elsewhere</a><a href="mailto:a@b">"#;
+        let page_url = "https://example.com/shoes";
+
+        assert_eq!(BTreeSet::from([String::from("https://example.com/boots")]), extract_links(html, page_url));
+        assert_eq!(Some(String::from("https://example.com/shoes?page=2")), resolve_link("?page=2", page_url));
+        assert_eq!(None, resolve_link("https://elsewhere.com", page_url));
+    }
+
+    #[test]
+    fn test_forms_are_extracted_with_default_values() {
+        let html_string = include_str!("../resources/testing_forms.html").to_string();
+
+        let page_url = String::from("https://example.com/shoes");
+
+        let forms = extract_forms(&Html::parse_document(&html_string), &page_url);
+
+        assert_eq!(2, forms.len());
+        assert_eq!("https://example.com/search", forms[0].action);
+        assert_eq!("get", forms[0].method);
+        assert_eq!(
+            FormField {
+                name: String::from("sort"),
+                field_type: String::from("select"),
+                value: Some(String::from("newest")),
+            },
+            forms[0].fields[2]
+        );
+        assert_eq!("https://example.com/shoes", forms[1].action);
+        assert_eq!("post", forms[1].method);
+        assert_eq!(Some(String::from("Hello")), forms[1].fields[2].value);
+    }
+
+    #[test]
+    fn test_namespaced_xhtml_form_fields() {
+        let html_string = include_str!("../resources/testing_forms_namespaced.xhtml").to_string();
+
+        let forms = extract_forms(&Html::parse_document(&html_string), "https://example.com/shoes");
+
+        let fields: Vec<(&str, Option<&str>)> =
+            forms[0].fields.iter().map(|field| (field.name.as_str(), field.value.as_deref())).collect();
+        assert_eq!(vec![("lang", Some("en")), ("sort", Some("newest")), ("comment", Some("Hello"))], fields);
+    }
+
+    #[test]
+    fn test_html_lang_dir_and_hreflangs_are_extracted() {
+        let html_string = include_str!("../resources/testing_hreflangs.html").to_string();
+        let parsed_html = Html::parse_document(&html_string);
+
+        let page_url = String::from("https://example.com/en/shoes");
+
+        assert_eq!(Some(String::from("en-GB")), extract_html_attribute(&parsed_html, LANG_ATTRIBUTE_NAME));
+        assert_eq!(Some(String::from("LTR")), extract_html_attribute(&parsed_html, DIR_ATTRIBUTE_NAME));
+        assert_eq!(None, extract_html_attribute(&parsed_html, "translate"));
+        assert_eq!(
+            BTreeMap::from([
+                (String::from("de"), String::from("https://example.com/de/shoes")),
+                (String::from("en-gb"), String::from("https://example.com/en/shoes")),
+                (String::from("x-default"), String::from("https://example.com/shoes")),
+            ]),
+            extract_hreflangs(&parsed_html, &page_url)
+        );
+    }
+
+    #[test]
+    fn test_only_media_alternates_are_extracted() {
+        let html_string = include_str!("../resources/testing_media_alternates.html").to_string();
+
+        let page_url = String::from("https://example.com/shoes");
+
+        let media_alternates = extract_media_alternates(&Html::parse_document(&html_string), &page_url);
+
+        assert_eq!(
+            vec![MediaAlternate {
+                href: String::from("https://m.example.com/shoes"),
+                media: String::from("only screen and (max-width: 640px)"),
+            }],
+            media_alternates
+        );
+    }
+
+    #[test]
+    fn test_noscript_links_are_extracted() {
+        let html_string = include_str!("../resources/testing_noscript_links.html").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert_eq!(
+            BTreeSet::from([
+                String::from("https://example.com/about"),
+                String::from("https://example.com/contact"),
+                String::from("https://example.com/products"),
+            ]),
+            internal_links
+        );
+    }
+
+    #[test]
+    fn test_valid_html_links_total() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert_eq!(4, internal_links.len());
+    }
+
+    #[test]
+    fn test_valid_html_links_page_relative_link() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let page_url = String::from("https://example.com/section/page");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &page_url, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/section/relativeUnsupportedLink"));
+    }
+
+    #[test]
+    fn test_query_only_link_resolves_against_page() {
+        let html_string = include_str!("../resources/testing_query_links.html").to_string();
+        let page_url = String::from("https://example.com/blog");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &page_url, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/blog?page=2"));
+    }
+
+    #[test]
+    fn test_relative_links_resolve_against_the_page_directory() {
+        let html_string = include_str!("../resources/testing_relative_links.html").to_string();
+        let page_url = String::from("https://example.com/docs/guide/page?view=full");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &page_url, &CrawlState::default());
+
+        let expected_links = [
+            "https://example.com/docs/sibling",
+            "https://example.com/top",
+            "https://example.com/beyond-root",
+            "https://example.com/docs/guide/child/grandchild",
+            "https://example.com/docs/guide/here",
+            "https://example.com/docs/intro",
+            "https://example.com/docs/guide/page?sort=asc",
+            "https://example.com/docs/guide/page?",
+            "https://example.com/docs/list?page=2",
+        ];
+        assert_eq!(BTreeSet::from(expected_links.map(String::from)), internal_links);
+    }
+
+    #[test]
+    fn test_dot_segments_resolve_the_same_as_the_page_they_point_at() {
+        assert_eq!(Some(String::from("https://example.com/a/c")), resolve_link("./b/../c", "https://example.com/a/"));
+        assert_eq!(Some(String::from("https://example.com/c")), resolve_link("../c", "https://example.com/a"));
+        assert_eq!(
+            Some(String::from("https://example.com/a?page=2")),
+            resolve_link("?page=2", "https://example.com/a")
+        );
+    }
+
+    #[test]
+    fn test_relative_links_resolve_against_the_base_href() {
+        let parsed_html = Html::parse_document(include_str!("../resources/testing_base_href.html"));
+        let page_url = String::from("https://example.com/blog/post");
+
+        let internal_links = generate_internal_links(&parsed_html, &page_url, &CrawlState::default());
+
+        let expected_links = [
+            "https://example.com/docs/v2/intro",
+            "https://example.com/docs/v1/intro",
+            "https://example.com/about",
+            "https://example.com/contact",
+        ];
+        assert_eq!(BTreeSet::from(expected_links.map(String::from)), internal_links);
+        assert_eq!(
+            Some(String::from("https://example.com/docs/v2/page-2")),
+            extract_pagination(&parsed_html, &page_url, &CrawlState::default()).next
+        );
+        assert_eq!(
+            Some(String::from("https://example.com/docs/v2/current")),
+            extract_canonical(&parsed_html, &page_url)
+        );
+
+        let forms = extract_forms(&parsed_html, &page_url);
+        let form_actions: Vec<&str> = forms.iter().map(|form| form.action.as_str()).collect();
+        assert_eq!(vec!["https://example.com/blog/post", "https://example.com/docs/v2/search"], form_actions);
+    }
+
+    #[test]
+    fn test_base_href_on_another_host_does_not_widen_the_scope() {
+        let html_string = r#"<base href="https://cdn.example.net/"><a href="asset">Asset</a>"#;
+        let state = CrawlState::default();
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(html_string), "https://example.com/blog/post", &state);
+
+        assert!(internal_links.is_empty());
+        assert!(state.skipped_links.lock().unwrap().contains_key("https://cdn.example.net/asset"));
+    }
+
+    #[test]
+    fn test_fragment_only_link_resolves_to_page() {
+        let html_string = include_str!("../resources/testing_query_links.html").to_string();
+        let page_url = String::from("https://example.com/blog?page=2");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &page_url, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/blog?page=2"));
+        assert!(!internal_links.iter().any(|link| link.contains('#')));
+    }
+
+    #[test]
+    fn test_valid_html_links_relative_link() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/goodLink"));
+    }
+
+    #[test]
+    fn test_valid_html_links_trimmed_link() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/goodLinkTrimMe"));
+    }
+
+    #[test]
+    fn test_valid_html_links_full_link_internal() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert!(internal_links.contains("https://example.com/goodInternalLink"));
+    }
+
+    #[test]
+    fn test_namespaced_xhtml_links() {
+        let html_string = include_str!("../resources/testing_links_namespaced.xhtml").to_string();
+        let root_domain = String::from("https://example.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert_eq!(2, internal_links.len());
+        assert!(internal_links.contains("https://example.com/defaultNamespaceLink"));
+        assert!(internal_links.contains("https://example.com/prefixedNamespaceLink"));
+    }
+
+    #[test]
+    fn test_url_list_skips_comments_and_invalid_lines() {
+        let links = read_url_list(Path::new("resources/testing_url_list.txt")).unwrap();
+
+        assert_eq!(vec!["https://example.com/", "https://example.com/about"], links);
+    }
+
+    #[test]
+    fn test_seen_links_match_the_visited_set() {
+        let seen_links = read_seen_links(Path::new("resources/testing_url_list.txt")).unwrap();
+
+        assert_eq!(vec!["https://example.com", "https://example.com/about"], seen_links);
+    }
+
+    #[test]
+    fn test_valid_html_links_full_link_external() {
+        let html_string = include_str!("../resources/testing_links.html").to_string();
+        let root_domain = String::from("https://facade.com");
+
+        let internal_links =
+            generate_internal_links(&Html::parse_document(&html_string), &root_domain, &CrawlState::default());
+
+        assert!(!internal_links.contains("https://example.com/goodInternalLink"));
+    }
+
+    #[test]
+    fn test_captured_headers_are_joined_and_missing_ones_skipped() {
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert("x-cache", "HIT".parse().unwrap());
+        response_headers.append("server-timing", "cdn;dur=1".parse().unwrap());
+        response_headers.append("server-timing", "origin;dur=20".parse().unwrap());
+        let header_names = vec![String::from("x-cache"), String::from("server-timing"), String::from("cf-ray")];
+
+        let captured = capture_headers(&response_headers, &header_names);
+
+        assert_eq!(2, captured.len());
+        assert_eq!("HIT", captured["x-cache"]);
+        assert_eq!("cdn;dur=1, origin;dur=20", captured["server-timing"]);
+    }
+
+    #[test]
+    fn test_retry_after_seconds() {
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(header::RETRY_AFTER, "5".parse().unwrap());
+
+        assert_eq!(Duration::from_secs(5), retry_after_delay(&response_headers));
+    }
+
+    #[test]
+    fn test_retry_after_date_in_the_past_retries_immediately() {
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+
+        assert_eq!(Duration::ZERO, retry_after_delay(&response_headers));
+    }
+
+    #[test]
+    fn test_retry_after_missing_or_too_long() {
+        let mut response_headers = HeaderMap::new();
+        assert_eq!(DEFAULT_RETRY_DELAY, retry_after_delay(&response_headers));
+
+        response_headers.insert(header::RETRY_AFTER, "86400".parse().unwrap());
+        assert_eq!(MAX_RETRY_DELAY, retry_after_delay(&response_headers));
+    }
+
+    #[test]
+    fn test_robots_blocked_links_are_recorded_with_referrer() {
+        let html_string = include_str!("../resources/testing_robots_blocked_links.html").to_string();
+        let page_url = String::from("https://blocked.example.com/home");
+        let state = CrawlState::default();
+        let members_rule = RobotsRule { kind: RuleKind::Disallow, path: String::from("/members") };
+        state.set_robots_rules(String::from("https://blocked.example.com"), vec![members_rule]);
+
+        let internal_links = generate_internal_links(&Html::parse_document(&html_string), &page_url, &state);
+
+        assert_eq!(1, internal_links.len());
+        let blocked_links = state.robots_blocked_links.lock().unwrap();
+        let referrers = &blocked_links["https://blocked.example.com/members/area"];
+        assert!(referrers.contains("https://blocked.example.com/home"));
+    }
+
+    #[test]
+    fn test_held_links_are_checked_against_the_prepared_hosts_robots() {
+        let state = CrawlState::default();
+        let members_rule = RobotsRule { kind: RuleKind::Disallow, path: String::from("/members") };
+        state.set_robots_rules(String::from("https://blog.example.com"), vec![members_rule]);
+        let referrer: Arc<str> = Arc::from("https://www.example.com/");
+        let held_links = ["https://blog.example.com/members/area", "https://blog.example.com/post"].map(|link| {
+            HeldLink { link: Arc::from(link), referrer: Some(referrer.clone()), queue: FrontierQueue::Strategy }
+        });
+
+        let mut frontier = Frontier::new(FrontierStrategy::default());
+        release_held_links(held_links.into(), &mut frontier, &state);
+
+        assert_eq!(Some(Arc::from("https://blog.example.com/post")), frontier.pop());
+        assert_eq!(None, frontier.pop());
+        let blocked_link = "https://blog.example.com/members/area";
+        assert_eq!(SkipReason::RobotsDisallowed, state.skipped_links.lock().unwrap()[blocked_link]);
+        assert!(state.robots_blocked_links.lock().unwrap()[blocked_link].contains(&*referrer));
+    }
+
+    #[test]
+    fn test_links_are_rewritten_to_the_scheme_their_host_serves() {
+        let html = r#"<a href="http://example.com/about">About</a><a href="http://other.example.com/">Other</a>"#;
+        let state = CrawlState::default();
+        state.record_served_scheme(&Url::parse("https://example.com/").unwrap());
+
+        let internal_links = generate_internal_links(&Html::parse_document(html), "https://example.com/", &state);
+
+        assert_eq!(BTreeSet::from([String::from("https://example.com/about")]), internal_links);
+    }
+
+    #[test]
+    fn test_protocol_relative_links_take_the_page_scheme() {
+        let html = r#"<a href="//example.com/about">About</a><a href="//cdn.example.net/app">App</a>"#;
+        let parsed_html = Html::parse_document(html);
+        let state = CrawlState::default();
+        state.set_url_filter(UrlFilter { strict_scheme: true, ..Default::default() });
+
+        let page_links = classify_page_links(&parsed_html, "http://example.com/", &state);
+        assert_eq!(LinkKind::InternalFollowed, page_links["http://example.com/about"]);
+        assert_eq!(LinkKind::External, page_links["http://cdn.example.net/app"]);
+
+        state.set_url_filter(UrlFilter { skip_protocol_relative: true, ..Default::default() });
+        let page_links = classify_page_links(&parsed_html, "https://example.com/", &state);
+        let protocol_relative = LinkKind::InternalSkipped { reason: SkipReason::ProtocolRelative };
+        assert_eq!(protocol_relative, page_links["https://example.com/about"]);
+        assert_eq!(LinkKind::External, page_links["https://cdn.example.net/app"]);
+    }
+
+    #[test]
+    fn test_nofollow_links_are_skipped_unless_ignored() {
+        let html = r#"<head><meta name="Robots" content="noindex"><meta name="googlebot" content="nofollow"></head>
+            <a href="/about">About</a><a href="/login" rel="NoFollow noopener">Log in</a>"#;
+        let parsed_html = Html::parse_document(html);
+        let state = CrawlState::default();
+
+        let page_links = classify_page_links(&parsed_html, "https://example.com/", &state);
+        assert_eq!(LinkKind::InternalFollowed, page_links["https://example.com/about"]);
+        assert_eq!(LinkKind::InternalSkipped { reason: SkipReason::Nofollow }, page_links["https://example.com/login"]);
+        assert_eq!(RobotsDirectives { noindex: true, nofollow: false }, extract_robots_directives(&parsed_html));
+
+        state.set_url_filter(UrlFilter { ignore_nofollow: true, ..Default::default() });
+        let page_links = classify_page_links(&parsed_html, "https://example.com/", &state);
+        assert_eq!(LinkKind::InternalFollowed, page_links["https://example.com/login"]);
+    }
+
+    #[test]
+    fn test_assets_are_collected_by_kind() {
+        let html_string = include_str!("../resources/testing_assets.html");
+
+        let assets = extract_assets(&Html::parse_document(html_string), "https://example.com/assets");
+
+        let expected_assets = [
+            ("https://cdn.example.net/lib.js", AssetKind::Script),
+            ("https://example.com/css/site.css", AssetKind::Stylesheet),
+            ("https://example.com/favicon.ico", AssetKind::Icon),
+            ("https://example.com/img/hero-wide.webp", AssetKind::Image),
+            ("https://example.com/img/hero.jpg", AssetKind::Image),
+            ("https://example.com/img/hero.webp", AssetKind::Image),
+            ("https://example.com/img/intro-poster.jpg", AssetKind::Image),
+            ("https://example.com/img/logo.png", AssetKind::Image),
+            ("https://example.com/img/logo@2x.png", AssetKind::Image),
+            ("https://example.com/media/intro.mp4", AssetKind::Video),
+            ("https://example.com/media/intro.webm", AssetKind::Video),
+            ("https://example.com/media/theme.ogg", AssetKind::Audio),
+            ("https://example.com/site.webmanifest", AssetKind::Manifest),
+            ("https://www.youtube.com/embed/abc", AssetKind::Iframe),
+        ]
+        .map(|(link, asset_kind)| (link.to_string(), asset_kind));
+        assert_eq!(BTreeMap::from(expected_assets), assets);
+    }
+
+    #[test]
+    fn test_page_links_are_classified() {
+        let html = concat!(
+            r#"<a href="/about">About</a><a href="/files/guide.PDF">Guide</a><a href="/members/">Members</a>"#,
+            r#"<a href="https://elsewhere.com/">Elsewhere</a><a href="mailto:help@example.com">Help</a>"#,
+        );
+        let state = CrawlState::default();
+        let members_rule = RobotsRule { kind: RuleKind::Disallow, path: String::from("/members") };
+        state.set_robots_rules(String::from("https://example.com"), vec![members_rule]);
+
+        let page_links = classify_page_links(&Html::parse_document(html), "https://example.com/", &state);
+
+        let robots_skipped = LinkKind::InternalSkipped { reason: SkipReason::RobotsDisallowed };
+        assert_eq!(LinkKind::InternalFollowed, page_links["https://example.com/about"]);
+        assert_eq!(LinkKind::Asset, page_links["https://example.com/files/guide.PDF"]);
+        assert_eq!(robots_skipped, page_links["https://example.com/members"]);
+        assert_eq!(LinkKind::External, page_links["https://elsewhere.com"]);
+        assert_eq!(LinkKind::NonHttp, page_links["mailto:help@example.com"]);
+        assert_eq!(
+            r#"{"kind":"internal-skipped","reason":"robots-disallowed"}"#,
+            serde_json::to_string(&robots_skipped).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_skipped_links_are_recorded_with_reason() {
+        let html_string = include_str!("../resources/testing_skipped_links.html").to_string();
+        let page_url = String::from("https://skipped.example.com/");
+
+        let state = CrawlState::default();
+        generate_internal_links(&Html::parse_document(&html_string), &page_url, &state);
+
+        let skipped_links = state.skipped_links.lock().unwrap();
+        assert_eq!(SkipReason::OffDomain, skipped_links["https://elsewhere.example.org/page"]);
+        assert_eq!(SkipReason::UnsupportedScheme, skipped_links["mailto:help@skipped.example.com"]);
+    }
+
+    #[test]
+    fn test_pagination_links_from_link_and_anchor_rels() {
+        let html_string = include_str!("../resources/testing_pagination_links.html").to_string();
+        let page_url = String::from("https://example.com/blog?page=2");
+
+        let pagination = extract_pagination(&Html::parse_document(&html_string), &page_url, &CrawlState::default());
+
+        assert_eq!(Some(String::from("https://example.com/blog?page=3")), pagination.next);
+        assert_eq!(Some(String::from("https://example.com/blog?page=1")), pagination.prev);
+    }
+
+    #[test]
+    fn test_sitemap_links_and_meta_refresh_are_discovered() {
+        let parsed_html = Html::parse_document(include_str!("../resources/testing_discovery_hints.html"));
+        let page_url = String::from("https://example.com/old-home");
+
+        assert_eq!(
+            vec![
+                String::from("https://example.com/sitemap.xml"),
+                String::from("https://cdn.example.net/sitemaps/example.xml.gz"),
+            ],
+            extract_sitemap_links(&parsed_html, &page_url)
+        );
+        assert_eq!(
+            Some(String::from("https://example.com/moved/new-home")),
+            extract_meta_refresh(&parsed_html, &page_url, &CrawlState::default())
+        );
+
+        let reload_only = Html::parse_document(r#"<meta http-equiv="refresh" content="30">"#);
+        assert_eq!(None, extract_meta_refresh(&reload_only, &page_url, &CrawlState::default()));
+    }
+
+    #[test]
+    fn test_header_links_are_resolved_and_scoped() {
+        let header_links = parse_link_header(
+            "<?page=3>; rel=next, <https://elsewhere.example.org/api/items>; rel=\"alternate\", \
+             </api/items.csv>; rel=alternate, </api/items>; rel=canonical",
+        );
+        let page_url = String::from("https://example.com/api/items?page=2");
+        let state = CrawlState::default();
+
+        let pagination = extract_header_pagination(&header_links, &page_url, &state);
+        let alternates: Vec<String> = header_link_targets(&header_links, &[ALTERNATE_REL], &page_url, &state).collect();
+
+        assert_eq!(Some(String::from("https://example.com/api/items?page=3")), pagination.next);
+        assert_eq!(None, pagination.prev);
+        assert_eq!(vec![String::from("https://example.com/api/items.csv")], alternates);
+        assert_eq!(
+            Some(String::from("https://example.com/api/items")),
+            extract_header_canonical(&header_links, &page_url)
+        );
+    }
+
+    #[test]
+    fn test_canonical_link_is_resolved() {
+        let html_string = include_str!("../resources/testing_canonical_link.html").to_string();
+        let page_url = String::from("https://example.com/shoes?sort=price");
+
+        let canonical = extract_canonical(&Html::parse_document(&html_string), &page_url);
+
+        assert_eq!(Some(String::from("https://example.com/shoes")), canonical);
+    }
+
+    #[test]
+    fn test_resource_hints_are_collected() {
+        let html_string = include_str!("../resources/testing_resource_hints.html").to_string();
+        let page_url = String::from("https://example.com/blog/");
 
-        let internal_links = generate_internal_links(html_string, &root_domain);
+        let resource_hints = extract_resource_hints(&Html::parse_document(&html_string), &page_url);
 
-        assert_eq!(false, internal_links.contains("https://example.com/goodInternalLink"));
+        assert_eq!(3, resource_hints.len());
+        assert_eq!(HintRel::Preload, resource_hints[0].rel);
+        assert_eq!("https://example.com/fonts/body.woff2", resource_hints[0].href);
+        assert_eq!(Some(String::from("font")), resource_hints[0].destination);
+        assert_eq!("https://example.com/blog/next-post", resource_hints[1].href);
+        assert_eq!(HintRel::Preconnect, resource_hints[2].rel);
     }
 }
\ No newline at end of file
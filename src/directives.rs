@@ -0,0 +1,77 @@
+use serde::Serialize;
+
+const NOINDEX_DIRECTIVE: &str = "noindex";
+const NOFOLLOW_DIRECTIVE: &str = "nofollow";
+// Short for noindex, nofollow.
+const NONE_DIRECTIVE: &str = "none";
+// Directives written "name: value", which an X-Robots-Tag could otherwise be mistaken as aimed at a crawler by.
+const VALUED_DIRECTIVES: [&str; 4] = ["unavailable_after", "max-snippet", "max-image-preview", "max-video-preview"];
+
+// What a page's <meta name="robots"> tags and X-Robots-Tag headers ask of crawlers, all of them combined - a directive
+// given anywhere applies to the page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct RobotsDirectives {
+    // The page shouldn't be indexed. It's still crawled, and its links followed unless it's nofollow too.
+    pub noindex: bool,
+    // None of the page's links should be followed.
+    pub nofollow: bool,
+}
+
+impl RobotsDirectives {
+    // A comma-separated list, e.g. "noindex, nofollow". Directives the crawler has no use for, such as noarchive, are
+    // ignored.
+    pub fn parse(directives: &str) -> Self {
+        directives.split(',').map(|directive| directive.trim().to_ascii_lowercase()).fold(
+            RobotsDirectives::default(),
+            |robots_directives, directive| match directive.as_str() {
+                NOINDEX_DIRECTIVE => RobotsDirectives { noindex: true, ..robots_directives },
+                NOFOLLOW_DIRECTIVE => RobotsDirectives { nofollow: true, ..robots_directives },
+                NONE_DIRECTIVE => RobotsDirectives { noindex: true, nofollow: true },
+                _ => robots_directives,
+            },
+        )
+    }
+
+    // An X-Robots-Tag can be aimed at one crawler, e.g. "googlebot: noindex". Those are left to the crawler they name.
+    pub fn parse_header_value(value: &str) -> Self {
+        let aimed_at_a_crawler = value.split_once(':').is_some_and(|(prefix, _)| {
+            let prefix = prefix.trim().to_ascii_lowercase();
+            !prefix.contains([',', ' ']) && !VALUED_DIRECTIVES.contains(&prefix.as_str())
+        });
+
+        if aimed_at_a_crawler {
+            RobotsDirectives::default()
+        } else {
+            RobotsDirectives::parse(value)
+        }
+    }
+
+    pub fn combine(self, other: RobotsDirectives) -> Self {
+        RobotsDirectives { noindex: self.noindex || other.noindex, nofollow: self.nofollow || other.nofollow }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.noindex && !self.nofollow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directives_are_parsed_from_lists() {
+        assert_eq!(RobotsDirectives { noindex: true, nofollow: false }, RobotsDirectives::parse("NOINDEX, noarchive"));
+        assert_eq!(RobotsDirectives { noindex: true, nofollow: true }, RobotsDirectives::parse("none"));
+        assert_eq!(RobotsDirectives { noindex: false, nofollow: true }, RobotsDirectives::parse(" nofollow "));
+        assert!(RobotsDirectives::parse("index, follow").is_empty());
+    }
+
+    #[test]
+    fn test_header_values_for_other_crawlers_are_ignored() {
+        assert!(RobotsDirectives::parse_header_value("googlebot: noindex, nofollow").is_empty());
+        assert!(RobotsDirectives::parse_header_value("max-snippet: 20, nofollow").nofollow);
+        assert!(RobotsDirectives::parse_header_value("noindex, max-image-preview: large").noindex);
+        assert!(RobotsDirectives::parse_header_value("unavailable_after: 25 Jun 2030 15:00:00 PST, noindex").noindex);
+    }
+}
@@ -0,0 +1,228 @@
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{header, Client, Url};
+use serde::{Deserialize, Serialize};
+
+const DNS_JSON_CONTENT_TYPE: &str = "application/dns-json";
+const A_RECORD_TYPE: u16 = 1;
+const AAAA_RECORD_TYPE: u16 = 28;
+
+const ANY_FAMILY_NAME: &str = "any";
+const PREFER_IPV4_NAME: &str = "prefer-ipv4";
+const PREFER_IPV6_NAME: &str = "prefer-ipv6";
+const IPV4_ONLY_NAME: &str = "ipv4";
+const IPV6_ONLY_NAME: &str = "ipv6";
+
+// Which addresses outgoing connections use. Preferring a family puts its addresses first, so it's tried first and
+// the other family is only used as a fallback; the *-only settings drop the other family entirely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    PreferIpv4,
+    PreferIpv6,
+    #[serde(rename = "ipv4")]
+    Ipv4Only,
+    #[serde(rename = "ipv6")]
+    Ipv6Only,
+}
+
+impl FromStr for AddressFamily {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            ANY_FAMILY_NAME => Ok(AddressFamily::Any),
+            PREFER_IPV4_NAME => Ok(AddressFamily::PreferIpv4),
+            PREFER_IPV6_NAME => Ok(AddressFamily::PreferIpv6),
+            IPV4_ONLY_NAME => Ok(AddressFamily::Ipv4Only),
+            IPV6_ONLY_NAME => Ok(AddressFamily::Ipv6Only),
+            _ => Err(format!(
+                "Unknown address family '{}', expected one of: {}, {}, {}, {}, {}",
+                name, ANY_FAMILY_NAME, PREFER_IPV4_NAME, PREFER_IPV6_NAME, IPV4_ONLY_NAME, IPV6_ONLY_NAME
+            )),
+        }
+    }
+}
+
+impl AddressFamily {
+    fn apply(self, mut addresses: Vec<IpAddr>) -> Vec<IpAddr> {
+        match self {
+            AddressFamily::Any => {}
+            AddressFamily::PreferIpv4 => addresses.sort_by_key(|address| !address.is_ipv4()),
+            AddressFamily::PreferIpv6 => addresses.sort_by_key(|address| !address.is_ipv6()),
+            AddressFamily::Ipv4Only => addresses.retain(IpAddr::is_ipv4),
+            AddressFamily::Ipv6Only => addresses.retain(IpAddr::is_ipv6),
+        }
+
+        addresses
+    }
+
+    fn record_types(self) -> &'static [u16] {
+        match self {
+            AddressFamily::Ipv4Only => &[A_RECORD_TYPE],
+            AddressFamily::Ipv6Only => &[AAAA_RECORD_TYPE],
+            _ => &[A_RECORD_TYPE, AAAA_RECORD_TYPE],
+        }
+    }
+}
+
+// Looks hostnames up with the system resolver, or a DNS-over-HTTPS provider's JSON API
+// (e.g. https://1.1.1.1/dns-query) when one is configured, then applies the address family setting. The DoH
+// provider itself is reached through the system resolver, so an IP address URL avoids local DNS entirely.
+struct CrawlResolver {
+    doh_provider: Option<Url>,
+    address_family: AddressFamily,
+    doh_client: Client,
+}
+
+#[derive(Deserialize)]
+struct DnsJsonResponse {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsJsonAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DnsJsonAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+// The default settings keep reqwest's own resolver rather than going through ours.
+pub fn build_client(doh_provider: Option<Url>, address_family: AddressFamily) -> reqwest::Result<Client> {
+    // The crawler follows redirects itself, so it can record each hop.
+    let client_builder = Client::builder().redirect(Policy::none());
+    if doh_provider.is_none() && address_family == AddressFamily::Any {
+        return client_builder.build();
+    }
+
+    let resolver = CrawlResolver { doh_provider, address_family, doh_client: Client::new() };
+    client_builder.dns_resolver(Arc::new(resolver)).build()
+}
+
+// Client::new with the crawler's redirect handling. It panics the same way if the TLS backend can't be set up.
+pub fn default_client() -> Client {
+    build_client(None, AddressFamily::Any).expect("Failed to create HTTP client.")
+}
+
+impl Resolve for CrawlResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let doh_provider = self.doh_provider.clone();
+        let address_family = self.address_family;
+        let doh_client = self.doh_client.clone();
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let addresses = match &doh_provider {
+                Some(provider) => {
+                    let mut addresses = Vec::new();
+                    for record_type in address_family.record_types() {
+                        addresses.extend(doh_lookup(&doh_client, provider, &host, *record_type).await?);
+                    }
+                    addresses
+                }
+                None => tokio::net::lookup_host((host.as_str(), 0)).await?.map(|address| address.ip()).collect(),
+            };
+
+            let addresses = address_family.apply(addresses);
+
+            if addresses.is_empty() {
+                return Err(format!("No {:?} addresses found for {}", address_family, host).into());
+            }
+
+            // The port is filled in by the connector from the request URL.
+            let socket_addresses = addresses.into_iter().map(|address| SocketAddr::new(address, 0));
+            Ok(Box::new(socket_addresses.collect::<Vec<_>>().into_iter()) as Addrs)
+        })
+    }
+}
+
+async fn doh_lookup(
+    client: &Client,
+    provider: &Url,
+    host: &str,
+    record_type: u16,
+) -> Result<Vec<IpAddr>, Box<dyn Error + Send + Sync>> {
+    let response = client
+        .get(provider.clone())
+        .query(&[("name", host), ("type", &record_type.to_string())])
+        .header(header::ACCEPT, DNS_JSON_CONTENT_TYPE)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let body = response.bytes().await?;
+    Ok(parse_dns_json(&body)?)
+}
+
+// CNAMEs in the answer are skipped - providers include the records they point at as well.
+fn parse_dns_json(body: &[u8]) -> serde_json::Result<Vec<IpAddr>> {
+    let response: DnsJsonResponse = serde_json::from_slice(body)?;
+
+    let addresses = response
+        .answer
+        .into_iter()
+        .filter(|answer| answer.record_type == A_RECORD_TYPE || answer.record_type == AAAA_RECORD_TYPE)
+        .filter_map(|answer| answer.data.parse().ok())
+        .collect();
+
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixed_addresses() -> Vec<IpAddr> {
+        vec!["2001:db8::1".parse().unwrap(), "192.0.2.1".parse().unwrap(), "2001:db8::2".parse().unwrap()]
+    }
+
+    #[test]
+    fn test_dns_json_answers_skip_cnames() {
+        let body = br#"{
+            "Status": 0,
+            "Answer": [
+                {"name": "www.example.com", "type": 5, "TTL": 300, "data": "example.com."},
+                {"name": "example.com", "type": 1, "TTL": 300, "data": "93.184.216.34"},
+                {"name": "example.com", "type": 28, "TTL": 300, "data": "2606:2800:220:1:248:1893:25c8:1946"}
+            ]
+        }"#;
+
+        let addresses = parse_dns_json(body).unwrap();
+
+        assert_eq!(2, addresses.len());
+        assert_eq!("93.184.216.34".parse::<IpAddr>().unwrap(), addresses[0]);
+    }
+
+    #[test]
+    fn test_dns_json_without_answers() {
+        let addresses = parse_dns_json(br#"{"Status": 3}"#).unwrap();
+
+        assert!(addresses.is_empty());
+    }
+
+    #[test]
+    fn test_preferred_family_is_tried_first() {
+        let addresses = AddressFamily::PreferIpv4.apply(mixed_addresses());
+
+        assert!(addresses[0].is_ipv4());
+        assert_eq!(3, addresses.len());
+    }
+
+    #[test]
+    fn test_only_family_drops_the_other() {
+        let addresses = AddressFamily::Ipv6Only.apply(mixed_addresses());
+
+        assert_eq!(2, addresses.len());
+        assert!(addresses.iter().all(IpAddr::is_ipv6));
+        assert_eq!(Ok(AddressFamily::Ipv6Only), "ipv6".parse());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+// Everything that says two URLs are really the same page. Each signal on its own needs cross-referencing by hand,
+// so they're combined into groups at the end of the crawl.
+#[derive(Default)]
+pub struct DuplicateSignals {
+    canonical_by_page: BTreeMap<String, String>,
+    redirect_target_by_link: BTreeMap<String, String>,
+    content_hash_by_page: BTreeMap<String, String>,
+    // Identical content only counts within a language, so a multilingual site's untranslated pages aren't reported
+    // as duplicates of each other across languages.
+    language_by_page: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateSignal {
+    CanonicalTag,
+    Redirect,
+    ContentHash,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateGroup {
+    pub canonical: String,
+    pub variants: Vec<String>,
+    pub signals: BTreeSet<DuplicateSignal>,
+    // The language every parsed page in the group is in, if they agree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl DuplicateSignals {
+    pub fn record_canonical(&mut self, page: String, canonical: String) {
+        self.canonical_by_page.insert(page, canonical);
+    }
+
+    pub fn record_redirect(&mut self, link: String, target: String) {
+        self.redirect_target_by_link.insert(link, target);
+    }
+
+    pub fn record_content_hash(&mut self, page: String, content_hash: String, language_tag: String) {
+        self.language_by_page.insert(page.clone(), language_tag);
+        self.content_hash_by_page.insert(page, content_hash);
+    }
+
+    // The canonical URL of a group is the one its pages declare most often, then a redirect target, then the
+    // shortest URL in it.
+    pub fn build_duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        let mut union_find = UnionFind::default();
+        let mut edges: Vec<(&String, &String, DuplicateSignal)> = Vec::new();
+
+        for (page, canonical) in self.canonical_by_page.iter() {
+            edges.push((page, canonical, DuplicateSignal::CanonicalTag));
+        }
+
+        for (link, target) in self.redirect_target_by_link.iter() {
+            edges.push((link, target, DuplicateSignal::Redirect));
+        }
+
+        let mut first_page_by_hash: HashMap<(Option<&String>, &String), &String> = HashMap::new();
+        for (page, content_hash) in self.content_hash_by_page.iter() {
+            let language_tag = self.language_by_page.get(page);
+            let first_page = *first_page_by_hash.entry((language_tag, content_hash)).or_insert(page);
+            edges.push((first_page, page, DuplicateSignal::ContentHash));
+        }
+
+        for (first, second, _) in edges.iter() {
+            union_find.union(first, second);
+        }
+
+        let mut members_by_root: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut signals_by_root: HashMap<String, BTreeSet<DuplicateSignal>> = HashMap::new();
+
+        for (first, second, signal) in edges.iter() {
+            let root = union_find.find(first);
+            let members = members_by_root.entry(root.clone()).or_default();
+            members.insert(first.to_string());
+            members.insert(second.to_string());
+
+            if first != second {
+                signals_by_root.entry(root).or_default().insert(*signal);
+            }
+        }
+
+        members_by_root
+            .into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(root, members)| {
+                let canonical = self.choose_canonical(&members);
+                let language = self.shared_language(&members);
+                let variants = members.into_iter().filter(|member| *member != canonical).collect();
+                let signals = signals_by_root.remove(&root).unwrap_or_default();
+
+                DuplicateGroup { canonical, variants, signals, language }
+            })
+            .collect()
+    }
+
+    // Links that only redirected were never parsed, so they don't have a say.
+    fn shared_language(&self, members: &BTreeSet<String>) -> Option<String> {
+        let languages: BTreeSet<&String> =
+            members.iter().filter_map(|member| self.language_by_page.get(member)).collect();
+
+        match languages.into_iter().collect::<Vec<_>>()[..] {
+            [language_tag] => Some(language_tag.clone()),
+            _ => None,
+        }
+    }
+
+    fn choose_canonical(&self, members: &BTreeSet<String>) -> String {
+        let mut declarations: BTreeMap<&String, usize> = BTreeMap::new();
+        for (page, canonical) in self.canonical_by_page.iter() {
+            if members.contains(page) {
+                *declarations.entry(canonical).or_default() += 1;
+            }
+        }
+
+        let most_declared = declarations
+            .into_iter()
+            .max_by(|(link_a, count_a), (link_b, count_b)| count_a.cmp(count_b).then(link_b.cmp(link_a)))
+            .map(|(link, _)| link.clone());
+
+        let redirect_target = self.redirect_target_by_link.values().find(|target| members.contains(*target)).cloned();
+
+        let shortest = || {
+            members.iter().min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b))).cloned().unwrap_or_default()
+        };
+
+        most_declared.or(redirect_target).unwrap_or_else(shortest)
+    }
+}
+
+#[derive(Default)]
+struct UnionFind {
+    parent_by_link: HashMap<String, String>,
+}
+
+impl UnionFind {
+    fn find(&mut self, link: &str) -> String {
+        let parent = self.parent_by_link.entry(link.to_string()).or_insert_with(|| link.to_string()).clone();
+
+        if parent == link {
+            return parent;
+        }
+
+        let root = self.find(&parent);
+        self.parent_by_link.insert(link.to_string(), root.clone());
+        root
+    }
+
+    fn union(&mut self, first: &str, second: &str) {
+        let first_root = self.find(first);
+        let second_root = self.find(second);
+
+        if first_root != second_root {
+            self.parent_by_link.insert(second_root, first_root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals_are_combined_into_one_group() {
+        let mut signals = DuplicateSignals::default();
+        let sorted_page = String::from("https://example.com/shoes?sort=price");
+        signals.record_canonical(sorted_page.clone(), String::from("https://example.com/shoes"));
+        signals.record_redirect(String::from("https://example.com/Shoes"), sorted_page.clone());
+        let english = || String::from("en");
+        signals.record_content_hash(sorted_page, String::from("abc"), english());
+        signals.record_content_hash(String::from("https://example.com/shoes-copy"), String::from("abc"), english());
+        signals.record_content_hash(String::from("https://example.com/about"), String::from("def"), english());
+
+        let groups = signals.build_duplicate_groups();
+
+        assert_eq!(1, groups.len());
+        assert_eq!("https://example.com/shoes", groups[0].canonical);
+        assert_eq!(
+            vec!["https://example.com/Shoes", "https://example.com/shoes-copy", "https://example.com/shoes?sort=price"],
+            groups[0].variants
+        );
+        assert_eq!(3, groups[0].signals.len());
+        assert_eq!(Some(String::from("en")), groups[0].language);
+    }
+
+    #[test]
+    fn test_identical_content_in_different_languages_is_not_a_duplicate() {
+        let mut signals = DuplicateSignals::default();
+        for (page, language_tag) in [("en/faq", "en"), ("de/faq", "de"), ("de/hilfe", "de")] {
+            let page = format!("https://example.com/{}", page);
+            signals.record_content_hash(page, String::from("abc"), language_tag.to_string());
+        }
+
+        let groups = signals.build_duplicate_groups();
+
+        assert_eq!(1, groups.len());
+        assert_eq!("https://example.com/de/faq", groups[0].canonical);
+        assert_eq!(vec!["https://example.com/de/hilfe"], groups[0].variants);
+        assert_eq!(Some(String::from("de")), groups[0].language);
+    }
+
+    #[test]
+    fn test_self_referencing_canonical_is_not_a_duplicate() {
+        let mut signals = DuplicateSignals::default();
+        signals.record_canonical(String::from("https://example.com/a"), String::from("https://example.com/a"));
+
+        assert!(signals.build_duplicate_groups().is_empty());
+    }
+
+    #[test]
+    fn test_redirect_target_is_canonical_without_tags() {
+        let mut signals = DuplicateSignals::default();
+        signals.record_redirect(String::from("https://example.com/old-page"), String::from("https://example.com/new"));
+
+        let groups = signals.build_duplicate_groups();
+
+        assert_eq!("https://example.com/new", groups[0].canonical);
+        assert_eq!(BTreeSet::from([DuplicateSignal::Redirect]), groups[0].signals);
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+const RAW_NAME: &str = "raw";
+const GZIP_NAME: &str = "gzip";
+
+// How each page's HTML is embedded in its links_by_page.ndjson record, for pipelines that do their own extraction.
+// Raw HTML is the easiest to read back, but it makes for a large file. Gzipped and base64-encoded, it's usually several
+// times smaller, as markup compresses well.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HtmlEmbedding {
+    Raw,
+    Gzip,
+}
+
+impl FromStr for HtmlEmbedding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            RAW_NAME => Ok(HtmlEmbedding::Raw),
+            GZIP_NAME => Ok(HtmlEmbedding::Gzip),
+            _ => Err(format!("Unknown HTML embedding '{}', expected one of: {}, {}", name, RAW_NAME, GZIP_NAME)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HtmlEncoding {
+    Raw,
+    GzipBase64,
+}
+
+// The record says how its HTML was encoded, so it can be read back without knowing how the crawl was run.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EmbeddedHtml {
+    pub encoding: HtmlEncoding,
+    pub content: String,
+}
+
+impl HtmlEmbedding {
+    // The HTML after it was decoded to UTF-8, not the bytes as they were served.
+    pub fn embed(&self, html: &str) -> EmbeddedHtml {
+        match self {
+            HtmlEmbedding::Raw => EmbeddedHtml { encoding: HtmlEncoding::Raw, content: html.to_string() },
+            HtmlEmbedding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                let compressed = encoder
+                    .write_all(html.as_bytes())
+                    .and_then(|_| encoder.finish())
+                    .expect("Failed to compress HTML in memory.");
+                EmbeddedHtml { encoding: HtmlEncoding::GzipBase64, content: STANDARD.encode(compressed) }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::MultiGzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn test_gzipped_html_reads_back() {
+        let html = "<html><body><a href=\"/café\">Café</a></body></html>";
+
+        let embedded_html = HtmlEmbedding::Gzip.embed(html);
+        assert_eq!(HtmlEncoding::GzipBase64, embedded_html.encoding);
+
+        let compressed = STANDARD.decode(&embedded_html.content).unwrap();
+        let mut decompressed = String::new();
+        MultiGzDecoder::new(compressed.as_slice()).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(html, decompressed);
+        assert_eq!(html, HtmlEmbedding::Raw.embed(html).content);
+    }
+}
@@ -0,0 +1,135 @@
+use encoding_rs::{Encoding, UTF_8};
+
+// Browsers only look this far into the document for a <meta> charset declaration.
+const META_SNIFF_LENGTH: usize = 1024;
+const META_TAG_START: &str = "<meta";
+const CHARSET_PARAMETER: &str = "charset=";
+const XML_DECLARATION_START: &str = "<?xml";
+const ENCODING_ATTRIBUTE: &str = "encoding=";
+
+// Header charset wins, then a <meta> declaration, then an XML declaration (for XHTML and sitemaps), then UTF-8. A BOM
+// overrides all of them. Bytes that aren't valid in the chosen encoding become U+FFFD rather than failing the page.
+pub fn decode_html(body: &[u8], header_charset: Option<&str>) -> String {
+    let encoding = header_charset
+        .and_then(|charset| Encoding::for_label(charset.trim().as_bytes()))
+        .or_else(|| sniff_meta_charset(body))
+        .or_else(|| sniff_xml_declaration(body))
+        .unwrap_or(UTF_8);
+
+    let (decoded, _, _) = encoding.decode(body);
+    decoded.into_owned()
+}
+
+// The type/subtype part of a Content-Type header, lowercased, e.g. "text/html" for "Text/HTML; charset=UTF-8".
+pub fn media_type(content_type: &str) -> String {
+    let media_type = content_type.split_once(';').map_or(content_type, |(media_type, _)| media_type);
+    media_type.trim().to_ascii_lowercase()
+}
+
+pub fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.trim().split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+// Covers both <meta charset="..."> and <meta http-equiv="Content-Type" content="text/html; charset=...">.
+fn sniff_meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head_length = body.len().min(META_SNIFF_LENGTH);
+    let head = String::from_utf8_lossy(&body[..head_length]).to_ascii_lowercase();
+
+    head.match_indices(META_TAG_START).find_map(|(tag_start, _)| {
+        let tag = &head[tag_start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+
+        let charset_start = tag.find(CHARSET_PARAMETER)? + CHARSET_PARAMETER.len();
+        let charset: String = tag[charset_start..]
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|character| !matches!(character, '"' | '\'' | ';' | '/' | ' '))
+            .collect();
+
+        // A page can't really be UTF-16 if we just read its <meta> tag as ASCII, so this maps those to UTF-8.
+        Encoding::for_label(charset.as_bytes()).map(Encoding::output_encoding)
+    })
+}
+
+// <?xml version="1.0" encoding="Shift_JIS"?> - only at the very start of the document, where XML allows it.
+fn sniff_xml_declaration(body: &[u8]) -> Option<&'static Encoding> {
+    let declaration_end = body.iter().take(META_SNIFF_LENGTH).position(|byte| *byte == b'>')?;
+    let declaration = String::from_utf8_lossy(&body[..declaration_end]).to_ascii_lowercase();
+    let declaration = declaration.strip_prefix(XML_DECLARATION_START)?;
+
+    let encoding_start = declaration.find(ENCODING_ATTRIBUTE)? + ENCODING_ATTRIBUTE.len();
+    let encoding = declaration[encoding_start..].trim_start_matches(['"', '\'']);
+    let encoding = &encoding[..encoding.find(['"', '\'', ' ', '?']).unwrap_or(encoding.len())];
+
+    Encoding::for_label(encoding.as_bytes()).map(Encoding::output_encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::SHIFT_JIS;
+
+    use super::*;
+
+    #[test]
+    fn test_meta_charset_used_without_header_charset() {
+        let html_bytes = include_bytes!("../resources/latin1_meta_charset.html");
+
+        let html_string = decode_html(html_bytes, None);
+
+        assert!(html_string.contains("/café"));
+    }
+
+    #[test]
+    fn test_http_equiv_charset_used_without_header_charset() {
+        let html_bytes = include_bytes!("../resources/latin1_http_equiv.html");
+
+        let html_string = decode_html(html_bytes, None);
+
+        assert!(html_string.contains("/naïve"));
+    }
+
+    #[test]
+    fn test_header_charset_takes_precedence() {
+        let html_bytes = "<meta charset=\"iso-8859-1\"><a href=\"/café\">".as_bytes();
+
+        let html_string = decode_html(html_bytes, Some("utf-8"));
+
+        assert!(html_string.contains("/café"));
+    }
+
+    #[test]
+    fn test_shift_jis_is_decoded_from_any_declaration() {
+        let page = "<a href=\"/ニュース\">ニュース</a>";
+        let (encoded_page, _, _) = SHIFT_JIS.encode(page);
+
+        assert!(decode_html(&encoded_page, Some("Shift_JIS")).contains(page));
+
+        let meta_page = [b"<meta charset=\"shift_jis\">".as_slice(), &encoded_page].concat();
+        assert!(decode_html(&meta_page, None).contains(page));
+
+        let xhtml_page = [b"<?xml version=\"1.0\" encoding='Shift_JIS'?>".as_slice(), &encoded_page].concat();
+        assert!(decode_html(&xhtml_page, None).contains(page));
+        assert!(!decode_html(&encoded_page, None).contains(page));
+    }
+
+    #[test]
+    fn test_charset_from_content_type() {
+        assert_eq!(Some("utf-8"), charset_from_content_type("text/html; charset=utf-8"));
+        assert_eq!(Some("Shift_JIS"), charset_from_content_type("text/html;Charset=\"Shift_JIS\""));
+        assert_eq!(None, charset_from_content_type("text/html"));
+    }
+
+    #[test]
+    fn test_media_type_ignores_parameters_case_and_whitespace() {
+        for content_type in ["text/html", "text/html; charset=utf-8", "Text/HTML;charset=\"UTF-8\"", " text/html ;"] {
+            assert_eq!("text/html", media_type(content_type));
+        }
+        assert_eq!("application/xhtml+xml", media_type("application/xhtml+xml; charset=utf-8"));
+        assert_eq!("", media_type(""));
+    }
+}
@@ -0,0 +1,78 @@
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+// Why a URL couldn't be crawled, as written to errors.json and returned in the CrawlReport.
+#[derive(Clone, Debug, Error, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum CrawlError {
+    #[error("network error: {message}")]
+    Network { message: String },
+    #[error("timed out: {message}")]
+    Timeout { message: String },
+    // The request couldn't be built, e.g. a URL reqwest won't accept.
+    #[error("invalid request: {message}")]
+    InvalidRequest { message: String },
+    // The page is still parsed - error pages often carry the site's navigation - but the URL is reported as failed.
+    #[error("HTTP {}", status_line(*status))]
+    HttpStatus { status: u16 },
+    #[error("still rate limited after {retries} retries")]
+    RateLimited { retries: usize },
+    #[error("failed to read the response body: {message}")]
+    Body { message: String },
+    // The body was arriving slower than the crawl's minimum rate, so it was given up on.
+    #[error("response too slow: {bytes_per_sec} bytes/s")]
+    TooSlow { bytes_per_sec: u64 },
+    // The body was over the crawl's maximum size, so it was abandoned and the URL skipped rather than failed.
+    #[error("response larger than {max_body_size} bytes")]
+    TooLarge { max_body_size: u64 },
+    #[error("more than {max_redirects} redirects")]
+    TooManyRedirects { max_redirects: usize },
+    // A redirect back to a URL already passed through on the way.
+    #[error("redirect loop")]
+    RedirectLoop,
+    #[error("not HTML: {content_type}")]
+    NonHtml { content_type: String },
+    // Only when crawling from an archive.
+    #[error("not in the archive")]
+    NotArchived,
+}
+
+impl CrawlError {
+    pub fn from_request_error(err: &reqwest::Error) -> Self {
+        let message = err.to_string();
+
+        if err.is_timeout() {
+            CrawlError::Timeout { message }
+        } else if err.is_builder() {
+            CrawlError::InvalidRequest { message }
+        } else {
+            CrawlError::Network { message }
+        }
+    }
+
+    // A non-HTML or oversized response is skipped rather than failed - it was never going to be crawled.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, CrawlError::NonHtml { .. } | CrawlError::TooLarge { .. })
+    }
+}
+
+// "404 Not Found", or just the code for statuses without a standard reason.
+fn status_line(status: u16) -> String {
+    StatusCode::from_u16(status).map(|status| status.to_string()).unwrap_or_else(|_| status.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_are_serialized_with_their_kind() {
+        let error = CrawlError::HttpStatus { status: 404 };
+
+        assert_eq!("HTTP 404 Not Found", error.to_string());
+        assert_eq!(r#"{"kind":"http-status","status":404}"#, serde_json::to_string(&error).unwrap());
+        assert_eq!(r#"{"kind":"not-archived"}"#, serde_json::to_string(&CrawlError::NotArchived).unwrap());
+        assert!(!CrawlError::NonHtml { content_type: String::from("image/png") }.is_failure());
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::read_artifact;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct EtagReport {
+    pub etags_by_link: BTreeMap<String, String>,
+    // ETags shared by more than one URL - the same content is likely being served at several addresses.
+    pub duplicate_groups: BTreeMap<String, Vec<String>>,
+    // URLs whose ETag is the same as the one recorded by the previous run.
+    pub unchanged_links: Vec<String>,
+}
+
+pub fn build_etag_report(
+    etags_by_link: BTreeMap<String, String>,
+    previous_etags: Option<&BTreeMap<String, String>>,
+) -> EtagReport {
+    let mut links_by_etag: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (link, etag) in etags_by_link.iter() {
+        links_by_etag.entry(etag.clone()).or_default().push(link.clone());
+    }
+
+    let duplicate_groups = links_by_etag
+        .into_iter()
+        .filter(|(_, links)| links.len() > 1)
+        .map(|(etag, mut links)| {
+            links.sort();
+            (etag, links)
+        })
+        .collect();
+
+    let mut unchanged_links: Vec<String> = previous_etags
+        .map(|previous| {
+            etags_by_link
+                .iter()
+                .filter(|(link, etag)| previous.get(*link) == Some(*etag))
+                .map(|(link, _)| link.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    unchanged_links.sort();
+
+    EtagReport { etags_by_link, duplicate_groups, unchanged_links }
+}
+
+// Reads the ETags back out of a report written by an earlier run.
+pub fn load_previous_etags(report_path: &Path) -> Option<BTreeMap<String, String>> {
+    let report: EtagReport = read_artifact(report_path)?;
+
+    Some(report.etags_by_link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn etags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(link, etag)| (link.to_string(), etag.to_string())).collect()
+    }
+
+    #[test]
+    fn test_shared_etags_are_grouped() {
+        let current = etags(&[
+            ("https://example.com/a", "\"abc\""),
+            ("https://example.com/b", "\"abc\""),
+            ("https://example.com/c", "\"def\""),
+        ]);
+
+        let report = build_etag_report(current, None);
+
+        assert_eq!(1, report.duplicate_groups.len());
+        assert_eq!(
+            vec!["https://example.com/a", "https://example.com/b"],
+            report.duplicate_groups["\"abc\""]
+        );
+    }
+
+    #[test]
+    fn test_matching_previous_etags_are_unchanged() {
+        let current = etags(&[("https://example.com/a", "\"abc\""), ("https://example.com/b", "\"new\"")]);
+        let previous = etags(&[("https://example.com/a", "\"abc\""), ("https://example.com/b", "\"old\"")]);
+
+        let report = build_etag_report(current, Some(&previous));
+
+        assert_eq!(vec!["https://example.com/a"], report.unchanged_links);
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::errors::CrawlError;
+
+// The URLs a site should have, e.g. from a CMS export, checked off as the crawl fetches them - so a crawl can verify a
+// deployment. Links are compared parsed and without a trailing slash, the way the crawl's own outputs write them.
+pub struct ExpectedUrls {
+    expected_links: BTreeSet<String>,
+    fetched_links: BTreeSet<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ExpectedUrlsReport {
+    pub expected: usize,
+    pub reachable: BTreeSet<String>,
+    // Fetched, but the fetch failed or the server answered with an error.
+    pub failed: BTreeMap<String, CrawlError>,
+    // Linked to, but never fetched - held back by robots.txt, a depth or page limit, or a section rule.
+    pub not_fetched: BTreeSet<String>,
+    // Nothing the crawl reached links to them.
+    pub missing: BTreeSet<String>,
+    // Fetched without being expected.
+    pub extra: BTreeSet<String>,
+}
+
+impl ExpectedUrls {
+    pub fn new(expected_links: impl IntoIterator<Item = String>) -> Self {
+        ExpectedUrls {
+            expected_links: expected_links.into_iter().map(|link| comparable_link(&link)).collect(),
+            fetched_links: BTreeSet::new(),
+        }
+    }
+
+    pub fn record_fetched(&mut self, link: &str) {
+        self.fetched_links.insert(comparable_link(link));
+    }
+
+    pub fn build_report(
+        &self,
+        discovered_links: &BTreeSet<String>,
+        failed_links: &BTreeMap<String, CrawlError>,
+    ) -> ExpectedUrlsReport {
+        let discovered_links: BTreeSet<String> = discovered_links.iter().map(|link| comparable_link(link)).collect();
+        let failed_links: BTreeMap<String, &CrawlError> =
+            failed_links.iter().map(|(link, crawl_error)| (comparable_link(link), crawl_error)).collect();
+
+        let mut report = ExpectedUrlsReport { expected: self.expected_links.len(), ..Default::default() };
+        for expected_link in self.expected_links.iter() {
+            if let Some(crawl_error) = failed_links.get(expected_link) {
+                report.failed.insert(expected_link.clone(), (*crawl_error).clone());
+            } else if self.fetched_links.contains(expected_link) {
+                report.reachable.insert(expected_link.clone());
+            } else if discovered_links.contains(expected_link) {
+                report.not_fetched.insert(expected_link.clone());
+            } else {
+                report.missing.insert(expected_link.clone());
+            }
+        }
+
+        report.extra = self.fetched_links.difference(&self.expected_links).cloned().collect();
+        report
+    }
+}
+
+fn comparable_link(link: &str) -> String {
+    let link = Url::parse(link).map(String::from).unwrap_or_else(|_| link.to_string());
+
+    link.strip_suffix('/').map(str::to_string).unwrap_or(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_urls_are_sorted_by_what_the_crawl_found() {
+        let expected_links = ["https://example.com/", "https://example.com/about", "https://example.com/old-page"];
+        let mut expected_urls =
+            ExpectedUrls::new(expected_links.into_iter().chain(["https://example.com/gone/"]).map(String::from));
+        expected_urls.record_fetched("https://example.com/");
+        expected_urls.record_fetched("https://example.com/gone/");
+        expected_urls.record_fetched("https://example.com/new-page");
+
+        let discovered_links = BTreeSet::from([
+            String::from("https://example.com"),
+            String::from("https://example.com/about"),
+            String::from("https://example.com/gone"),
+            String::from("https://example.com/new-page"),
+        ]);
+        let failed_links =
+            BTreeMap::from([(String::from("https://example.com/gone/"), CrawlError::HttpStatus { status: 404 })]);
+
+        let report = expected_urls.build_report(&discovered_links, &failed_links);
+
+        assert_eq!(4, report.expected);
+        assert_eq!(BTreeSet::from([String::from("https://example.com")]), report.reachable);
+        assert_eq!(Some(&CrawlError::HttpStatus { status: 404 }), report.failed.get("https://example.com/gone"));
+        assert_eq!(BTreeSet::from([String::from("https://example.com/about")]), report.not_fetched);
+        assert_eq!(BTreeSet::from([String::from("https://example.com/old-page")]), report.missing);
+        assert_eq!(BTreeSet::from([String::from("https://example.com/new-page")]), report.extra);
+    }
+}
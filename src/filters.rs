@@ -0,0 +1,228 @@
+use regex::Regex;
+use reqwest::Url;
+
+const GLOB_PREFIX: &str = "glob:";
+
+// A pattern for --include and --exclude. Regexes are searched for anywhere in the URL, so "/docs/" matches every page
+// under /docs. Globs are written "glob:/docs/**" and match the whole path, with * standing for anything but a slash
+// and ** for anything at all.
+#[derive(Clone, Debug)]
+pub struct UrlPattern {
+    pattern: String,
+    regex: Regex,
+    matches_path: bool,
+}
+
+impl UrlPattern {
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        if self.matches_path {
+            self.regex.is_match(url.path())
+        } else {
+            self.regex.is_match(url.as_str())
+        }
+    }
+}
+
+pub fn parse_url_pattern(pattern: &str) -> Result<UrlPattern, String> {
+    let (regex, matches_path) = match pattern.strip_prefix(GLOB_PREFIX) {
+        Some(glob) => (glob_to_regex(glob), true),
+        None => (pattern.to_string(), false),
+    };
+
+    let regex = Regex::new(&regex).map_err(|err| format!("Invalid URL pattern '{}': {}", pattern, err))?;
+    Ok(UrlPattern { pattern: pattern.to_string(), regex, matches_path })
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut characters = glob.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '*' if characters.next_if_eq(&'*').is_some() => regex.push_str(".*"),
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            _ => regex.push_str(&regex::escape(&character.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+// Which discovered links are worth queueing at all. A link has to be on the host of the page it was found on - or,
+// with subdomains included, anywhere under the same registrable domain. With include patterns, it has to match one of
+// them, and it mustn't match any exclude pattern.
+#[derive(Clone, Debug, Default)]
+pub struct UrlFilter {
+    pub include_subdomains: bool,
+    // http:// and https:// are different sites, so a link has to keep the scheme of the page it's on. Otherwise
+    // they're the same pages, written with the scheme the host actually serves.
+    pub strict_scheme: bool,
+    // Links written without a scheme ("//example.com/page") take the scheme of the page they're on, like a browser
+    // does. They can be skipped instead, for sites that only use them for other hosts' assets.
+    pub skip_protocol_relative: bool,
+    // rel="nofollow" and nofollow pages are respected unless this is set.
+    pub ignore_nofollow: bool,
+    pub includes: Vec<UrlPattern>,
+    pub excludes: Vec<UrlPattern>,
+    // Never crawled, whatever the scope, along with their subdomains - e.g. sites a wide scope could wander onto.
+    pub blocked_hosts: Vec<String>,
+}
+
+impl UrlFilter {
+    // IP addresses have no domain, so they're never in scope.
+    pub fn in_scope(&self, url: &Url, page_url: &Url) -> bool {
+        let (Some(domain), Some(page_domain)) = (url.domain(), page_url.domain()) else {
+            return false;
+        };
+
+        if self.strict_scheme && url.scheme() != page_url.scheme() {
+            return false;
+        }
+
+        let same_site = || registrable_domain(domain) == registrable_domain(page_domain);
+        domain == page_domain || self.include_subdomains && same_site()
+    }
+
+    pub fn allows(&self, url: &Url) -> bool {
+        let included = self.includes.is_empty() || self.includes.iter().any(|pattern| pattern.matches(url));
+
+        included && !self.excludes.iter().any(|pattern| pattern.matches(url))
+    }
+
+    pub fn is_blocked(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        self.blocked_hosts.iter().any(|blocked_host| {
+            host.strip_suffix(blocked_host.as_str())
+                .is_some_and(|subdomains| subdomains.is_empty() || subdomains.ends_with('.'))
+        })
+    }
+
+    pub fn include_patterns(&self) -> Vec<String> {
+        self.includes.iter().map(|pattern| pattern.as_str().to_string()).collect()
+    }
+
+    pub fn exclude_patterns(&self) -> Vec<String> {
+        self.excludes.iter().map(|pattern| pattern.as_str().to_string()).collect()
+    }
+}
+
+// A host for --block-host, e.g. "example.com" - "*.example.com" is taken to mean the same, as subdomains are blocked
+// anyway.
+pub fn parse_blocked_host(host: &str) -> Result<String, String> {
+    let blocked_host = host.trim().trim_start_matches("*.").trim_end_matches('.').to_lowercase();
+
+    match Url::parse(&format!("http://{}/", blocked_host)) {
+        Ok(parsed_url) if parsed_url.host_str().is_some_and(|parsed_host| parsed_host == blocked_host) => {
+            Ok(blocked_host)
+        }
+        _ => Err(format!("Invalid host to block '{}' - give just the host, e.g. example.com", host)),
+    }
+}
+
+// "//example.com/page" - browsers treat backslashes as slashes in http(s) URLs, so "\\example.com" is one too.
+pub fn is_protocol_relative(href: &str) -> bool {
+    let mut characters = href.trim_start_matches(|character: char| character <= ' ').chars();
+    matches!((characters.next(), characters.next()), (Some('/' | '\\'), Some('/' | '\\')))
+}
+
+// The part of a host registered with a registrar (eTLD+1 in the Public Suffix List), e.g. example.co.uk for
+// blog.example.co.uk. A host without one, such as localhost, is its own.
+fn registrable_domain(domain: &str) -> &str {
+    psl::domain_str(domain).unwrap_or(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(link: &str) -> Url {
+        Url::parse(link).unwrap()
+    }
+
+    #[test]
+    fn test_globs_match_the_whole_path() {
+        let pattern = parse_url_pattern("glob:/blog/*/comments").unwrap();
+
+        assert!(pattern.matches(&url("https://example.com/blog/first-post/comments")));
+        assert!(!pattern.matches(&url("https://example.com/blog/2024/first-post/comments")));
+        assert!(!pattern.matches(&url("https://example.com/archive/blog/first-post/comments")));
+
+        let pattern = parse_url_pattern("glob:/docs/**").unwrap();
+        assert!(pattern.matches(&url("https://example.com/docs/v2/intro?lang=en")));
+        assert!(!pattern.matches(&url("https://example.com/docs")));
+    }
+
+    #[test]
+    fn test_excludes_win_over_includes() {
+        let includes = vec![parse_url_pattern("/docs/.*").unwrap()];
+        let excludes = vec![parse_url_pattern("/tag/").unwrap(), parse_url_pattern("glob:**.pdf").unwrap()];
+        let url_filter = UrlFilter { includes, excludes, ..Default::default() };
+
+        assert!(url_filter.allows(&url("https://example.com/docs/intro")));
+        assert!(!url_filter.allows(&url("https://example.com/docs/tag/rust")));
+        assert!(!url_filter.allows(&url("https://example.com/docs/manual.pdf")));
+        assert!(!url_filter.allows(&url("https://example.com/blog/")));
+        assert!(UrlFilter::default().allows(&url("https://example.com/blog/")));
+    }
+
+    #[test]
+    fn test_subdomains_are_in_scope_by_registrable_domain() {
+        let url_filter = UrlFilter { include_subdomains: true, ..Default::default() };
+        let page_url = url("https://www.example.co.uk/");
+
+        assert!(url_filter.in_scope(&url("https://blog.example.co.uk/post"), &page_url));
+        assert!(url_filter.in_scope(&url("https://example.co.uk/"), &page_url));
+        assert!(!url_filter.in_scope(&url("https://other.co.uk/"), &page_url));
+        assert!(!url_filter.in_scope(&url("https://example.com/"), &page_url));
+        assert!(!url_filter.in_scope(&url("https://alice.github.io/"), &url("https://bob.github.io/")));
+        assert!(!UrlFilter::default().in_scope(&url("https://blog.example.co.uk/post"), &page_url));
+    }
+
+    #[test]
+    fn test_other_schemes_are_out_of_scope_when_strict() {
+        let url_filter = UrlFilter { strict_scheme: true, ..Default::default() };
+        let page_url = url("https://example.com/");
+
+        assert!(url_filter.in_scope(&url("https://example.com/about"), &page_url));
+        assert!(!url_filter.in_scope(&url("http://example.com/about"), &page_url));
+        assert!(UrlFilter::default().in_scope(&url("http://example.com/about"), &page_url));
+    }
+
+    #[test]
+    fn test_protocol_relative_links_are_told_apart_from_paths() {
+        assert!(is_protocol_relative("//cdn.example.com/app.js"));
+        assert!(is_protocol_relative(" \\/example.com/"));
+        assert!(!is_protocol_relative("/docs//intro"));
+        assert!(!is_protocol_relative("https://example.com/"));
+        assert!(!is_protocol_relative("docs"));
+    }
+
+    #[test]
+    fn test_blocked_hosts_cover_their_subdomains() {
+        let blocked_hosts = vec![parse_blocked_host("*.Example.com").unwrap(), parse_blocked_host("10.0.0.1").unwrap()];
+        let url_filter = UrlFilter { blocked_hosts, ..Default::default() };
+
+        assert!(url_filter.is_blocked(&url("https://example.com/")));
+        assert!(url_filter.is_blocked(&url("https://shop.eu.example.com/cart")));
+        assert!(url_filter.is_blocked(&url("http://10.0.0.1:8080/")));
+        assert!(!url_filter.is_blocked(&url("https://notexample.com/")));
+        assert!(!url_filter.is_blocked(&url("https://example.com.evil.net/")));
+        assert!(parse_blocked_host("https://example.com/").is_err());
+        assert!(parse_blocked_host("example.com:8080").is_err());
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected() {
+        assert!(parse_url_pattern("/docs/(").is_err());
+        assert!(parse_url_pattern("glob:/docs/(").is_ok());
+    }
+}
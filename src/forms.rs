@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use reqwest::Url;
+use serde::Serialize;
+
+const GET_METHOD: &str = "get";
+// Fields whose values a crawler has no business making up or submitting.
+const UNSAFE_FIELD_TYPES: [&str; 2] = ["password", "file"];
+// Fields that only contribute a value when a person clicks or ticks them.
+const UNSUBMITTED_FIELD_TYPES: [&str; 5] = ["submit", "button", "reset", "checkbox", "radio"];
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Form {
+    pub action: String,
+    pub method: String,
+    pub fields: Vec<FormField>,
+    // Links built from this form with the configured field values, when it's safe to do so.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expanded_links: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl Form {
+    // Only GET forms are idempotent, and anything asking for a password or a file is left well alone.
+    pub fn is_expandable(&self) -> bool {
+        self.method == GET_METHOD
+            && !self.fields.iter().any(|field| UNSAFE_FIELD_TYPES.contains(&field.field_type.as_str()))
+    }
+
+    // One link per configured value of each field the form has, with every other field left at its default. Fields
+    // are substituted one at a time rather than combined, so a handful of values can't multiply into thousands of URLs.
+    pub fn expand(&self, form_values: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+        let Ok(action) = Url::parse(&self.action) else {
+            return Vec::new();
+        };
+
+        if !self.is_expandable() {
+            return Vec::new();
+        }
+
+        let mut expanded_links = Vec::new();
+        for field in self.fields.iter() {
+            for value in form_values.get(&field.name).into_iter().flatten() {
+                let mut expanded_url = action.clone();
+                expanded_url.set_query(None);
+                expanded_url.set_fragment(None);
+
+                let mut query = expanded_url.query_pairs_mut();
+                for submitted_field in self.fields.iter().filter(|other| other.is_submitted()) {
+                    let submitted_value = if submitted_field.name == field.name {
+                        value.as_str()
+                    } else {
+                        submitted_field.value.as_deref().unwrap_or_default()
+                    };
+                    query.append_pair(&submitted_field.name, submitted_value);
+                }
+                drop(query);
+
+                expanded_links.push(expanded_url.to_string());
+            }
+        }
+
+        expanded_links
+    }
+}
+
+impl FormField {
+    fn is_submitted(&self) -> bool {
+        !UNSUBMITTED_FIELD_TYPES.contains(&self.field_type.as_str())
+    }
+}
+
+// Parses "name=value" - the value may itself contain '='.
+pub fn parse_form_value(form_value: &str) -> Result<(String, String), String> {
+    match form_value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("Invalid form value '{}', expected 'name=value'", form_value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, field_type: &str, value: Option<&str>) -> FormField {
+        FormField { name: name.to_string(), field_type: field_type.to_string(), value: value.map(str::to_string) }
+    }
+
+    fn search_form(method: &str) -> Form {
+        Form {
+            action: String::from("https://example.com/search"),
+            method: method.to_string(),
+            fields: vec![
+                field("q", "search", None),
+                field("lang", "hidden", Some("en")),
+                field("go", "submit", Some("Search")),
+            ],
+            expanded_links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_get_forms_expand_with_configured_values() {
+        let form_values = BTreeMap::from([(String::from("q"), vec![String::from("red shoes"), String::from("boots")])]);
+
+        assert_eq!(
+            vec!["https://example.com/search?q=red+shoes&lang=en", "https://example.com/search?q=boots&lang=en"],
+            search_form(GET_METHOD).expand(&form_values)
+        );
+    }
+
+    #[test]
+    fn test_unsafe_forms_are_not_expanded() {
+        let form_values = BTreeMap::from([(String::from("q"), vec![String::from("boots")])]);
+        let mut login_form = search_form(GET_METHOD);
+        login_form.fields.push(field("password", "password", None));
+
+        assert!(search_form("post").expand(&form_values).is_empty());
+        assert!(login_form.expand(&form_values).is_empty());
+        assert!(search_form(GET_METHOD).expand(&BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_form_value_parsing() {
+        assert_eq!(Ok((String::from("q"), String::from("a=b"))), parse_form_value("q=a=b"));
+        assert!(parse_form_value("=shoes").is_err());
+        assert!(parse_form_value("shoes").is_err());
+    }
+}
@@ -0,0 +1,434 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fs::{remove_file, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+const BREADTH_FIRST_NAME: &str = "breadth-first";
+const DEPTH_FIRST_NAME: &str = "depth-first";
+const SHALLOW_PATHS_FIRST_NAME: &str = "shallow-paths-first";
+const RANDOM_NAME: &str = "random";
+const SITEMAP_PRIORITY_NAME: &str = "sitemap-priority";
+const SPILL_RELOAD_BATCH: usize = 1000;
+
+// The order in which discovered links are handed out for fetching.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrontierStrategy {
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+    #[serde(rename = "shallow-paths-first")]
+    ShallowPaths,
+    // A different order every run, so repeated monitoring crawls don't always warm the same caches in the same order.
+    Random,
+    // Pages the sitemaps list first, in the order they were found, then the rest breadth-first - a site's own list of
+    // what matters is covered before a bounded crawl runs out of pages.
+    SitemapPriority,
+}
+
+// Which of the frontier's queues a link is pushed onto.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontierQueue {
+    // Ordered by the strategy.
+    Strategy,
+    // Listed in a sitemap. Only the sitemap-priority strategy sets these apart from the rest.
+    Sitemap,
+    // Handed out ahead of everything else regardless of strategy, e.g. the next page of a paginated series.
+    Priority,
+}
+
+impl FromStr for FrontierStrategy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            BREADTH_FIRST_NAME => Ok(FrontierStrategy::BreadthFirst),
+            DEPTH_FIRST_NAME => Ok(FrontierStrategy::DepthFirst),
+            SHALLOW_PATHS_FIRST_NAME => Ok(FrontierStrategy::ShallowPaths),
+            RANDOM_NAME => Ok(FrontierStrategy::Random),
+            SITEMAP_PRIORITY_NAME => Ok(FrontierStrategy::SitemapPriority),
+            _ => Err(format!(
+                "Unknown frontier strategy '{}', expected one of: {}, {}, {}, {}, {}",
+                name,
+                BREADTH_FIRST_NAME,
+                DEPTH_FIRST_NAME,
+                SHALLOW_PATHS_FIRST_NAME,
+                RANDOM_NAME,
+                SITEMAP_PRIORITY_NAME
+            )),
+        }
+    }
+}
+
+pub struct Frontier {
+    strategy: FrontierStrategy,
+    queue: VecDeque<Arc<str>>,
+    // Only used by ShallowPaths - links bucketed by the number of segments in their path.
+    queues_by_path_depth: BTreeMap<usize, VecDeque<Arc<str>>>,
+    // Handed out ahead of everything else regardless of strategy, e.g. the next page of a paginated series.
+    priority_queue: VecDeque<Arc<str>>,
+    // Only used by SitemapPriority - handed out after the priority links, ahead of the rest.
+    sitemap_queue: VecDeque<Arc<str>>,
+    // Links that can't be fetched before a given time, e.g. rate-limited pages, soonest first. They join the strategy's
+    // order once they're due, and are never spilled.
+    delayed: BinaryHeap<Reverse<(Instant, Arc<str>)>>,
+    spill_file: Option<SpillFile>,
+    // Only used by Random. Seeded, the same links pushed in the same order come back out in the same order.
+    rng: StdRng,
+}
+
+// Links moved out to disk under memory pressure, read back a batch at a time once the in-memory queues run dry.
+struct SpillFile {
+    path: PathBuf,
+    read_offset: u64,
+    unread_links: usize,
+}
+
+impl Frontier {
+    pub fn new(strategy: FrontierStrategy) -> Self {
+        Frontier {
+            strategy,
+            queue: VecDeque::new(),
+            queues_by_path_depth: BTreeMap::new(),
+            priority_queue: VecDeque::new(),
+            sitemap_queue: VecDeque::new(),
+            delayed: BinaryHeap::new(),
+            spill_file: None,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_random_seed(mut self, random_seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(random_seed);
+        self
+    }
+
+    pub fn push(&mut self, link: Arc<str>) {
+        match self.strategy {
+            FrontierStrategy::BreadthFirst
+            | FrontierStrategy::DepthFirst
+            | FrontierStrategy::Random
+            | FrontierStrategy::SitemapPriority => self.queue.push_back(link),
+            FrontierStrategy::ShallowPaths => self.queues_by_path_depth
+                .entry(path_depth(&link))
+                .or_default()
+                .push_back(link),
+        }
+    }
+
+    pub fn push_priority(&mut self, link: Arc<str>) {
+        self.priority_queue.push_back(link);
+    }
+
+    pub fn push_sitemap(&mut self, link: Arc<str>) {
+        match self.strategy {
+            FrontierStrategy::SitemapPriority => self.sitemap_queue.push_back(link),
+            _ => self.push(link),
+        }
+    }
+
+    pub fn push_to(&mut self, link: Arc<str>, queue: FrontierQueue) {
+        match queue {
+            FrontierQueue::Strategy => self.push(link),
+            FrontierQueue::Sitemap => self.push_sitemap(link),
+            FrontierQueue::Priority => self.push_priority(link),
+        }
+    }
+
+    pub fn push_not_before(&mut self, link: Arc<str>, not_before: Instant) {
+        self.delayed.push(Reverse((not_before, link)));
+    }
+
+    // When the first delayed link is due, if any are waiting.
+    pub fn next_not_before(&self) -> Option<Instant> {
+        self.delayed.peek().map(|Reverse((not_before, _))| *not_before)
+    }
+
+    // Spilled links only come back once everything in memory has been handed out, so while spilling the strategy's
+    // order holds within each batch rather than across the whole crawl.
+    pub fn pop(&mut self) -> Option<Arc<str>> {
+        self.release_due(Instant::now());
+        if let Some(link) = self.pop_in_memory() {
+            return Some(link);
+        }
+
+        match self.reload_spilled() {
+            Ok(true) => self.pop_in_memory(),
+            Ok(false) => None,
+            Err(err) => {
+                eprintln!("Failed to read spilled links back: {:?}", err);
+                None
+            }
+        }
+    }
+
+    // Moves every queued link except the priority ones out to the spill file, returning how many were moved. Sitemap
+    // links go first, but once spilled they come back like any other link.
+    pub fn spill(&mut self, spill_path: &Path) -> std::io::Result<usize> {
+        let links: Vec<Arc<str>> = self.sitemap_queue
+            .drain(..)
+            .chain(self.queue.drain(..))
+            .chain(std::mem::take(&mut self.queues_by_path_depth).into_values().flatten())
+            .collect();
+        self.queue.shrink_to_fit();
+        self.sitemap_queue.shrink_to_fit();
+
+        let mut writer = BufWriter::new(OpenOptions::new().create(true).append(true).open(spill_path)?);
+        for link in links.iter() {
+            writeln!(writer, "{}", link)?;
+        }
+        writer.flush()?;
+
+        let spill_file = self.spill_file.get_or_insert_with(|| SpillFile {
+            path: spill_path.to_path_buf(),
+            read_offset: 0,
+            unread_links: 0,
+        });
+        spill_file.unread_links += links.len();
+
+        Ok(links.len())
+    }
+
+    fn release_due(&mut self, now: Instant) {
+        while self.next_not_before().is_some_and(|not_before| not_before <= now) {
+            if let Some(Reverse((_, link))) = self.delayed.pop() {
+                self.push(link);
+            }
+        }
+    }
+
+    // Returns false when there's nothing left on disk, removing the spill file at that point.
+    fn reload_spilled(&mut self) -> std::io::Result<bool> {
+        let Some(spill_file) = self.spill_file.as_mut() else {
+            return Ok(false);
+        };
+
+        if spill_file.unread_links == 0 {
+            remove_file(&spill_file.path)?;
+            self.spill_file = None;
+            return Ok(false);
+        }
+
+        let mut file = File::open(&spill_file.path)?;
+        file.seek(SeekFrom::Start(spill_file.read_offset))?;
+        let mut reader = BufReader::new(file);
+
+        let mut links = Vec::new();
+        let mut line = String::new();
+        while links.len() < SPILL_RELOAD_BATCH && reader.read_line(&mut line)? > 0 {
+            spill_file.read_offset += line.len() as u64;
+            links.push(Arc::from(line.trim_end()));
+            line.clear();
+        }
+
+        spill_file.unread_links = spill_file.unread_links.saturating_sub(links.len());
+        let reloaded = !links.is_empty();
+        links.into_iter().for_each(|link| self.push(link));
+
+        Ok(reloaded)
+    }
+
+    fn pop_in_memory(&mut self) -> Option<Arc<str>> {
+        if let Some(link) = self.priority_queue.pop_front() {
+            return Some(link);
+        }
+
+        match self.strategy {
+            FrontierStrategy::BreadthFirst => self.queue.pop_front(),
+            FrontierStrategy::SitemapPriority => self.sitemap_queue.pop_front().or_else(|| self.queue.pop_front()),
+            FrontierStrategy::DepthFirst => self.queue.pop_back(),
+            FrontierStrategy::Random => {
+                let index = self.rng.gen_range(0..self.queue.len().max(1));
+                self.queue.swap_remove_back(index)
+            }
+            FrontierStrategy::ShallowPaths => {
+                let mut shallowest = self.queues_by_path_depth.first_entry()?;
+                let link = shallowest.get_mut().pop_front();
+
+                if shallowest.get().is_empty() {
+                    shallowest.remove();
+                }
+
+                link
+            }
+        }
+    }
+}
+
+// A crawl that's cancelled partway through never drains the frontier, so the spill file has to go with it.
+impl Drop for Frontier {
+    fn drop(&mut self) {
+        if let Some(spill_file) = self.spill_file.take() {
+            let _ = remove_file(spill_file.path);
+        }
+    }
+}
+
+fn path_depth(link: &str) -> usize {
+    Url::parse(link)
+        .map(|parsed_url| parsed_url.path().split('/').filter(|part| !part.is_empty()).count())
+        .unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(mut frontier: Frontier) -> Vec<String> {
+        std::iter::from_fn(|| frontier.pop()).map(|link| link.to_string()).collect()
+    }
+
+    fn filled_frontier(strategy: FrontierStrategy) -> Frontier {
+        let mut frontier = Frontier::new(strategy);
+        frontier.push(Arc::from("https://example.com/a/b/c"));
+        frontier.push(Arc::from("https://example.com/a"));
+        frontier.push(Arc::from("https://example.com/a/b"));
+        frontier
+    }
+
+    #[test]
+    fn test_breadth_first_order() {
+        let links = drain(filled_frontier(FrontierStrategy::BreadthFirst));
+
+        assert_eq!(vec!["https://example.com/a/b/c", "https://example.com/a", "https://example.com/a/b"], links);
+    }
+
+    #[test]
+    fn test_depth_first_order() {
+        let links = drain(filled_frontier(FrontierStrategy::DepthFirst));
+
+        assert_eq!(vec!["https://example.com/a/b", "https://example.com/a", "https://example.com/a/b/c"], links);
+    }
+
+    #[test]
+    fn test_shallow_paths_first_order() {
+        let links = drain(filled_frontier(FrontierStrategy::ShallowPaths));
+
+        assert_eq!(vec!["https://example.com/a", "https://example.com/a/b", "https://example.com/a/b/c"], links);
+    }
+
+    #[test]
+    fn test_random_order_hands_out_every_link_once() {
+        let mut links = drain(filled_frontier(FrontierStrategy::Random));
+        links.sort();
+
+        assert_eq!(vec!["https://example.com/a", "https://example.com/a/b", "https://example.com/a/b/c"], links);
+    }
+
+    #[test]
+    fn test_seeded_random_order_repeats() {
+        let seeded_frontier = |random_seed| {
+            let mut frontier = Frontier::new(FrontierStrategy::Random).with_random_seed(random_seed);
+            (0..50).for_each(|page| frontier.push(Arc::from(format!("https://example.com/{}", page))));
+            frontier
+        };
+
+        let links = drain(seeded_frontier(7));
+        assert_eq!(50, links.len());
+        assert_eq!(links, drain(seeded_frontier(7)));
+        assert_ne!(links, drain(seeded_frontier(8)));
+    }
+
+    #[test]
+    fn test_sitemap_priority_order() {
+        let mut frontier = filled_frontier(FrontierStrategy::SitemapPriority);
+        frontier.push_sitemap(Arc::from("https://example.com/listed/1"));
+        frontier.push_priority(Arc::from("https://example.com/a?page=2"));
+        frontier.push_sitemap(Arc::from("https://example.com/listed/2"));
+
+        assert_eq!(
+            vec![
+                "https://example.com/a?page=2",
+                "https://example.com/listed/1",
+                "https://example.com/listed/2",
+                "https://example.com/a/b/c",
+                "https://example.com/a",
+                "https://example.com/a/b",
+            ],
+            drain(frontier)
+        );
+    }
+
+    #[test]
+    fn test_sitemap_links_follow_other_strategies() {
+        let mut frontier = filled_frontier(FrontierStrategy::DepthFirst);
+        frontier.push_sitemap(Arc::from("https://example.com/listed"));
+
+        assert_eq!(Some("https://example.com/listed"), frontier.pop().as_deref());
+        assert_eq!(Some("https://example.com/a/b"), frontier.pop().as_deref());
+    }
+
+    #[test]
+    fn test_priority_links_come_first() {
+        let mut frontier = filled_frontier(FrontierStrategy::ShallowPaths);
+        frontier.push_priority(Arc::from("https://example.com/a/b/c/d"));
+
+        assert_eq!(Some("https://example.com/a/b/c/d"), frontier.pop().as_deref());
+        assert_eq!(Some("https://example.com/a"), frontier.pop().as_deref());
+    }
+
+    #[test]
+    fn test_delayed_links_wait_until_they_are_due() {
+        let mut frontier = Frontier::new(FrontierStrategy::BreadthFirst);
+        let later = Instant::now() + std::time::Duration::from_secs(60);
+        frontier.push_not_before(Arc::from("https://example.com/later"), later);
+        frontier.push_not_before(Arc::from("https://example.com/due"), Instant::now());
+        frontier.push(Arc::from("https://example.com/a"));
+
+        assert_eq!(Some("https://example.com/a"), frontier.pop().as_deref());
+        assert_eq!(Some("https://example.com/due"), frontier.pop().as_deref());
+        assert_eq!(None, frontier.pop());
+        assert_eq!(Some(later), frontier.next_not_before());
+
+        frontier.release_due(later);
+        assert_eq!(Some("https://example.com/later"), frontier.pop().as_deref());
+        assert_eq!(None, frontier.next_not_before());
+    }
+
+    #[test]
+    fn test_spilled_links_are_handed_out_after_memory() {
+        let spill_path = std::env::temp_dir().join(format!("frontier-{}.spill", std::process::id()));
+        let mut frontier = filled_frontier(FrontierStrategy::BreadthFirst);
+
+        assert_eq!(3, frontier.spill(&spill_path).unwrap());
+        frontier.push(Arc::from("https://example.com/d"));
+
+        assert_eq!(
+            vec![
+                "https://example.com/d",
+                "https://example.com/a/b/c",
+                "https://example.com/a",
+                "https://example.com/a/b",
+            ],
+            drain(frontier)
+        );
+        assert!(!spill_path.exists());
+    }
+
+    #[test]
+    fn test_spill_file_is_removed_with_an_undrained_frontier() {
+        let spill_path = std::env::temp_dir().join(format!("frontier-dropped-{}.spill", std::process::id()));
+        let mut frontier = filled_frontier(FrontierStrategy::DepthFirst);
+        frontier.spill(&spill_path).unwrap();
+
+        drop(frontier);
+
+        assert!(!spill_path.exists());
+    }
+
+    #[test]
+    fn test_strategy_from_name() {
+        assert_eq!(Ok(FrontierStrategy::ShallowPaths), "shallow-paths-first".parse());
+        assert_eq!(Ok(FrontierStrategy::SitemapPriority), "sitemap-priority".parse());
+        assert!("sideways".parse::<FrontierStrategy>().is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+// The crawl's links as adjacency lists of integer IDs. Each URL is stored once however many pages link to it, which
+// keeps the graph small on large sites and makes walking it cheap.
+#[derive(Default)]
+pub struct LinkGraph {
+    ids_by_link: HashMap<Arc<str>, u32>,
+    links: Vec<Arc<str>>,
+    outgoing: Vec<Vec<u32>>,
+    seed_ids: Vec<u32>,
+}
+
+// How many clicks each page is from the nearest seed, following links found during the crawl.
+#[derive(Serialize, Debug, Default)]
+pub struct ClickDepthReport {
+    pub pages_by_depth: BTreeMap<usize, usize>,
+    pub depth_by_page: BTreeMap<String, usize>,
+}
+
+impl LinkGraph {
+    pub fn record_seed(&mut self, seed_link: &str) {
+        let seed_id = self.intern(seed_link);
+        self.seed_ids.push(seed_id);
+    }
+
+    pub fn record_links<'a>(&mut self, page: &str, links: impl IntoIterator<Item = &'a str>) {
+        let page_id = self.intern(page);
+        let link_ids: Vec<u32> = links.into_iter().map(|link| self.intern(link)).collect();
+
+        self.outgoing[page_id as usize].extend(link_ids);
+    }
+
+    // Breadth-first from every seed at once, so a page's depth is its shortest distance from any of them.
+    pub fn build_click_depth_report(&self) -> ClickDepthReport {
+        let mut depths: Vec<Option<usize>> = vec![None; self.links.len()];
+        let mut queue: VecDeque<u32> = VecDeque::new();
+
+        for seed_id in self.seed_ids.iter() {
+            if depths[*seed_id as usize].is_none() {
+                depths[*seed_id as usize] = Some(0);
+                queue.push_back(*seed_id);
+            }
+        }
+
+        while let Some(page_id) = queue.pop_front() {
+            let next_depth = depths[page_id as usize].unwrap_or_default() + 1;
+
+            for link_id in self.outgoing[page_id as usize].iter() {
+                if depths[*link_id as usize].is_none() {
+                    depths[*link_id as usize] = Some(next_depth);
+                    queue.push_back(*link_id);
+                }
+            }
+        }
+
+        let mut report = ClickDepthReport::default();
+        for (link_id, depth) in depths.into_iter().enumerate() {
+            if let Some(depth) = depth {
+                *report.pages_by_depth.entry(depth).or_default() += 1;
+                report.depth_by_page.insert(self.links[link_id].to_string(), depth);
+            }
+        }
+
+        report
+    }
+
+    fn intern(&mut self, link: &str) -> u32 {
+        if let Some(link_id) = self.ids_by_link.get(link) {
+            return *link_id;
+        }
+
+        let link_id = self.links.len() as u32;
+        let link: Arc<str> = Arc::from(link);
+        self.ids_by_link.insert(link.clone(), link_id);
+        self.links.push(link);
+        self.outgoing.push(Vec::new());
+
+        link_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_are_interned_once() {
+        let mut graph = LinkGraph::default();
+        graph.record_links("https://example.com", ["https://example.com/a", "https://example.com/b"]);
+        graph.record_links("https://example.com/a", ["https://example.com/b", "https://example.com"]);
+
+        assert_eq!(3, graph.links.len());
+        assert_eq!(vec![2, 0], graph.outgoing[1]);
+    }
+
+    #[test]
+    fn test_click_depth_is_shortest_distance_from_a_seed() {
+        let mut graph = LinkGraph::default();
+        graph.record_seed("https://example.com");
+        graph.record_links("https://example.com", ["https://example.com/a", "https://example.com/b"]);
+        graph.record_links("https://example.com/a", ["https://example.com/a/deep"]);
+        graph.record_links("https://example.com/a/deep", ["https://example.com/b"]);
+        graph.record_links("https://example.com/orphan", ["https://example.com/a"]);
+
+        let report = graph.build_click_depth_report();
+
+        assert_eq!(Some(&1), report.depth_by_page.get("https://example.com/b"));
+        assert_eq!(Some(&2), report.depth_by_page.get("https://example.com/a/deep"));
+        assert_eq!(None, report.depth_by_page.get("https://example.com/orphan"));
+        assert_eq!(BTreeMap::from([(0, 1), (1, 2), (2, 1)]), report.pages_by_depth);
+    }
+}
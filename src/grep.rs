@@ -0,0 +1,62 @@
+use regex::Regex;
+use serde::Serialize;
+
+// Enough either side of a match to recognise where it is on the page.
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+const MATCHES_KEPT_PER_PAGE: usize = 10;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GrepMatch {
+    pub line: usize,
+    pub snippet: String,
+}
+
+// Searches the raw HTML, so markup like tracking snippets in <script> tags can be found as well as visible text.
+pub fn find_matches(pattern: &Regex, html: &str) -> Vec<GrepMatch> {
+    pattern
+        .find_iter(html)
+        .take(MATCHES_KEPT_PER_PAGE)
+        .map(|found| GrepMatch {
+            line: html[..found.start()].matches('\n').count() + 1,
+            snippet: snippet(html, found.start(), found.end()),
+        })
+        .collect()
+}
+
+fn snippet(html: &str, start: usize, end: usize) -> String {
+    let snippet_start = html[..start].char_indices().rev().nth(SNIPPET_CONTEXT_CHARS - 1).map_or(0, |(index, _)| index);
+    let snippet_end = html[end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT_CHARS)
+        .map_or(html.len(), |(index, _)| end + index);
+
+    html[snippet_start..snippet_end].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_have_line_and_context() {
+        let html = "<html>\n<head>\n<script src=\"https://tracker.example.com/old.js\"></script>\n</head>\n</html>";
+        let pattern = Regex::new(r"tracker\.example\.com").unwrap();
+
+        let matches = find_matches(&pattern, html);
+
+        assert_eq!(1, matches.len());
+        assert_eq!(3, matches[0].line);
+        assert_eq!(
+            "<html> <head> <script src=\"https://tracker.example.com/old.js\"></script> </head> </html>",
+            matches[0].snippet
+        );
+    }
+
+    #[test]
+    fn test_matches_are_capped_per_page() {
+        let html = "teh ".repeat(MATCHES_KEPT_PER_PAGE * 2);
+        let pattern = Regex::new("teh").unwrap();
+
+        assert_eq!(MATCHES_KEPT_PER_PAGE, find_matches(&pattern, &html).len());
+    }
+}
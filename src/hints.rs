@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+const PRELOAD_REL: &str = "preload";
+const PREFETCH_REL: &str = "prefetch";
+const PRECONNECT_REL: &str = "preconnect";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HintRel {
+    Preload,
+    Prefetch,
+    Preconnect,
+}
+
+impl HintRel {
+    pub fn from_rel(rel: &str) -> Option<Self> {
+        match rel.to_ascii_lowercase().as_str() {
+            PRELOAD_REL => Some(HintRel::Preload),
+            PREFETCH_REL => Some(HintRel::Prefetch),
+            PRECONNECT_REL => Some(HintRel::Preconnect),
+            _ => None,
+        }
+    }
+
+    // Preconnect only names an origin to open a connection to - there's nothing there to fetch.
+    pub fn has_fetchable_target(self) -> bool {
+        self != HintRel::Preconnect
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResourceHint {
+    pub rel: HintRel,
+    pub href: String,
+    #[serde(rename = "as", skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+}
+
+// Each page's <link rel="preload|prefetch|preconnect"> hints, and what fetching each hinted resource returned.
+#[derive(Default, Serialize)]
+pub struct ResourceHintReport {
+    pub hints_by_page: BTreeMap<String, Vec<ResourceHint>>,
+    pub target_statuses: BTreeMap<String, String>,
+}
+
+impl ResourceHintReport {
+    pub fn record_hints(&mut self, page: String, hints: Vec<ResourceHint>) {
+        if !hints.is_empty() {
+            self.hints_by_page.insert(page, hints);
+        }
+    }
+
+    // Returns false if the target has already been checked (or is being checked) for another page.
+    pub fn start_target_check(&mut self, target: &str) -> bool {
+        if self.target_statuses.contains_key(target) {
+            return false;
+        }
+
+        self.target_statuses.insert(target.to_string(), String::new());
+        true
+    }
+
+    pub fn record_target_status(&mut self, target: String, status: String) {
+        self.target_statuses.insert(target, status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_rels_are_case_insensitive() {
+        assert_eq!(Some(HintRel::Preload), HintRel::from_rel("PreLoad"));
+        assert_eq!(None, HintRel::from_rel("stylesheet"));
+        assert!(!HintRel::Preconnect.has_fetchable_target());
+    }
+
+    #[test]
+    fn test_targets_are_only_checked_once() {
+        let mut report = ResourceHintReport::default();
+
+        assert!(report.start_target_check("https://cdn.example.com/font.woff2"));
+        assert!(!report.start_target_check("https://cdn.example.com/font.woff2"));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+// Where the crawl was when it reached its host limit, for deciding whether to go past it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostLimitReached {
+    pub max_hosts: usize,
+    // The hosts crawled so far, the seeds' included.
+    pub hosts: Vec<String>,
+    pub new_host: String,
+}
+
+// Asked at most once a crawl, the first time a link would take it past the host limit. Returning true lifts the limit
+// for the rest of the crawl - otherwise links to new hosts are skipped from then on. It can block, e.g. on a prompt, so
+// it's run on a blocking thread and never while the guard is locked.
+pub type HostLimitConfirmation = Arc<dyn Fn(&HostLimitReached) -> bool + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostAdmission {
+    Admitted,
+    Refused,
+    // Past the limit, and the confirmation hasn't been answered yet. The caller asks it and records the answer.
+    Unconfirmed(HostLimitReached),
+}
+
+// Counts the hosts a crawl has followed links onto, so a scope wider than intended - every subdomain of a hosting
+// provider's domain, say - is caught before it's crawling hundreds of other people's sites.
+#[derive(Default)]
+pub struct HostGuard {
+    // 0 for no limit.
+    max_hosts: usize,
+    hosts: BTreeSet<String>,
+    confirmation: Option<HostLimitConfirmation>,
+    confirmed: Option<bool>,
+}
+
+impl HostGuard {
+    // The seeds' hosts are always crawled, and count towards the limit.
+    pub fn new(
+        max_hosts: usize,
+        confirmation: Option<HostLimitConfirmation>,
+        seed_hosts: impl IntoIterator<Item = String>,
+    ) -> Self {
+        HostGuard { max_hosts, hosts: seed_hosts.into_iter().collect(), confirmation, confirmed: None }
+    }
+
+    pub fn admit(&mut self, host: &str) -> HostAdmission {
+        if self.hosts.contains(host) || self.max_hosts == 0 || self.hosts.len() < self.max_hosts {
+            self.hosts.insert(host.to_string());
+            return HostAdmission::Admitted;
+        }
+
+        let host_limit_reached = HostLimitReached {
+            max_hosts: self.max_hosts,
+            hosts: self.hosts.iter().cloned().collect(),
+            new_host: host.to_string(),
+        };
+        match self.confirmed {
+            Some(true) => {
+                self.hosts.insert(host.to_string());
+                HostAdmission::Admitted
+            }
+            Some(false) => HostAdmission::Refused,
+            None if self.confirmation.is_some() => HostAdmission::Unconfirmed(host_limit_reached),
+            None => {
+                self.record_confirmation(&host_limit_reached, false);
+                HostAdmission::Refused
+            }
+        }
+    }
+
+    pub fn confirmation(&self) -> Option<HostLimitConfirmation> {
+        self.confirmation.clone()
+    }
+
+    // Only the first answer counts, in case the confirmation was asked more than once.
+    pub fn record_confirmation(&mut self, host_limit_reached: &HostLimitReached, confirmed: bool) {
+        if self.confirmed.is_some() {
+            return;
+        }
+
+        self.confirmed = Some(confirmed);
+        if confirmed {
+            self.hosts.insert(host_limit_reached.new_host.clone());
+        } else {
+            let HostLimitReached { max_hosts, new_host, .. } = host_limit_reached;
+            eprintln!("Reached the limit of {} hosts at {} - links to new hosts are skipped", max_hosts, new_host);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn hosts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_new_hosts_past_the_limit_are_refused() {
+        let mut host_guard = HostGuard::new(2, None, hosts(&["example.com"]));
+
+        assert_eq!(HostAdmission::Admitted, host_guard.admit("blog.example.com"));
+        assert_eq!(HostAdmission::Refused, host_guard.admit("shop.example.com"));
+        assert_eq!(HostAdmission::Admitted, host_guard.admit("example.com"));
+        assert_eq!(HostAdmission::Admitted, host_guard.admit("blog.example.com"));
+        assert_eq!(HostAdmission::Admitted, HostGuard::default().admit("shop.example.com"));
+    }
+
+    #[test]
+    fn test_new_hosts_wait_for_the_confirmation_without_it_being_called() {
+        let asked = Arc::new(AtomicUsize::new(0));
+        let confirmation_asked = asked.clone();
+        let confirmation: HostLimitConfirmation = Arc::new(move |_| {
+            confirmation_asked.fetch_add(1, Ordering::SeqCst);
+            true
+        });
+        let mut host_guard = HostGuard::new(1, Some(confirmation), hosts(&["example.com"]));
+
+        let HostAdmission::Unconfirmed(host_limit_reached) = host_guard.admit("blog.example.com") else {
+            panic!("Expected the new host to wait for the confirmation");
+        };
+        assert_eq!(hosts(&["example.com"]), host_limit_reached.hosts);
+        assert_eq!("blog.example.com", host_limit_reached.new_host);
+        assert!(matches!(host_guard.admit("shop.example.com"), HostAdmission::Unconfirmed(_)));
+        assert_eq!(0, asked.load(Ordering::SeqCst));
+
+        host_guard.record_confirmation(&host_limit_reached, true);
+        host_guard.record_confirmation(&host_limit_reached, false);
+        assert_eq!(HostAdmission::Admitted, host_guard.admit("blog.example.com"));
+        assert_eq!(HostAdmission::Admitted, host_guard.admit("shop.example.com"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use reqwest::Url;
+
+const ROOT_SECTION: &str = "/";
+
+// One URL per line, for piping into other tools.
+pub fn render_url_list(links: &BTreeSet<String>) -> String {
+    links.iter().fold(String::new(), |mut url_list, link| {
+        let _ = writeln!(url_list, "{}", link);
+        url_list
+    })
+}
+
+// The same URLs as a readable inventory, grouped by the first segment of their path so each part of the site can be
+// skimmed on its own. Pages on more than one host are grouped by host as well.
+pub fn render_url_markdown(links: &BTreeSet<String>) -> String {
+    let mut links_by_section: BTreeMap<(String, String), Vec<&str>> = BTreeMap::new();
+    for link in links {
+        links_by_section.entry(section_of(link)).or_default().push(link);
+    }
+
+    let hosts: BTreeSet<&String> = links_by_section.keys().map(|(host, _)| host).collect();
+    let mut markdown = String::new();
+    let _ = writeln!(markdown, "# Crawled URLs ({})", links.len());
+
+    for ((host, section), section_links) in &links_by_section {
+        let heading = if hosts.len() > 1 { format!("{}{}", host, section) } else { section.clone() };
+        let _ = writeln!(markdown, "\n## {} ({})\n", heading, section_links.len());
+
+        for link in section_links {
+            let _ = writeln!(markdown, "- <{}>", link);
+        }
+    }
+
+    markdown
+}
+
+// "https://example.com/docs/intro" is in "/docs". Pages directly under the root, and links that aren't valid URLs,
+// are in "/".
+fn section_of(link: &str) -> (String, String) {
+    let Ok(url) = Url::parse(link) else {
+        return (String::new(), ROOT_SECTION.to_string());
+    };
+
+    let host = url.host_str().unwrap_or_default().to_string();
+    let mut segments = url.path().split('/').filter(|segment| !segment.is_empty());
+
+    match (segments.next(), segments.next()) {
+        (Some(first_segment), Some(_)) => (host, format!("/{}", first_segment)),
+        _ => (host, ROOT_SECTION.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_are_grouped_by_section() {
+        let links = BTreeSet::from([
+            String::from("https://example.com"),
+            String::from("https://example.com/about"),
+            String::from("https://example.com/docs/intro"),
+            String::from("https://example.com/docs/setup/linux"),
+        ]);
+
+        assert_eq!(
+            "# Crawled URLs (4)\n\n\
+             ## / (2)\n\n- <https://example.com>\n- <https://example.com/about>\n\n\
+             ## /docs (2)\n\n- <https://example.com/docs/intro>\n- <https://example.com/docs/setup/linux>\n",
+            render_url_markdown(&links)
+        );
+        assert_eq!(4, render_url_list(&links).lines().count());
+    }
+
+    #[test]
+    fn test_sections_are_split_by_host_when_there_are_several() {
+        let links = BTreeSet::from([
+            String::from("https://example.com/docs/intro"),
+            String::from("https://blog.example.com/posts/first"),
+        ]);
+
+        let markdown = render_url_markdown(&links);
+
+        assert!(markdown.contains("## blog.example.com/posts (1)"));
+        assert!(markdown.contains("## example.com/docs (1)"));
+    }
+}
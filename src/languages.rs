@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+// hreflang's catch-all for visitors matching no other language, so there's nothing to compare it with.
+const X_DEFAULT_HREFLANG: &str = "x-default";
+// BCP 47's tag for content in an undetermined language, used for pages that don't say.
+pub const UNDETERMINED_LANGUAGE: &str = "und";
+// Languages written without spaces between words, so their words can't be counted by splitting on whitespace -
+// each character is counted instead.
+const UNSPACED_LANGUAGES: [&str; 6] = ["ja", "km", "lo", "my", "th", "zh"];
+
+// The language a page says it's in, from its Content-Language header and its <html lang> attribute, along with the
+// direction its text is written in (<html dir>).
+#[derive(Clone, Serialize, Debug, Default, PartialEq)]
+pub struct PageLanguage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html_lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html_dir: Option<String>,
+}
+
+// The pages in one language and how many words they have between them. Word counts only compare within a language -
+// a page of Japanese and a page of English with the same count aren't the same length.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct LanguageContent {
+    pub pages: usize,
+    pub words: usize,
+    pub average_words: usize,
+}
+
+// A page that an hreflang annotation on another page (or itself) declares to be in a language it doesn't claim.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LanguageMismatch {
+    pub page: String,
+    pub declared_by: String,
+    pub hreflang: String,
+    #[serde(flatten)]
+    pub language: PageLanguage,
+}
+
+#[derive(Serialize)]
+pub struct LanguageReport<'a> {
+    pub languages_by_page: &'a BTreeMap<String, PageLanguage>,
+    pub mismatches: Vec<LanguageMismatch>,
+    // Keyed by language tag, with "und" for pages that don't declare one.
+    pub content_by_language: BTreeMap<String, LanguageContent>,
+}
+
+#[derive(Default)]
+pub struct LanguageSignals {
+    languages_by_page: BTreeMap<String, PageLanguage>,
+    // Page -> hreflang -> the page it points at.
+    hreflangs_by_page: BTreeMap<String, BTreeMap<String, String>>,
+    content_by_language: BTreeMap<String, LanguageContent>,
+}
+
+impl LanguageSignals {
+    pub fn record_page(
+        &mut self,
+        page: String,
+        language: PageLanguage,
+        hreflangs: BTreeMap<String, String>,
+        page_text: &str,
+    ) {
+        if !hreflangs.is_empty() {
+            self.hreflangs_by_page.insert(page.clone(), hreflangs);
+        }
+
+        let language_tag = language.language_tag();
+        let content = self.content_by_language.entry(language_tag.clone()).or_default();
+        content.pages += 1;
+        content.words += count_words(page_text, &language_tag);
+
+        self.languages_by_page.insert(page, language);
+    }
+
+    // hreflang targets are only checked once they've been crawled - there's nothing to compare against otherwise.
+    pub fn build_report(&self) -> LanguageReport<'_> {
+        let mut mismatches = Vec::new();
+
+        for (declared_by, hreflangs) in self.hreflangs_by_page.iter() {
+            for (hreflang, target) in hreflangs.iter().filter(|(hreflang, _)| *hreflang != X_DEFAULT_HREFLANG) {
+                let Some(language) = self.languages_by_page.get(target) else {
+                    continue;
+                };
+
+                if !language.matches(hreflang) {
+                    mismatches.push(LanguageMismatch {
+                        page: target.clone(),
+                        declared_by: declared_by.clone(),
+                        hreflang: hreflang.clone(),
+                        language: language.clone(),
+                    });
+                }
+            }
+        }
+
+        let content_by_language = self
+            .content_by_language
+            .iter()
+            .map(|(language_tag, content)| {
+                let average_words = content.words / content.pages.max(1);
+                (language_tag.clone(), LanguageContent { average_words, ..*content })
+            })
+            .collect();
+
+        LanguageReport { languages_by_page: &self.languages_by_page, mismatches, content_by_language }
+    }
+}
+
+impl PageLanguage {
+    // The language the page's content is in, normalised for grouping: <html lang> describes the document itself, so
+    // it wins over the first language in Content-Language.
+    pub fn language_tag(&self) -> String {
+        let content_language = self.content_language.as_deref().and_then(|languages| languages.split(',').next());
+
+        self.html_lang
+            .as_deref()
+            .or(content_language)
+            .map(|language| language.trim().to_ascii_lowercase().replace('_', "-"))
+            .filter(|language| !language.is_empty())
+            .unwrap_or_else(|| UNDETERMINED_LANGUAGE.to_string())
+    }
+
+    // Content-Language can list several languages, and only has to include the declared one. A page that doesn't
+    // say what language it's in can't contradict anything.
+    fn matches(&self, hreflang: &str) -> bool {
+        let html_lang_matches = self.html_lang.as_deref().is_none_or(|html_lang| languages_match(html_lang, hreflang));
+        let content_language_matches = self.content_language.as_deref().is_none_or(|content_language| {
+            content_language.split(',').any(|language| languages_match(language, hreflang))
+        });
+
+        html_lang_matches && content_language_matches
+    }
+}
+
+// Tags are compared case-insensitively, and a bare language matches any region of it ("en" and "en-GB"), but two
+// different regions ("en-US" and "en-GB") don't match.
+fn languages_match(first: &str, second: &str) -> bool {
+    let first = first.trim().to_ascii_lowercase().replace('_', "-");
+    let second = second.trim().to_ascii_lowercase().replace('_', "-");
+
+    first == second
+        || second.strip_prefix(&first).is_some_and(|rest| rest.starts_with('-'))
+        || first.strip_prefix(&second).is_some_and(|rest| rest.starts_with('-'))
+}
+
+fn count_words(text: &str, language_tag: &str) -> usize {
+    let primary_language = language_tag.split('-').next().unwrap_or_default();
+
+    if UNSPACED_LANGUAGES.contains(&primary_language) {
+        text.chars().filter(|character| character.is_alphanumeric()).count()
+    } else {
+        text.split_whitespace().filter(|word| word.chars().any(char::is_alphanumeric)).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_tags_match_across_case_and_region() {
+        assert!(languages_match("en", "en-GB"));
+        assert!(languages_match("de_DE", "de-de"));
+        assert!(!languages_match("en-US", "en-GB"));
+        assert!(!languages_match("en", "eo"));
+    }
+
+    #[test]
+    fn test_hreflang_mismatches_are_reported() {
+        let mut signals = LanguageSignals::default();
+        let hreflangs = BTreeMap::from([
+            (String::from("en"), String::from("https://example.com/en")),
+            (String::from("de"), String::from("https://example.com/de")),
+            (String::from("fr"), String::from("https://example.com/fr")),
+            (String::from("x-default"), String::from("https://example.com")),
+        ]);
+        let language = |content_language: Option<&str>, html_lang: Option<&str>| PageLanguage {
+            content_language: content_language.map(str::to_string),
+            html_lang: html_lang.map(str::to_string),
+            html_dir: None,
+        };
+
+        let en_page = String::from("https://example.com/en");
+        signals.record_page(en_page, language(Some("en-GB, en"), Some("en")), hreflangs, "");
+        let de_page = String::from("https://example.com/de");
+        signals.record_page(de_page, language(Some("en"), Some("de")), BTreeMap::new(), "");
+        signals.record_page(String::from("https://example.com"), language(None, Some("en")), BTreeMap::new(), "");
+
+        let report = signals.build_report();
+
+        assert_eq!(1, report.mismatches.len());
+        assert_eq!("https://example.com/de", report.mismatches[0].page);
+        assert_eq!("de", report.mismatches[0].hreflang);
+    }
+
+    #[test]
+    fn test_word_counts_are_grouped_by_language() {
+        let mut signals = LanguageSignals::default();
+        let language = |content_language: Option<&str>, html_lang: Option<&str>| PageLanguage {
+            content_language: content_language.map(str::to_string),
+            html_lang: html_lang.map(str::to_string),
+            html_dir: None,
+        };
+
+        let pages = [
+            ("a", language(None, Some("en")), "Hello there, world - again"),
+            ("b", language(Some("en"), None), "Hi"),
+            ("ja", language(None, Some("ja")), "日本語 です"),
+            ("c", language(None, None), "One two"),
+        ];
+        for (page, page_language, page_text) in pages {
+            signals.record_page(format!("https://example.com/{}", page), page_language, BTreeMap::new(), page_text);
+        }
+
+        let report = signals.build_report();
+
+        assert_eq!(LanguageContent { pages: 2, words: 5, average_words: 2 }, report.content_by_language["en"]);
+        assert_eq!(LanguageContent { pages: 1, words: 5, average_words: 5 }, report.content_by_language["ja"]);
+        assert_eq!(2, report.content_by_language[UNDETERMINED_LANGUAGE].words);
+    }
+
+    #[test]
+    fn test_html_lang_wins_over_content_language() {
+        let language = PageLanguage {
+            content_language: Some(String::from("fr-CA, en")),
+            html_lang: Some(String::from("de_AT")),
+            html_dir: None,
+        };
+
+        assert_eq!("de-at", language.language_tag());
+        assert_eq!("fr-ca", PageLanguage { html_lang: None, ..language }.language_tag());
+        assert_eq!(UNDETERMINED_LANGUAGE, PageLanguage::default().language_tag());
+    }
+}
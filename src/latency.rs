@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use reqwest::Url;
+
+// Buckets grow by 10% from 100µs, which covers everything up to a couple of minutes in a fixed amount of memory.
+// Percentiles are reported as the upper edge of their bucket, so they can be up to 10% high.
+const BUCKET_COUNT: usize = 150;
+const SMALLEST_BUCKET_MICROS: f64 = 100.0;
+const BUCKET_GROWTH: f64 = 1.1;
+const ID_SEGMENT: &str = "{id}";
+
+#[derive(Clone, Debug)]
+pub struct LatencyHistogram {
+    counts: Vec<usize>,
+    total: usize,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram { counts: vec![0; BUCKET_COUNT], total: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.counts[bucket_index(elapsed)] += 1;
+        self.total += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.total
+    }
+
+    // `quantile` is between 0 and 1, e.g. 0.9 for p90.
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        let rank = ((quantile * self.total as f64).ceil() as usize).max(1);
+        let mut seen = 0;
+
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper_bound(index);
+            }
+        }
+
+        Duration::ZERO
+    }
+}
+
+fn bucket_index(elapsed: Duration) -> usize {
+    let micros = elapsed.as_secs_f64() * 1_000_000.0;
+
+    if micros <= SMALLEST_BUCKET_MICROS {
+        return 0;
+    }
+
+    let index = (micros / SMALLEST_BUCKET_MICROS).log(BUCKET_GROWTH).ceil() as usize;
+    index.min(BUCKET_COUNT - 1)
+}
+
+fn bucket_upper_bound(index: usize) -> Duration {
+    Duration::from_secs_f64(SMALLEST_BUCKET_MICROS * BUCKET_GROWTH.powi(index as i32) / 1_000_000.0)
+}
+
+// Groups URLs that are the same kind of page - numeric or hex-looking path segments are treated as IDs, so
+// /product/123 and /product/456 are both /product/{id}.
+pub fn path_template(link: &str) -> String {
+    let Ok(parsed_url) = Url::parse(link) else {
+        return String::from("/");
+    };
+
+    let segments: Vec<&str> = parsed_url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+
+    let template: Vec<&str> = segments
+        .into_iter()
+        .map(|segment| if is_id_segment(segment) { ID_SEGMENT } else { segment })
+        .collect();
+
+    format!("/{}", template.join("/"))
+}
+
+fn is_id_segment(segment: &str) -> bool {
+    let is_numeric = segment.chars().all(|character| character.is_ascii_digit());
+    let is_long_hex = segment.len() >= 16 && segment.chars().all(|character| character.is_ascii_hexdigit());
+
+    is_numeric || is_long_hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_are_within_a_bucket() {
+        let mut histogram = LatencyHistogram::default();
+
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+
+        let p50 = histogram.percentile(0.5).as_secs_f64() * 1000.0;
+        let p99 = histogram.percentile(0.99).as_secs_f64() * 1000.0;
+
+        assert_eq!(100, histogram.count());
+        assert!((50.0..=55.0).contains(&p50), "p50 was {}", p50);
+        assert!((99.0..=109.0).contains(&p99), "p99 was {}", p99);
+    }
+
+    #[test]
+    fn test_path_template_replaces_ids() {
+        assert_eq!("/product/{id}/reviews", path_template("https://example.com/product/123/reviews"));
+        assert_eq!("/blog/{id}", path_template("https://example.com/blog/5f2c9a7e3b1d4c6a?page=2"));
+        assert_eq!("/about", path_template("https://example.com/about/"));
+        assert_eq!("/", path_template("https://example.com"));
+    }
+}
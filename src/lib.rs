@@ -0,0 +1,58 @@
+// The crawler as a library, for embedding in another Tokio program. The binary in main.rs is a thin CLI over it.
+
+mod alternates;
+mod archive;
+mod assets;
+pub mod builder;
+pub mod config;
+pub mod crawler;
+mod directives;
+pub mod dns;
+mod duplicates;
+pub mod embedding;
+mod encoding;
+pub mod errors;
+mod etags;
+mod expected;
+pub mod filters;
+pub mod forms;
+pub mod frontier;
+mod graph;
+mod grep;
+pub mod host_guard;
+mod hints;
+pub mod inventory;
+mod languages;
+mod latency;
+mod limits;
+mod link_header;
+mod listings;
+pub mod memory;
+pub mod metadata;
+pub mod multi_site;
+pub mod normalize;
+pub mod output;
+mod pacing;
+mod pagination;
+mod politeness;
+mod preflight;
+mod ranges;
+pub mod redirects;
+pub mod replay;
+mod robots;
+pub mod sections;
+mod session_params;
+mod sitemaps;
+mod soft404;
+mod stats;
+mod summary;
+pub mod testsite;
+mod throughput;
+mod visited;
+
+pub use crate::builder::{CrawlerBuilder, CrawlerOptions};
+pub use crate::crawler::{
+    extract_links, resolve_link, Crawler, LinkKind, PageResult, RequestHook, SkipReason, WebCrawler,
+};
+pub use crate::errors::CrawlError;
+pub use crate::summary::CrawlReport;
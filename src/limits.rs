@@ -0,0 +1,52 @@
+use std::fs::read_to_string;
+
+const PROC_LIMITS_PATH: &str = "/proc/self/limits";
+const OPEN_FILES_FIELD: &str = "Max open files";
+const UNLIMITED: &str = "unlimited";
+// Kept back for everything that isn't a page connection - stdio, output and spill files, robots.txt and DNS lookups.
+const RESERVED_FILE_DESCRIPTORS: u64 = 64;
+
+// How many pages can be fetched at once without running out of file descriptors, as each one holds a connection
+// open. Only Linux exposes the limit without extra dependencies, so elsewhere there's no cap.
+pub fn connection_limit() -> Option<usize> {
+    let open_files_limit = parse_open_files_limit(&read_to_string(PROC_LIMITS_PATH).ok()?)?;
+
+    Some(connection_limit_for(open_files_limit))
+}
+
+fn connection_limit_for(open_files_limit: u64) -> usize {
+    let connections = open_files_limit.saturating_sub(RESERVED_FILE_DESCRIPTORS).max(1);
+
+    usize::try_from(connections).unwrap_or(usize::MAX)
+}
+
+// The soft limit is the one the process actually hits, so the hard limit next to it is ignored.
+fn parse_open_files_limit(proc_limits: &str) -> Option<u64> {
+    let line = proc_limits.lines().find(|line| line.starts_with(OPEN_FILES_FIELD))?;
+    let soft_limit = line[OPEN_FILES_FIELD.len()..].split_whitespace().next()?;
+
+    if soft_limit == UNLIMITED {
+        return None;
+    }
+
+    soft_limit.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limit_from_soft_open_files_limit() {
+        let proc_limits = "Limit                     Soft Limit           Hard Limit           Units     \n\
+                           Max processes             23960                23960                processes \n\
+                           Max open files            1024                 1048576              files     \n";
+        let open_files_limit = parse_open_files_limit(proc_limits).unwrap();
+
+        assert_eq!(1024, open_files_limit);
+        assert_eq!(960, connection_limit_for(open_files_limit));
+        assert_eq!(1, connection_limit_for(16));
+        let unlimited_line = "Max open files            unlimited            unlimited            files";
+        assert_eq!(None, parse_open_files_limit(unlimited_line));
+    }
+}
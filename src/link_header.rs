@@ -0,0 +1,97 @@
+const REL_PARAM: &str = "rel";
+
+// One entry of a Link response header, e.g. `<https://example.com/items?page=2>; rel="next"`. The target is left as
+// written - it's resolved against the page like any other href.
+#[derive(Debug, PartialEq)]
+pub struct HeaderLink {
+    pub href: String,
+    pub rels: Vec<String>,
+}
+
+impl HeaderLink {
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rels.iter().any(|link_rel| link_rel == rel)
+    }
+}
+
+// Entries are comma separated, but a comma can also appear inside the <...> target or a quoted parameter, so the
+// value is split by hand rather than on every comma. Entries without a target are dropped.
+pub fn parse_link_header(value: &str) -> Vec<HeaderLink> {
+    let mut header_links = Vec::new();
+    let mut rest = value;
+
+    while let Some(target_start) = rest.find('<') {
+        let Some(target_length) = rest[target_start..].find('>') else {
+            break;
+        };
+        let href = rest[target_start + 1..target_start + target_length].trim().to_string();
+        rest = &rest[target_start + target_length + 1..];
+
+        let params_end = entry_end(rest);
+        let rels = rel_values(&rest[..params_end]);
+        rest = &rest[params_end..];
+
+        header_links.push(HeaderLink { href, rels });
+    }
+
+    header_links
+}
+
+// Where the current entry's parameters stop - the first comma outside quotes.
+fn entry_end(params: &str) -> usize {
+    let mut in_quotes = false;
+
+    for (index, character) in params.char_indices() {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => return index,
+            _ => {}
+        }
+    }
+
+    params.len()
+}
+
+// rel can hold several space separated relations, and like the HTML attribute it's case-insensitive.
+fn rel_values(params: &str) -> Vec<String> {
+    params
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case(REL_PARAM))
+        .flat_map(|(_, rel_value)| {
+            rel_value.trim().trim_matches('"').split_ascii_whitespace().map(str::to_ascii_lowercase).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_header_entries_are_parsed() {
+        let header_links = parse_link_header(
+            "</items?page=3&sort=a,b>; rel=\"next\", <https://example.com/items>; title=\"x, y\"; REL=\"Canonical\", \
+             </items.json>; rel=alternate; type=\"application/json\", <https://fonts.example.com>",
+        );
+
+        assert_eq!(
+            vec![
+                HeaderLink { href: String::from("/items?page=3&sort=a,b"), rels: vec![String::from("next")] },
+                HeaderLink { href: String::from("https://example.com/items"), rels: vec![String::from("canonical")] },
+                HeaderLink { href: String::from("/items.json"), rels: vec![String::from("alternate")] },
+                HeaderLink { href: String::from("https://fonts.example.com"), rels: Vec::new() },
+            ],
+            header_links
+        );
+    }
+
+    #[test]
+    fn test_an_entry_can_have_several_rels() {
+        let header_links = parse_link_header("</archive/2>; rel=\"next archives\"");
+
+        assert!(header_links[0].has_rel("next"));
+        assert!(header_links[0].has_rel("archives"));
+        assert!(parse_link_header("no links here").is_empty());
+    }
+}
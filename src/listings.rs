@@ -0,0 +1,54 @@
+use serde::Serialize;
+
+const INDEX_OF_TITLE_PREFIX: &str = "Index of /";
+const DIRECTORY_LISTING_TITLE_PREFIX: &str = "Directory listing for /";
+// mod_autoindex's column sorting links and server signature.
+const APACHE_SORT_LINK: &str = "?C=N;O=D";
+const APACHE_ADDRESS: &str = "<address>Apache";
+// nginx's autoindex is a bare <pre> block that always starts with the parent directory.
+const NGINX_PARENT_LINK: &str = "<pre><a href=\"../\">../</a>";
+
+// Which server generated an auto-index page, going by the markup it's known to produce.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListingServer {
+    Apache,
+    Nginx,
+    // An "Index of /..." or "Directory listing for /..." page from anything else, e.g. lighttpd or Python's
+    // http.server.
+    Other,
+}
+
+// Auto-index pages are recognised by their title, which every common server fills in the same way, and then
+// attributed to a server by its markup.
+pub fn detect_directory_listing(title: &str, html: &str) -> Option<ListingServer> {
+    let title = title.trim();
+    if !title.starts_with(INDEX_OF_TITLE_PREFIX) && !title.starts_with(DIRECTORY_LISTING_TITLE_PREFIX) {
+        return None;
+    }
+
+    if html.contains(APACHE_SORT_LINK) || html.contains(APACHE_ADDRESS) {
+        Some(ListingServer::Apache)
+    } else if html.replace(['\r', '\n'], "").contains(NGINX_PARENT_LINK) {
+        Some(ListingServer::Nginx)
+    } else {
+        Some(ListingServer::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_servers_are_detected() {
+        let apache_html = "<h1>Index of /files</h1><table><tr><th><a href=\"?C=N;O=D\">Name</a></th></tr></table>";
+        let nginx_html = "<h1>Index of /files/</h1><hr><pre><a href=\"../\">../</a>\n<a href=\"a.txt\">a.txt</a>";
+        let python_html = "<h1>Directory listing for /files/</h1><ul><li><a href=\"a.txt\">a.txt</a></li></ul>";
+
+        assert_eq!(Some(ListingServer::Apache), detect_directory_listing("Index of /files", apache_html));
+        assert_eq!(Some(ListingServer::Nginx), detect_directory_listing("Index of /files/", nginx_html));
+        assert_eq!(Some(ListingServer::Other), detect_directory_listing("Directory listing for /files/", python_html));
+        assert_eq!(None, detect_directory_listing("Index of products", "<h1>Index of products</h1>"));
+    }
+}
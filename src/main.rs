@@ -1,37 +1,1056 @@
-use std::env::args;
-use std::time::{Duration, Instant};
+use std::collections::BTreeSet;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use regex::Regex;
 use reqwest::Url;
-use crate::crawler::{Crawler, WebCrawler};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use crawling::{CrawlReport, Crawler, RequestHook, WebCrawler};
+use crawling::config::{parse_config, read_config, site_configs, CrawlConfig, SiteConfig};
+use crawling::dns::AddressFamily;
+use crawling::embedding::HtmlEmbedding;
+use crawling::filters::{parse_blocked_host, parse_url_pattern, UrlPattern};
+use crawling::forms::parse_form_value;
+use crawling::frontier::FrontierStrategy;
+use crawling::host_guard::{HostLimitConfirmation, HostLimitReached};
+use crawling::inventory::{render_url_list, render_url_markdown};
+use crawling::memory::{parse_body_size, parse_chunk_size, parse_memory_limit, MemoryBudget};
+use crawling::metadata::parse_label;
+use crawling::multi_site::{MultiSiteCrawl, Site, SiteOutcome};
+use crawling::normalize::{parse_seed_url, UrlNormalization};
+use crawling::output::{print_text, read_artifact, OutputCompression, OutputRotation};
+use crawling::redirects::OffDomainRedirects;
+use crawling::replay::{load_run_manifest, RunManifest};
+use crawling::sections::{parse_section_rule, SectionRule};
+use crawling::testsite::{SiteSpec, SyntheticSite};
 
-extern crate lazy_static;
+const DEFAULT_TEST_SITE_PORT: u16 = 8000;
+const CRAWL: &str = "crawl";
+const CHECK_LINKS: &str = "check-links";
 
-mod crawler;
+#[derive(Parser)]
+#[command(version, about = "Crawls a site and reports on its links, errors and structure")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Crawl a site from one or more seed URLs, following their links")]
+    Crawl {
+        #[arg(value_parser = parse_seed_url, help = "Where the crawl starts - the config's seeds if left out")]
+        urls: Vec<Url>,
+        #[arg(
+            long,
+            value_name = "ERRORS_JSON",
+            help = "Re-fetch only the URLs in a previous run's errors.json, and merge the results into that run"
+        )]
+        only_failed: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Don't follow links in an earlier run's all_links.json, or a plain list of URLs"
+        )]
+        seen_links: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "RUN_MANIFEST_JSON",
+            conflicts_with_all = ["urls", "only_failed", "seen_links"],
+            help = "Run an earlier crawl again from its run_manifest.json - only --output-dir is taken from here"
+        )]
+        replay: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "With sites in the --config file, the most requests in flight across all of them"
+        )]
+        total_concurrency: Option<usize>,
+        #[command(flatten)]
+        crawl_args: CrawlArgs,
+    },
+    #[command(
+        about = "Fetch only the URLs listed in a file, without following their links",
+        override_usage = "crawling check-links [OPTIONS] [URL] <URL_LIST>"
+    )]
+    CheckLinks {
+        // clap can't put an optional positional before a required one, so the two are split up after parsing.
+        #[arg(
+            required = true,
+            num_args = 1..=2,
+            value_names = ["URL", "URL_LIST"],
+            help = "The site the URLs are on, whose robots.txt is read (the config's first seed if left out), then a \
+                file with one URL per line, # comments allowed"
+        )]
+        url_and_list: Vec<String>,
+        #[command(flatten)]
+        crawl_args: CrawlArgs,
+    },
+    #[command(about = "Write a previous run's all_links.json out as a plain URL list or a Markdown inventory")]
+    Export {
+        #[arg(help = "all_links.json from a previous run, compressed or not")]
+        all_links: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Text)]
+        format: ExportFormat,
+        #[arg(long, help = "Where to write it - standard output by default. A .gz or .zst extension compresses it")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Serve a generated site locally, to check and demo the crawler against")]
+    ServeTestSite(TestSiteArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Text,
+    Markdown,
+}
+
+// Everything crawl and check-links have in common.
+#[derive(Args, Clone)]
+struct CrawlArgs {
+    #[arg(long, value_name = "FILE", help = "Read settings from a TOML file, e.g. crawler.toml - flags given here win")]
+    config: Option<PathBuf>,
+    #[arg(long, value_name = "DIR", help = "Write the outputs here instead of the working directory")]
+    output_dir: Option<PathBuf>,
+    #[arg(long, help = "The User-Agent sent with every request, and matched against robots.txt")]
+    user_agent: Option<String>,
+    #[arg(long, value_name = "ARCHIVE_DIR", help = "Crawl a saved mirror instead of the live site")]
+    offline: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "The order pages are fetched in: breadth-first, depth-first, shallow-paths-first, random or \
+                sitemap-priority"
+    )]
+    strategy: Option<FrontierStrategy>,
+    #[arg(long, help = "Seed the random strategy's order with this, to repeat a run's order - one is picked otherwise")]
+    random_seed: Option<u64>,
+    #[arg(long, value_name = "ETAGS_JSON", help = "Flag pages whose ETag hasn't changed since an earlier run")]
+    previous_etags: Option<PathBuf>,
+    #[arg(long, help = "Compress the outputs as they're written: gzip or zstd")]
+    compress: Option<OutputCompression>,
+    #[arg(long, value_name = "RECORDS", help = "Split links_by_page.ndjson into chunks of at most this many pages")]
+    chunk_records: Option<usize>,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_chunk_size,
+        help = "Split links_by_page.ndjson into chunks of at most this size before compression, e.g. 100M"
+    )]
+    chunk_size: Option<u64>,
+    #[arg(long, value_name = "FILE", help = "Also write the aggregate statistics to a separate JSON file")]
+    summary_json: Option<String>,
+    #[arg(
+        long,
+        value_name = "KEY=VALUE",
+        value_parser = parse_label,
+        help = "Tag the run, e.g. env=prod, in every output's metadata - can be repeated"
+    )]
+    label: Vec<(String, String)>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Check the crawl against the URLs that should exist, listed one per line or as an all_links.json"
+    )]
+    expected_urls: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Seed the crawl with the pages listed in the site's XML sitemaps"
+    )]
+    sitemaps: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Start crawling without first checking that the seeds' sites are reachable"
+    )]
+    skip_preflight: Option<bool>,
+    #[arg(long, value_name = "NAME", help = "Record this response header for each page - can be repeated")]
+    capture_header: Vec<String>,
+    #[arg(
+        long,
+        value_name = "NAME: VALUE",
+        value_parser = parse_request_header,
+        help = "Send this header with every request - can be repeated"
+    )]
+    request_header: Vec<(String, String)>,
+    #[arg(
+        long,
+        value_name = "NAME=VALUE",
+        value_parser = parse_form_value,
+        help = "Fill in this GET form field when expanding forms - can be repeated"
+    )]
+    form_value: Vec<(String, String)>,
+    #[arg(long, value_name = "URL", help = "Resolve hosts through this DNS-over-HTTPS provider's JSON API")]
+    dns_over_https: Option<String>,
+    #[arg(long, help = "Which addresses to connect to: any, prefer-ipv4, prefer-ipv6, ipv4 or ipv6")]
+    ip_family: Option<AddressFamily>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Fetch each page's next page before the rest of the frontier"
+    )]
+    prioritize_pagination: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Fetch one page at a time in a fixed order, so runs can be compared"
+    )]
+    deterministic: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Request each media alternate once and record its status"
+    )]
+    check_alternates: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Follow the entries of autoindex directory listings"
+    )]
+    follow_directory_listings: Option<bool>,
+    #[arg(long, value_name = "ENCODING", help = "Embed each page's HTML in links_by_page.ndjson: raw or gzip")]
+    embed_html: Option<HtmlEmbedding>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Write the headers every request was sent with to request_headers.json"
+    )]
+    debug_request_headers: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Request only the first kilobyte of each URL and record its full size"
+    )]
+    sample_ranges: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Follow links to other subdomains of the seed's registrable domain"
+    )]
+    include_subdomains: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Treat http:// and https:// links as different sites instead of the same pages"
+    )]
+    strict_scheme: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Skip links written without a scheme (//host/path) instead of following them"
+    )]
+    skip_protocol_relative: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Follow rel=nofollow links, and links on pages whose meta robots tag or header says nofollow"
+    )]
+    ignore_nofollow: Option<bool>,
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_url_pattern,
+        help = "Only queue links matching this regex, or glob:/path/** - can be repeated"
+    )]
+    include: Vec<UrlPattern>,
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = parse_url_pattern,
+        help = "Never queue links matching this regex, or glob:/path/** - can be repeated"
+    )]
+    exclude: Vec<UrlPattern>,
+    #[arg(
+        long,
+        value_name = "HOST",
+        value_parser = parse_blocked_host,
+        help = "Never crawl this host or its subdomains, whatever the scope - can be repeated"
+    )]
+    block_host: Vec<String>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Drop utm_* and click ID parameters from links before comparing them"
+    )]
+    strip_tracking_params: Option<bool>,
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Sort links' query parameters by name before comparing them"
+    )]
+    sort_query_params: Option<bool>,
+    #[arg(long, value_name = "PATTERN", help = "Record where each page's HTML matches this regex")]
+    grep: Option<Regex>,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_memory_limit,
+        help = "Keep memory use under this, e.g. 512M or 2G"
+    )]
+    memory_limit: Option<MemoryBudget>,
+    #[arg(long, help = "The most requests in flight at once")]
+    concurrency: Option<usize>,
+    #[arg(
+        long,
+        value_name = "BYTES_PER_SEC",
+        help = "Give up on responses arriving slower than this after the first second - 0 turns it off [default: 1024]"
+    )]
+    min_throughput: Option<u64>,
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_body_size,
+        help = "Skip responses larger than this, e.g. 10M - 0 turns it off [default: 50M]"
+    )]
+    max_body_size: Option<u64>,
+    #[arg(long, help = "The most redirects followed for one request [default: 10]")]
+    max_redirects: Option<usize>,
+    #[arg(long, value_name = "POLICY", help = "Whether redirects off the domain are followed: follow or stop")]
+    off_domain_redirects: Option<OffDomainRedirects>,
+    #[arg(
+        long,
+        value_name = "RULE",
+        value_parser = parse_section_rule,
+        help = "Limit part of the site, e.g. /blog:depth=2 - can be repeated"
+    )]
+    section: Vec<SectionRule>,
+    #[arg(long, help = "The least time between requests to the same host")]
+    delay_ms: Option<u64>,
+    #[arg(long, help = "The most random time added to each wait for a host")]
+    jitter_ms: Option<u64>,
+    #[arg(long, help = "The most link hops from the seed to follow")]
+    max_depth: Option<usize>,
+    #[arg(long, help = "Stop the crawl after fetching this many pages")]
+    max_pages: Option<usize>,
+    #[arg(long, help = "Ask before following links onto more than this many hosts - 0 for no limit [default: 20]")]
+    max_hosts: Option<usize>,
+}
+
+#[derive(Args)]
+struct TestSiteArgs {
+    #[arg(long, default_value_t = DEFAULT_TEST_SITE_PORT)]
+    port: u16,
+    #[arg(long)]
+    pages: Option<usize>,
+    #[arg(long)]
+    branching: Option<usize>,
+    #[arg(long)]
+    broken_links: Option<usize>,
+    #[arg(long)]
+    redirect_chains: Option<usize>,
+    #[arg(long)]
+    redirect_chain_length: Option<usize>,
+    #[arg(long, value_name = "PATH", help = "Disallow this path in robots.txt - can be repeated")]
+    disallow: Vec<String>,
+}
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    let args: Vec<String> = args().collect();
+    let arguments = std::env::args_os().skip(1).map(|argument| argument.to_string_lossy().into_owned());
+    let run_manifest = RunManifest { arguments: arguments.collect(), config: None };
+
+    match Cli::parse().command {
+        Command::Crawl { replay: Some(manifest_path), crawl_args, .. } => {
+            let (command, run_manifest) = replay_command(&manifest_path, crawl_args.output_dir);
+            run(command, run_manifest).await
+        }
+        command => run(command, run_manifest).await,
+    }
+}
+
+async fn run(command: Command, mut run_manifest: RunManifest) -> std::io::Result<()> {
+    match command {
+        Command::Crawl { urls, only_failed, seen_links, total_concurrency, crawl_args, .. } => {
+            let (site_configs, config_total_concurrency) =
+                with_site_configs(&crawl_args, &mut run_manifest).unwrap_or_else(|err| usage_error(CRAWL, err));
+            if !site_configs.is_empty() {
+                if !urls.is_empty() || only_failed.is_some() || seen_links.is_some() {
+                    let message = "Seed URLs, --only-failed and --seen-links can't be used with sites in the config";
+                    usage_error(CRAWL, String::from(message));
+                }
+
+                let (mut multi_site_crawl, summary_json_paths) = with_sites(site_configs, crawl_args, run_manifest)
+                    .unwrap_or_else(|err| usage_error(CRAWL, err));
+                match total_concurrency.or(config_total_concurrency) {
+                    Some(0) => usage_error(CRAWL, String::from("The total concurrency must be at least 1")),
+                    Some(total_concurrency) => {
+                        multi_site_crawl = multi_site_crawl.with_total_concurrency(total_concurrency);
+                    }
+                    None => {}
+                }
+
+                crawl_sites(multi_site_crawl, summary_json_paths).await;
+                return Ok(());
+            }
+
+            if total_concurrency.is_some() {
+                usage_error(CRAWL, String::from("--total-concurrency only applies to sites in the --config file"));
+            }
+
+            // The results are merged into the previous run's directory, so there's nowhere else for them to go.
+            if only_failed.is_some() && crawl_args.output_dir.is_some() {
+                let message = "--only-failed writes into the previous run's directory, so --output-dir can't be used";
+                usage_error(CRAWL, String::from(message));
+            }
+
+            let (seed_urls, crawl_args) =
+                with_config(urls, crawl_args, &mut run_manifest).unwrap_or_else(|err| usage_error(CRAWL, err));
+
+            let summary_json_path = crawl_args.summary_json.clone();
+            let mut crawler = build_crawler(crawl_args, cancel_on_ctrl_c())
+                .unwrap_or_else(|err| usage_error(CRAWL, err))
+                .with_run_manifest(run_manifest);
+
+            if let Some(only_failed_path) = only_failed {
+                crawler = crawler.with_only_failed(&only_failed_path);
+            }
+
+            if let Some(seen_links_path) = seen_links {
+                crawler = crawler.with_seen_links(&seen_links_path);
+            }
+
+            crawl(crawler, seed_urls, summary_json_path).await;
+        }
+        Command::CheckLinks { mut url_and_list, crawl_args } => {
+            let url_list = PathBuf::from(url_and_list.pop().expect("clap requires the URL list."));
+            let url = url_and_list.first().map(|url| parse_seed_url(url));
+            let url = url.transpose().unwrap_or_else(|err| usage_error(CHECK_LINKS, err));
+            let (mut seed_urls, crawl_args) = with_config(url.into_iter().collect(), crawl_args, &mut run_manifest)
+                .unwrap_or_else(|err| usage_error(CHECK_LINKS, err));
+
+            // The list replaces the seeds - only the first is kept, for its robots.txt.
+            seed_urls.truncate(1);
+            let summary_json_path = crawl_args.summary_json.clone();
+            let crawler = build_crawler(crawl_args, cancel_on_ctrl_c())
+                .unwrap_or_else(|err| usage_error(CHECK_LINKS, err))
+                .with_run_manifest(run_manifest);
+            crawl(crawler.with_url_list(&url_list), seed_urls, summary_json_path).await;
+        }
+        Command::Export { all_links, format, output } => export(&all_links, format, output.as_deref()),
+        Command::ServeTestSite(test_site_args) => return serve_test_site(test_site_args).await,
+    }
+
+    Ok(())
+}
 
-    if args.len() < 1 {
-        println!("Valid URL required as command line arg");
-        return Ok(());
+// Parses a run_manifest.json's command line again, as if it had just been typed, along with the config file's contents
+// and the random seed it recorded. Relative paths on that command line are read from the working directory.
+fn replay_command(manifest_path: &Path, output_dir: Option<PathBuf>) -> (Command, RunManifest) {
+    let artifact = load_run_manifest(manifest_path).unwrap_or_else(|err| usage_error(CRAWL, err));
+    let metadata = artifact.metadata;
+    let run_manifest = artifact.data;
+
+    let crawler_version = env!("CARGO_PKG_VERSION");
+    if let Some(metadata) = metadata.as_ref().filter(|metadata| metadata.crawler_version != crawler_version) {
+        let made_by = &metadata.crawler_version;
+        eprintln!("Replaying a run made by crawler {} with {} - it may not go the same way", made_by, crawler_version);
+    }
+
+    let arguments = std::iter::once(env!("CARGO_BIN_NAME").to_string()).chain(run_manifest.arguments.iter().cloned());
+    let mut command = Cli::try_parse_from(arguments).unwrap_or_else(|err| err.exit()).command;
+    let random_seed = metadata.and_then(|metadata| metadata.settings.random_seed);
+
+    match &mut command {
+        Command::Crawl { crawl_args, .. } | Command::CheckLinks { crawl_args, .. } => {
+            crawl_args.output_dir = output_dir.or(crawl_args.output_dir.take());
+            crawl_args.random_seed = crawl_args.random_seed.or(random_seed);
+        }
+        _ => usage_error(CRAWL, format!("{} doesn't record a crawl", manifest_path.display())),
+    }
+
+    (command, run_manifest)
+}
+
+// Input that only turns out to be bad once it's combined with the config file is reported the way clap reports its
+// own errors: the reason and the subcommand's usage on stderr, exiting with status 2.
+fn usage_error(subcommand_name: &str, message: String) -> ! {
+    let mut cli_command = Cli::command();
+    cli_command.build();
+
+    let subcommand = cli_command.find_subcommand_mut(subcommand_name).expect("Unknown subcommand.");
+    subcommand.error(ErrorKind::InvalidValue, message).exit()
+}
+
+// The sites in the config file, each with its own settings, and the config's total-concurrency. No sites without a
+// config, or with one that doesn't list any.
+fn with_site_configs(
+    crawl_args: &CrawlArgs,
+    run_manifest: &mut RunManifest,
+) -> Result<(Vec<SiteConfig>, Option<usize>), String> {
+    let Some(config_path) = &crawl_args.config else {
+        return Ok((Vec::new(), None));
+    };
+
+    if run_manifest.config.is_none() {
+        run_manifest.config = Some(read_config(config_path)?);
     }
 
-    let target_url_arg = &args[1];
-    let target_url = Url::parse(target_url_arg).unwrap().to_string();
+    let config = run_manifest.config.as_deref().unwrap_or_default();
+    let total_concurrency = parse_config(config, config_path)?.total_concurrency;
+    Ok((site_configs(config, config_path)?, total_concurrency))
+}
+
+// Each site's crawler is built from its settings and the command line, the way a single crawl's would be, and writes
+// to its own directory under the output directory. A --summary-json path is taken to be inside that directory too.
+fn with_sites(
+    site_configs: Vec<SiteConfig>,
+    crawl_args: CrawlArgs,
+    run_manifest: RunManifest,
+) -> Result<(MultiSiteCrawl, Vec<Option<String>>), String> {
+    let cancellation_token = cancel_on_ctrl_c();
+    let mut multi_site_crawl = MultiSiteCrawl::new();
+    let mut summary_json_paths = Vec::new();
+
+    for SiteConfig { name: site_name, config: site_config } in site_configs {
+        let seed_urls = site_config.seeds.iter().map(|seed| parse_seed_url(seed)).collect::<Result<Vec<_>, _>>()?;
+        if seed_urls.is_empty() {
+            return Err(format!("Site '{}' has no seeds", site_name));
+        }
+
+        let mut site_args = apply_config(crawl_args.clone(), site_config)?;
+        let output_dir = site_args.output_dir.take().unwrap_or_default().join(&site_name);
+        summary_json_paths.push(site_args.summary_json.take().map(|path| output_dir.join(path).display().to_string()));
+        site_args.output_dir = Some(output_dir);
+
+        let crawler = build_crawler(site_args, cancellation_token.clone())?.with_run_manifest(run_manifest.clone());
+        multi_site_crawl = multi_site_crawl.with_site(Site { name: site_name, crawler, seed_urls });
+    }
+
+    Ok((multi_site_crawl, summary_json_paths))
+}
+
+// Reads the config file, if there is one, into whatever wasn't given on the command line. Seed URLs given there
+// replace the config's seeds, and a repeatable flag replaces the config's list rather than adding to it. Never returns
+// an empty list of seeds. The file's contents are kept in the run manifest - a replay brings them along, rather than
+// reading the file as it is now.
+fn with_config(
+    urls: Vec<Url>,
+    crawl_args: CrawlArgs,
+    run_manifest: &mut RunManifest,
+) -> Result<(Vec<Url>, CrawlArgs), String> {
+    let config = match &crawl_args.config {
+        Some(config_path) => {
+            if run_manifest.config.is_none() {
+                run_manifest.config = Some(read_config(config_path)?);
+            }
+
+            parse_config(run_manifest.config.as_deref().unwrap_or_default(), config_path)?
+        }
+        None => CrawlConfig::default(),
+    };
+
+    if !config.sites.is_empty() {
+        return Err(String::from("The sites in the config are only crawled by crawl, without seed URLs"));
+    }
 
-    let crawler = WebCrawler::new();
+    if config.total_concurrency.is_some() {
+        return Err(String::from("total-concurrency in the config only applies to its sites"));
+    }
+
+    let seed_urls = if urls.is_empty() {
+        config.seeds.iter().map(|seed| parse_seed_url(seed)).collect::<Result<_, _>>()?
+    } else {
+        urls
+    };
+
+    if seed_urls.is_empty() {
+        return Err(String::from("No seed URL - give one, or list seeds in the --config file"));
+    }
+
+    Ok((seed_urls, apply_config(crawl_args, config)?))
+}
+
+fn apply_config(mut crawl_args: CrawlArgs, config: CrawlConfig) -> Result<CrawlArgs, String> {
+    crawl_args.output_dir = crawl_args.output_dir.or(config.output_dir);
+    crawl_args.user_agent = crawl_args.user_agent.or(config.user_agent);
+    crawl_args.max_depth = crawl_args.max_depth.or(config.max_depth);
+    crawl_args.max_pages = crawl_args.max_pages.or(config.max_pages);
+    crawl_args.max_hosts = crawl_args.max_hosts.or(config.max_hosts);
+    crawl_args.concurrency = crawl_args.concurrency.or(config.concurrency);
+    crawl_args.min_throughput = crawl_args.min_throughput.or(config.min_throughput);
+    if crawl_args.max_body_size.is_none() {
+        crawl_args.max_body_size = config.max_body_size.as_deref().map(parse_body_size).transpose()?;
+    }
+    crawl_args.embed_html = crawl_args.embed_html.or(config.embed_html);
+    crawl_args.max_redirects = crawl_args.max_redirects.or(config.max_redirects);
+    crawl_args.off_domain_redirects = crawl_args.off_domain_redirects.or(config.off_domain_redirects);
+    crawl_args.delay_ms = crawl_args.delay_ms.or(config.delay_ms);
+    crawl_args.jitter_ms = crawl_args.jitter_ms.or(config.jitter_ms);
+    crawl_args.strategy = crawl_args.strategy.or(config.strategy);
+    crawl_args.random_seed = crawl_args.random_seed.or(config.random_seed);
+    crawl_args.compress = crawl_args.compress.or(config.compress);
+    crawl_args.chunk_records = crawl_args.chunk_records.or(config.chunk_records);
+    crawl_args.summary_json = crawl_args.summary_json.or(config.summary_json);
+    crawl_args.expected_urls = crawl_args.expected_urls.or(config.expected_urls);
+    crawl_args.dns_over_https = crawl_args.dns_over_https.or(config.dns_over_https);
+    crawl_args.ip_family = crawl_args.ip_family.or(config.ip_family);
+
+    // A switch given on the command line wins either way - --sitemaps=false turns off one set in the file.
+    crawl_args.sitemaps = crawl_args.sitemaps.or(config.sitemaps);
+    crawl_args.skip_preflight = crawl_args.skip_preflight.or(config.skip_preflight);
+    crawl_args.prioritize_pagination = crawl_args.prioritize_pagination.or(config.prioritize_pagination);
+    crawl_args.deterministic = crawl_args.deterministic.or(config.deterministic);
+    crawl_args.check_alternates = crawl_args.check_alternates.or(config.check_alternates);
+    crawl_args.follow_directory_listings = crawl_args.follow_directory_listings.or(config.follow_directory_listings);
+    crawl_args.debug_request_headers = crawl_args.debug_request_headers.or(config.debug_request_headers);
+    crawl_args.sample_ranges = crawl_args.sample_ranges.or(config.sample_ranges);
+    crawl_args.include_subdomains = crawl_args.include_subdomains.or(config.include_subdomains);
+    crawl_args.strict_scheme = crawl_args.strict_scheme.or(config.strict_scheme);
+    crawl_args.skip_protocol_relative = crawl_args.skip_protocol_relative.or(config.skip_protocol_relative);
+    crawl_args.ignore_nofollow = crawl_args.ignore_nofollow.or(config.ignore_nofollow);
+    crawl_args.strip_tracking_params = crawl_args.strip_tracking_params.or(config.strip_tracking_params);
+    crawl_args.sort_query_params = crawl_args.sort_query_params.or(config.sort_query_params);
+
+    if crawl_args.section.is_empty() {
+        crawl_args.section = config.sections.iter().map(|rule| parse_section_rule(rule)).collect::<Result<_, _>>()?;
+    }
+
+    if crawl_args.capture_header.is_empty() {
+        crawl_args.capture_header = config.capture_headers;
+    }
+
+    if crawl_args.request_header.is_empty() {
+        crawl_args.request_header = config.request_headers.into_iter().collect();
+    }
+
+    if crawl_args.label.is_empty() {
+        crawl_args.label = config.labels.into_iter().collect();
+    }
+
+    if crawl_args.include.is_empty() {
+        crawl_args.include = config.include.iter().map(|pattern| parse_url_pattern(pattern)).collect::<Result<_, _>>()?;
+    }
+
+    if crawl_args.exclude.is_empty() {
+        crawl_args.exclude = config.exclude.iter().map(|pattern| parse_url_pattern(pattern)).collect::<Result<_, _>>()?;
+    }
+
+    if crawl_args.block_host.is_empty() {
+        let blocked_hosts = config.block_hosts.iter().map(|blocked_host| parse_blocked_host(blocked_host));
+        crawl_args.block_host = blocked_hosts.collect::<Result<_, _>>()?;
+    }
+
+    if crawl_args.form_value.is_empty() {
+        crawl_args.form_value = config.form_values.into_iter().collect();
+    }
+
+    if crawl_args.grep.is_none() {
+        crawl_args.grep = config
+            .grep
+            .map(|pattern| Regex::new(&pattern))
+            .transpose()
+            .map_err(|err| format!("Invalid grep pattern in config: {}", err))?;
+    }
+
+    if crawl_args.memory_limit.is_none() {
+        crawl_args.memory_limit = config.memory_limit.as_deref().map(parse_memory_limit).transpose()?;
+    }
+
+    if crawl_args.chunk_size.is_none() {
+        crawl_args.chunk_size = config.chunk_size.as_deref().map(parse_chunk_size).transpose()?;
+    }
+
+    Ok(crawl_args)
+}
+
+fn build_crawler(crawl_args: CrawlArgs, cancellation_token: CancellationToken) -> Result<WebCrawler, String> {
+    let mut builder = WebCrawler::builder();
+    if let Some(user_agent) = &crawl_args.user_agent {
+        builder = builder.with_user_agent(user_agent);
+    }
+
+    if let Some(concurrency) = crawl_args.concurrency {
+        builder = builder.with_concurrency(concurrency);
+    }
+
+    if let Some(min_throughput) = crawl_args.min_throughput {
+        builder = builder.with_min_throughput(min_throughput);
+    }
+
+    if let Some(max_body_size) = crawl_args.max_body_size {
+        builder = builder.with_max_body_size(max_body_size);
+    }
 
+    if let Some(max_redirects) = crawl_args.max_redirects {
+        builder = builder.with_max_redirects(max_redirects);
+    }
+
+    if let Some(off_domain_redirects) = crawl_args.off_domain_redirects {
+        builder = builder.with_off_domain_redirects(off_domain_redirects);
+    }
+
+    if let Some(max_depth) = crawl_args.max_depth {
+        builder = builder.with_max_depth(max_depth);
+    }
+
+    if let Some(max_pages) = crawl_args.max_pages {
+        builder = builder.with_max_pages(max_pages);
+    }
+
+    if let Some(max_hosts) = crawl_args.max_hosts {
+        builder = builder.with_max_hosts(max_hosts);
+    }
+
+    if let Some(delay_ms) = crawl_args.delay_ms {
+        builder = builder.with_host_delay(Duration::from_millis(delay_ms));
+    }
+
+    if let Some(jitter_ms) = crawl_args.jitter_ms {
+        builder = builder.with_jitter(Duration::from_millis(jitter_ms));
+    }
+
+    for section_rule in crawl_args.section {
+        builder = builder.with_section_rule(section_rule);
+    }
+
+    if let Some(archive_dir) = crawl_args.offline {
+        builder = builder.with_archive(archive_dir);
+    }
+
+    let mut crawler = builder
+        .build()?
+        .with_frontier_strategy(crawl_args.strategy.unwrap_or_default())
+        .with_output_compression(crawl_args.compress.unwrap_or_default())
+        .with_links_by_page_to_file(true)
+        .with_links_by_page_rotation(OutputRotation {
+            max_records: crawl_args.chunk_records,
+            max_bytes: crawl_args.chunk_size,
+        })
+        .with_captured_headers(crawl_args.capture_header)
+        .with_address_family(crawl_args.ip_family.unwrap_or_default())
+        .with_pagination_priority(crawl_args.prioritize_pagination.unwrap_or_default())
+        .with_deterministic(crawl_args.deterministic.unwrap_or_default())
+        .with_alternate_checks(crawl_args.check_alternates.unwrap_or_default())
+        .with_form_values(crawl_args.form_value)
+        .with_labels(crawl_args.label)
+        .with_directory_listings_followed(crawl_args.follow_directory_listings.unwrap_or_default())
+        .with_request_headers_debug(crawl_args.debug_request_headers.unwrap_or_default())
+        .with_range_sampling(crawl_args.sample_ranges.unwrap_or_default())
+        .with_subdomains(crawl_args.include_subdomains.unwrap_or_default())
+        .with_strict_scheme(crawl_args.strict_scheme.unwrap_or_default())
+        .with_protocol_relative_links_skipped(crawl_args.skip_protocol_relative.unwrap_or_default())
+        .with_nofollow_ignored(crawl_args.ignore_nofollow.unwrap_or_default())
+        .with_include_patterns(crawl_args.include)
+        .with_exclude_patterns(crawl_args.exclude)
+        .with_blocked_hosts(crawl_args.block_host)
+        .with_url_normalization(UrlNormalization {
+            strip_tracking_params: crawl_args.strip_tracking_params.unwrap_or_default(),
+            sort_query_params: crawl_args.sort_query_params.unwrap_or_default(),
+        })
+        .with_sitemaps(crawl_args.sitemaps.unwrap_or_default())
+        .with_preflight(!crawl_args.skip_preflight.unwrap_or_default())
+        .with_cancellation_token(cancellation_token);
+
+    if !crawl_args.request_header.is_empty() {
+        crawler = crawler.with_request_hook(add_request_headers(crawl_args.request_header));
+    }
+
+    // Only asked when someone's there to answer - otherwise links to new hosts past the limit are skipped.
+    if std::io::stdin().is_terminal() {
+        crawler = crawler.with_host_limit_confirmation(confirm_host_limit());
+    }
+
+    if let Some(dns_over_https_provider) = crawl_args.dns_over_https {
+        crawler = crawler.with_dns_over_https(&dns_over_https_provider);
+    }
+
+    if let Some(grep_pattern) = crawl_args.grep {
+        crawler = crawler.with_grep_pattern(grep_pattern);
+    }
+
+    if let Some(html_embedding) = crawl_args.embed_html {
+        crawler = crawler.with_embedded_html(html_embedding);
+    }
+
+    if let Some(random_seed) = crawl_args.random_seed {
+        crawler = crawler.with_random_seed(random_seed);
+    }
+
+    if let Some(memory_budget) = crawl_args.memory_limit {
+        crawler = crawler.with_memory_budget(memory_budget);
+    }
+
+    if let Some(previous_etags_path) = crawl_args.previous_etags {
+        crawler = crawler.with_previous_etags(&previous_etags_path);
+    }
+
+    if let Some(expected_urls_path) = crawl_args.expected_urls {
+        crawler = crawler.with_expected_urls(&expected_urls_path);
+    }
+
+    if let Some(output_dir) = crawl_args.output_dir {
+        crawler = crawler.with_output_dir(&output_dir);
+    }
+
+    Ok(crawler)
+}
+
+async fn crawl(crawler: WebCrawler, seed_urls: Vec<Url>, summary_json_path: Option<String>) {
     println!("Starting scrape...");
 
-    let start = Instant::now();
-    let _ = crawler.scrape_site(target_url).await;
-    let duration: Duration = start.elapsed();
+    let crawl_report = match crawler.scrape_sites(seed_urls).await {
+        Ok(crawl_report) => crawl_report,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    report_crawl(&crawler, crawl_report, summary_json_path);
+}
+
+// A site that fails to start doesn't stop the others, but still fails the run once they're done.
+async fn crawl_sites(multi_site_crawl: MultiSiteCrawl, summary_json_paths: Vec<Option<String>>) {
+    println!("Starting scrape of {} sites...", summary_json_paths.len());
+
+    let mut any_failed = false;
+    let site_outcomes = multi_site_crawl.crawl().await;
+    for (SiteOutcome { site, crawl_report }, summary_json_path) in site_outcomes.into_iter().zip(summary_json_paths) {
+        println!();
+        println!("Site {}", site.name);
+        match crawl_report {
+            Ok(crawl_report) => report_crawl(&site.crawler, crawl_report, summary_json_path),
+            Err(err) => {
+                eprintln!("{}", err);
+                any_failed = true;
+            }
+        }
+    }
+
+    if any_failed {
+        std::process::exit(1);
+    }
+}
+
+fn report_crawl(crawler: &WebCrawler, crawl_report: CrawlReport, summary_json_path: Option<String>) {
+    println!("Time elapsed: {:?}", crawl_report.elapsed);
+    println!(
+        "Visited {} pages, discovered {} links and downloaded {} bytes - {} failed requests, {} error responses",
+        crawl_report.pages_visited,
+        crawl_report.links_discovered,
+        crawl_report.bytes_downloaded,
+        crawl_report.failed_requests,
+        crawl_report.error_responses
+    );
 
-    println!("Time elapsed: {:?}", duration);
+    crawler.print_summary();
+
+    if let Some(summary_json_path) = summary_json_path {
+        crawler.write_summary_json(&summary_json_path);
+    }
 
-    crawler.print_links_by_page(true);
+    crawler.print_url_inventory(true);
+    crawler.print_expected_urls(true);
     crawler.print_all_links(true);
+    crawler.print_etags(true);
+    crawler.print_errors(true);
+    crawler.print_manifest(true);
+    crawler.print_robots_blocked(true);
+    crawler.print_skipped(true);
+    crawler.print_pagination(true);
+    crawler.print_duplicates(true);
+    crawler.print_grep_matches(true);
+    crawler.print_resource_hints(true);
+    crawler.print_media_alternates(true);
+    crawler.print_forms(true);
+    crawler.print_assets_by_page(true);
+    crawler.print_noindex_pages(true);
+    crawler.print_directory_listings(true);
+    crawler.print_languages(true);
+    crawler.print_soft_404s(true);
+    crawler.print_request_headers(true);
+    crawler.print_resource_sizes(true);
+    crawler.print_robots(true);
+    crawler.print_sitemaps(true);
+    crawler.print_politeness(true);
+    crawler.print_session_params(true);
+    crawler.print_redirect_audit(true);
+    crawler.print_redirect_aliases(true);
+    crawler.print_redirect_map(true);
+    crawler.print_click_depths(true);
+    crawler.print_run_manifest(true);
+}
 
+fn export(all_links_path: &Path, format: ExportFormat, output_path: Option<&Path>) {
+    let Some(all_links) = read_artifact::<BTreeSet<String>>(all_links_path) else {
+        eprintln!("Failed to read links from {}", all_links_path.display());
+        std::process::exit(1);
+    };
 
-    Ok(())
-}
\ No newline at end of file
+    let text = match format {
+        ExportFormat::Text => render_url_list(&all_links),
+        ExportFormat::Markdown => render_url_markdown(&all_links),
+    };
+
+    let Some(output_path) = output_path else {
+        print!("{}", text);
+        return;
+    };
+
+    // The compression's extension is added back as the file is written.
+    let compression = OutputCompression::from_path(output_path);
+    let uncompressed_path = match compression {
+        OutputCompression::None => output_path.to_path_buf(),
+        _ => output_path.with_extension(""),
+    };
+    print_text(&text, &uncompressed_path, true, compression);
+}
+
+fn parse_request_header(request_header: &str) -> Result<(String, String), String> {
+    match request_header.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("Invalid request header '{}', expected 'Name: value'", request_header)),
+    }
+}
+
+// Sites crawled at once take turns asking.
+static HOST_LIMIT_PROMPT: Mutex<()> = Mutex::new(());
+
+fn confirm_host_limit() -> HostLimitConfirmation {
+    Arc::new(|host_limit_reached: &HostLimitReached| {
+        let _prompt = HOST_LIMIT_PROMPT.lock().unwrap_or_else(PoisonError::into_inner);
+        let hosts = host_limit_reached.hosts.join(", ");
+        eprintln!("The crawl has reached its limit of {} hosts: {}", host_limit_reached.max_hosts, hosts);
+        eprint!("Keep following links onto new hosts, starting with {}? [y/N] ", host_limit_reached.new_host);
+
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    })
+}
+
+fn add_request_headers(request_headers: Vec<(String, String)>) -> RequestHook {
+    Arc::new(move |request, _| {
+        request_headers.iter().fold(request, |request, (name, value)| request.header(name, value))
+    })
+}
+
+// The first Ctrl-C stops the crawl but still writes out what it found; a second one quits straight away.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancellation_token = CancellationToken::new();
+    let ctrl_c_token = cancellation_token.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("Stopping the crawl and writing results - press Ctrl-C again to quit without them.");
+            ctrl_c_token.cancel();
+        }
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+
+    cancellation_token
+}
+
+// Developer tool - serves a generated site locally so crawler behaviour can be checked and demoed deterministically.
+async fn serve_test_site(test_site_args: TestSiteArgs) -> std::io::Result<()> {
+    let default_spec = SiteSpec::default();
+    let disallowed_paths =
+        if test_site_args.disallow.is_empty() { default_spec.disallowed_paths } else { test_site_args.disallow };
+    let spec = SiteSpec {
+        page_count: test_site_args.pages.unwrap_or(default_spec.page_count),
+        branching_factor: test_site_args.branching.unwrap_or(default_spec.branching_factor),
+        broken_links: test_site_args.broken_links.unwrap_or(default_spec.broken_links),
+        redirect_chains: test_site_args.redirect_chains.unwrap_or(default_spec.redirect_chains),
+        redirect_chain_length: test_site_args.redirect_chain_length.unwrap_or(default_spec.redirect_chain_length),
+        disallowed_paths,
+    };
+
+    let site = SyntheticSite::generate(&spec);
+    let listener = TcpListener::bind(("127.0.0.1", test_site_args.port)).await?;
+
+    println!("Serving {} synthetic pages at http://localhost:{}", site.page_count(), test_site_args.port);
+
+    Arc::new(site).serve(listener).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_switches_given_on_the_command_line_win_over_the_config() {
+        let sitemaps = |arguments: &[&str]| {
+            let command = Cli::parse_from([&["crawling", "crawl", "https://example.com/"], arguments].concat()).command;
+            let Command::Crawl { crawl_args, .. } = command else {
+                unreachable!("Parsed as another subcommand.");
+            };
+
+            let config = CrawlConfig { sitemaps: Some(true), ..Default::default() };
+            apply_config(crawl_args, config).unwrap().sitemaps
+        };
+
+        assert_eq!(Some(false), sitemaps(&["--sitemaps=false"]));
+        assert_eq!(Some(true), sitemaps(&["--sitemaps"]));
+        assert_eq!(Some(true), sitemaps(&[]));
+    }
+
+    async fn offline_pages_visited(arguments: &[&str]) -> usize {
+        let command = ["crawling", "crawl", "https://example.com/", "--offline", "resources/archive"];
+        let Command::Crawl { crawl_args, .. } = Cli::parse_from([&command, arguments].concat()).command else {
+            unreachable!("Parsed as another subcommand.");
+        };
+
+        let crawler = build_crawler(crawl_args, CancellationToken::new()).unwrap();
+        let seed_url = Url::parse("https://example.com/").unwrap();
+        crawler.with_links_by_page_to_file(false).scrape_sites(vec![seed_url]).await.unwrap().pages_visited
+    }
+
+    #[tokio::test]
+    async fn test_offline_crawls_keep_their_limits() {
+        assert_eq!(2, offline_pages_visited(&[]).await);
+        assert_eq!(1, offline_pages_visited(&["--max-depth", "0"]).await);
+        assert_eq!(1, offline_pages_visited(&["--max-pages", "1"]).await);
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs::read_to_string;
+
+const PROC_STATUS_PATH: &str = "/proc/self/status";
+const RESIDENT_SET_FIELD: &str = "VmRSS:";
+// Leaves some headroom for the pages already being fetched when the limit is approached.
+const PRESSURE_THRESHOLD_PERCENT: u64 = 90;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryBudget {
+    pub limit_bytes: u64,
+}
+
+impl MemoryBudget {
+    // Only Linux exposes the resident set size cheaply, so elsewhere the budget is never considered under pressure.
+    pub fn is_under_pressure(&self) -> bool {
+        resident_memory_bytes().is_some_and(|used_bytes| self.is_over_threshold(used_bytes))
+    }
+
+    fn is_over_threshold(&self, used_bytes: u64) -> bool {
+        used_bytes.saturating_mul(100) >= self.limit_bytes.saturating_mul(PRESSURE_THRESHOLD_PERCENT)
+    }
+}
+
+pub fn parse_memory_limit(limit: &str) -> Result<MemoryBudget, String> {
+    match parse_size(limit) {
+        Some(limit_bytes) if limit_bytes > 0 => Ok(MemoryBudget { limit_bytes }),
+        _ => Err(format!("Invalid memory limit '{}', expected e.g. 512M or 2G", limit.trim())),
+    }
+}
+
+// 0 reads bodies whatever their size.
+pub fn parse_body_size(size: &str) -> Result<u64, String> {
+    parse_size(size).ok_or_else(|| format!("Invalid body size '{}', expected e.g. 10M, or 0 for no limit", size.trim()))
+}
+
+pub fn parse_chunk_size(size: &str) -> Result<u64, String> {
+    match parse_size(size) {
+        Some(chunk_bytes) if chunk_bytes > 0 => Ok(chunk_bytes),
+        _ => Err(format!("Invalid chunk size '{}', expected e.g. 100M", size.trim())),
+    }
+}
+
+// Accepts a plain number of bytes or one with a K, M or G suffix (powers of 1024), e.g. "512M".
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (digits, multiplier) = match size.to_ascii_uppercase().chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+
+    digits.parse::<u64>().ok().map(|amount| amount.saturating_mul(multiplier))
+}
+
+fn resident_memory_bytes() -> Option<u64> {
+    parse_resident_set(&read_to_string(PROC_STATUS_PATH).ok()?)
+}
+
+fn parse_resident_set(proc_status: &str) -> Option<u64> {
+    let line = proc_status.lines().find(|line| line.starts_with(RESIDENT_SET_FIELD))?;
+    let kilobytes: u64 = line[RESIDENT_SET_FIELD.len()..].trim().trim_end_matches("kB").trim().parse().ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_units() {
+        assert_eq!(Ok(MemoryBudget { limit_bytes: 512 * 1024 * 1024 }), parse_memory_limit("512M"));
+        assert_eq!(Ok(MemoryBudget { limit_bytes: 2 * 1024 * 1024 * 1024 }), parse_memory_limit("2g"));
+        assert_eq!(Ok(MemoryBudget { limit_bytes: 4096 }), parse_memory_limit("4096"));
+        assert!(parse_memory_limit("lots").is_err());
+        assert!(parse_memory_limit("0M").is_err());
+        assert_eq!(Ok(0), parse_body_size("0"));
+        assert_eq!(Ok(10 * 1024 * 1024), parse_body_size("10m"));
+        assert_eq!(Ok(100 * 1024 * 1024), parse_chunk_size("100M"));
+        assert!(parse_chunk_size("0").is_err());
+    }
+
+    #[test]
+    fn test_pressure_from_resident_set() {
+        let proc_status = "Name:\tcrawling\nVmPeak:\t  300000 kB\nVmRSS:\t  95000 kB\nThreads:\t8\n";
+        let used_bytes = parse_resident_set(proc_status).unwrap();
+
+        assert_eq!(95000 * 1024, used_bytes);
+        assert!(MemoryBudget { limit_bytes: 100000 * 1024 }.is_over_threshold(used_bytes));
+        assert!(!MemoryBudget { limit_bytes: 200000 * 1024 }.is_over_threshold(used_bytes));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::dns::AddressFamily;
+use crate::embedding::HtmlEmbedding;
+use crate::frontier::FrontierStrategy;
+use crate::normalize::UrlNormalization;
+use crate::output::{OutputCompression, OutputRotation};
+use crate::redirects::OffDomainRedirects;
+use crate::sections::SectionRule;
+
+const CRAWLER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Carried in every output so an artifact still says where it came from once it's been copied elsewhere.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrawlMetadata {
+    pub crawl_id: String,
+    // The first seed. A crawl from several of them lists them all in seed_urls.
+    pub seed_url: String,
+    pub seed_urls: Vec<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub crawler_version: String,
+    // Whatever the run was tagged with, e.g. env=prod or release=v1.2.3, so downstream systems can slice by them.
+    pub labels: BTreeMap<String, String>,
+    pub settings: CrawlSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrawlSettings {
+    pub strategy: FrontierStrategy,
+    // Only for the random strategy - the seed its order came from.
+    pub random_seed: Option<u64>,
+    pub compression: OutputCompression,
+    pub links_by_page_rotation: OutputRotation,
+    pub archive_dir: Option<String>,
+    pub previous_etags: bool,
+    pub follow_links: bool,
+    pub merge_previous_run: bool,
+    pub seen_links: Option<String>,
+    pub expected_urls: Option<String>,
+    pub sitemaps: bool,
+    pub preflight: bool,
+    pub captured_headers: Vec<String>,
+    pub dns_over_https: Option<String>,
+    pub address_family: AddressFamily,
+    pub prioritize_pagination: bool,
+    pub grep_pattern: Option<String>,
+    pub memory_limit_bytes: Option<u64>,
+    pub deterministic: bool,
+    pub check_alternates: bool,
+    pub form_values: BTreeMap<String, Vec<String>>,
+    pub follow_directory_listings: bool,
+    pub embed_html: Option<HtmlEmbedding>,
+    pub debug_request_headers: bool,
+    pub range_sampling: bool,
+    pub url_normalization: UrlNormalization,
+    pub include_subdomains: bool,
+    pub strict_scheme: bool,
+    pub skip_protocol_relative: bool,
+    pub ignore_nofollow: bool,
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub blocked_hosts: Vec<String>,
+    pub user_agent: String,
+    pub request_timeout_ms: u64,
+    pub min_throughput: u64,
+    pub max_body_size: u64,
+    pub max_redirects: usize,
+    pub off_domain_redirects: OffDomainRedirects,
+    pub max_depth: Option<usize>,
+    pub max_pages: Option<usize>,
+    pub max_hosts: usize,
+    pub section_rules: Vec<SectionRule>,
+    pub workers: usize,
+    pub concurrency: Option<usize>,
+    pub host_delay_ms: Option<u64>,
+    pub jitter_ms: Option<u64>,
+}
+
+// The shape of every JSON artifact - the crawl metadata alongside the output's own data.
+#[derive(Serialize, Deserialize)]
+pub struct Artifact<T> {
+    pub metadata: Option<CrawlMetadata>,
+    pub data: T,
+}
+
+// Streamed outputs can't wrap their records, so the metadata goes on the first line instead.
+#[derive(Serialize)]
+pub struct ArtifactHeader {
+    pub metadata: Option<CrawlMetadata>,
+}
+
+impl CrawlMetadata {
+    pub fn start(seed_urls: &[String], settings: CrawlSettings, labels: BTreeMap<String, String>) -> Self {
+        CrawlMetadata {
+            crawl_id: Uuid::new_v4().to_string(),
+            seed_url: seed_urls.first().cloned().unwrap_or_default(),
+            seed_urls: seed_urls.to_vec(),
+            started_at: timestamp_now(),
+            finished_at: None,
+            crawler_version: CRAWLER_VERSION.to_string(),
+            labels,
+            settings,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.finished_at = Some(timestamp_now());
+    }
+}
+
+// Parses "key=value" - the value may itself contain '='.
+pub fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("Invalid label '{}', expected 'key=value'", label)),
+    }
+}
+
+fn timestamp_now() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> CrawlSettings {
+        CrawlSettings {
+            strategy: FrontierStrategy::ShallowPaths,
+            random_seed: None,
+            compression: OutputCompression::None,
+            links_by_page_rotation: OutputRotation::default(),
+            archive_dir: None,
+            previous_etags: false,
+            follow_links: true,
+            merge_previous_run: false,
+            seen_links: None,
+            expected_urls: None,
+            sitemaps: false,
+            preflight: true,
+            captured_headers: Vec::new(),
+            dns_over_https: None,
+            address_family: AddressFamily::default(),
+            prioritize_pagination: false,
+            grep_pattern: None,
+            memory_limit_bytes: None,
+            deterministic: false,
+            check_alternates: false,
+            form_values: BTreeMap::new(),
+            follow_directory_listings: false,
+            embed_html: None,
+            debug_request_headers: false,
+            range_sampling: false,
+            url_normalization: UrlNormalization::default(),
+            include_subdomains: false,
+            strict_scheme: false,
+            skip_protocol_relative: false,
+            ignore_nofollow: false,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            blocked_hosts: Vec::new(),
+            user_agent: String::from("crawler"),
+            request_timeout_ms: 3000,
+            min_throughput: 1024,
+            max_body_size: 50 * 1024 * 1024,
+            max_redirects: 10,
+            off_domain_redirects: OffDomainRedirects::Follow,
+            max_depth: None,
+            max_pages: None,
+            max_hosts: 20,
+            section_rules: Vec::new(),
+            workers: 16,
+            concurrency: None,
+            host_delay_ms: None,
+            jitter_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_metadata_is_finished_with_end_time() {
+        let mut metadata = CrawlMetadata::start(&[String::from("https://example.com")], settings(), BTreeMap::new());
+        assert!(metadata.finished_at.is_none());
+
+        metadata.finish();
+
+        assert!(metadata.finished_at.is_some());
+        assert_eq!(CRAWLER_VERSION, metadata.crawler_version);
+    }
+
+    #[test]
+    fn test_labels_are_parsed_from_key_value_pairs() {
+        assert_eq!(Ok((String::from("env"), String::from("prod"))), parse_label("env=prod"));
+        assert_eq!(Ok((String::from("query"), String::from("a=b"))), parse_label("query=a=b"));
+        assert!(parse_label("=prod").is_err());
+        assert!(parse_label("release").is_err());
+    }
+
+    #[test]
+    fn test_settings_use_cli_names() {
+        let settings_json = serde_json::to_value(settings()).unwrap();
+
+        assert_eq!("shallow-paths-first", settings_json["strategy"]);
+        assert_eq!("none", settings_json["compression"]);
+    }
+}
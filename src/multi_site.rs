@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use reqwest::Url;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::crawler::{Crawler, WebCrawler};
+use crate::summary::CrawlReport;
+
+// One site of a multi-site crawl. Its crawler is its own - settings, visited links, robots.txt rules and output
+// directory included - so nothing one site finds ever reaches another.
+pub struct Site {
+    pub name: String,
+    pub crawler: WebCrawler,
+    pub seed_urls: Vec<Url>,
+}
+
+pub struct SiteOutcome {
+    pub site: Site,
+    pub crawl_report: Result<CrawlReport, String>,
+}
+
+// Crawls several independent sites at once. The only thing they share is an optional limit on the requests in flight
+// across all of them, on top of each site's own concurrency limit.
+#[derive(Default)]
+pub struct MultiSiteCrawl {
+    sites: Vec<Site>,
+    request_budget: Option<Arc<Semaphore>>,
+}
+
+impl MultiSiteCrawl {
+    pub fn new() -> Self {
+        MultiSiteCrawl::default()
+    }
+
+    pub fn with_site(mut self, site: Site) -> Self {
+        self.sites.push(site);
+        self
+    }
+
+    pub fn with_total_concurrency(mut self, total_concurrency: usize) -> Self {
+        self.request_budget = Some(Arc::new(Semaphore::new(total_concurrency)));
+        self
+    }
+
+    // Every site is crawled to the end whether or not the others fail. Outcomes come back in the order the sites were
+    // added, with their crawlers, so their outputs can be written.
+    pub async fn crawl(self) -> Vec<SiteOutcome> {
+        let mut site_crawls = JoinSet::new();
+        for (site_index, mut site) in self.sites.into_iter().enumerate() {
+            if let Some(request_budget) = self.request_budget.as_ref() {
+                site.crawler = site.crawler.with_shared_request_budget(request_budget.clone());
+            }
+
+            site_crawls.spawn(async move {
+                let crawl_report = site.crawler.scrape_sites(site.seed_urls.clone()).await;
+                (site_index, SiteOutcome { site, crawl_report })
+            });
+        }
+
+        let mut site_outcomes = Vec::new();
+        while let Some(site_crawl) = site_crawls.join_next().await {
+            site_outcomes.push(site_crawl.expect("Site crawl panicked."));
+        }
+
+        site_outcomes.sort_by_key(|(site_index, _)| *site_index);
+        site_outcomes.into_iter().map(|(_, site_outcome)| site_outcome).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsite::{SiteSpec, SyntheticSite};
+
+    async fn serve_site(page_count: usize) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let site = SyntheticSite::generate(&SiteSpec { page_count, ..Default::default() });
+        tokio::spawn(Arc::new(site).serve(listener));
+
+        Url::parse(&format!("http://localhost:{}/", port)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sites_are_crawled_with_their_own_state() {
+        let small_site = serve_site(3).await;
+        let large_site = serve_site(12).await;
+        let site = |name: &str, seed_url: &Url| Site {
+            name: name.to_string(),
+            crawler: WebCrawler::builder().build().unwrap().with_links_by_page_to_file(false),
+            seed_urls: vec![seed_url.clone()],
+        };
+
+        let site_outcomes = MultiSiteCrawl::new()
+            .with_site(site("small", &small_site))
+            .with_site(site("large", &large_site))
+            .with_total_concurrency(2)
+            .crawl()
+            .await;
+
+        let site_names: Vec<&str> = site_outcomes.iter().map(|site_outcome| site_outcome.site.name.as_str()).collect();
+        assert_eq!(vec!["small", "large"], site_names);
+        for (site_outcome, seed_url) in site_outcomes.iter().zip([&small_site, &large_site]) {
+            let all_links = site_outcome.site.crawler.all_links();
+            assert!(!all_links.is_empty());
+            assert!(all_links.iter().all(|link| link.starts_with(seed_url.as_str().trim_end_matches('/'))));
+            assert!(site_outcome.crawl_report.is_ok());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+const CRAWLABLE_SCHEMES: [&str; 2] = ["http", "https"];
+const INFERRED_SCHEME: &str = "https://";
+
+// Query parameters that only say where a visitor came from, so they never change the page.
+const TRACKING_PARAM_PREFIXES: [&str; 1] = ["utm_"];
+const TRACKING_PARAMS: [&str; 7] = ["gclid", "dclid", "fbclid", "msclkid", "yclid", "mc_cid", "mc_eid"];
+
+// The optional parts of normalising a link. Parsing already lowercases the scheme and host, drops default ports and
+// resolves dot-segments, and the crawl always drops fragments and normalises percent-encoding on top of that.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UrlNormalization {
+    // utm_* and the ad platforms' click IDs.
+    pub strip_tracking_params: bool,
+    // Most servers don't care what order parameters come in, so ?a=1&b=2 and ?b=2&a=1 are the same page.
+    pub sort_query_params: bool,
+}
+
+impl UrlNormalization {
+    pub fn normalize(&self, url: &mut Url) {
+        url.set_fragment(None);
+
+        let path = normalize_percent_encoding(url.path());
+        url.set_path(&path);
+
+        let Some(query) = url.query().filter(|query| !query.is_empty()) else {
+            return;
+        };
+
+        let mut params: Vec<String> = query
+            .split('&')
+            .map(normalize_percent_encoding)
+            .filter(|param| !(self.strip_tracking_params && is_tracking_param(param)))
+            .collect();
+
+        // A stable sort, so repeated parameters keep their order relative to each other.
+        if self.sort_query_params {
+            params.sort_by(|first, second| param_name(first).cmp(param_name(second)));
+        }
+
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.set_query(Some(&params.join("&")));
+        }
+    }
+}
+
+// A seed typed without a scheme, e.g. "example.com/docs", is taken to be https. Only web pages can be crawled, so a URL
+// that parses but isn't http(s) is turned away.
+pub fn parse_seed_url(seed: &str) -> Result<Url, String> {
+    let inferred_url = || Url::parse(&format!("{}{}", INFERRED_SCHEME, seed));
+    let parsed_url = match Url::parse(seed) {
+        Ok(url) if !is_host_and_port(&url) => Ok(url),
+        Ok(_) => inferred_url(),
+        Err(_) if !seed.contains("://") => inferred_url(),
+        Err(err) => Err(err),
+    };
+    let parsed_url = parsed_url.map_err(|err| format!("Invalid URL '{}': {}", seed, err))?;
+
+    if !CRAWLABLE_SCHEMES.contains(&parsed_url.scheme()) {
+        return Err(format!("Invalid URL '{}', expected an http or https URL", seed));
+    }
+
+    Ok(parsed_url)
+}
+
+// "localhost:8000" parses as a URL with the scheme "localhost", but it was meant as a host and port.
+fn is_host_and_port(url: &Url) -> bool {
+    !url.has_host() && url.path().starts_with(|character: char| character.is_ascii_digit())
+}
+
+fn param_name(param: &str) -> &str {
+    param.split_once('=').map_or(param, |(name, _)| name)
+}
+
+fn is_tracking_param(param: &str) -> bool {
+    let name = param_name(param).to_ascii_lowercase();
+
+    TRACKING_PARAMS.contains(&name.as_str()) || TRACKING_PARAM_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+// Escapes of unreserved characters (letters, digits and -._~) are decoded, since %7E and ~ are the same URL, and the
+// hex digits of the escapes that are left are uppercased, per RFC 3986.
+fn normalize_percent_encoding(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut normalized = String::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3).filter(|_| bytes[index] == b'%');
+        match escaped.and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()) {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(byte as char);
+                index += 3;
+            }
+            Some(byte) => {
+                normalized.push_str(&format!("%{:02X}", byte));
+                index += 3;
+            }
+            None => {
+                let character = text[index..].chars().next().unwrap_or_default();
+                normalized.push(character);
+                index += character.len_utf8();
+            }
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(link: &str, normalization: UrlNormalization) -> String {
+        let mut url = Url::parse(link).unwrap();
+        normalization.normalize(&mut url);
+        url.to_string()
+    }
+
+    #[test]
+    fn test_links_are_normalized_without_options() {
+        let normalization = UrlNormalization::default();
+
+        assert_eq!("https://example.com/a", normalized("HTTPS://Example.COM:443/a#top", normalization));
+        assert_eq!("https://example.com/~user/a%2Fb", normalized("https://example.com/%7euser/a%2fb", normalization));
+        assert_eq!("https://example.com/a?q=%C3%A9", normalized("https://example.com/a?q=%c3%a9", normalization));
+        assert_eq!(
+            "https://example.com/a?utm_source=x&b=1",
+            normalized("https://example.com/a?utm_source=x&b=1", normalization)
+        );
+    }
+
+    #[test]
+    fn test_seeds_without_a_scheme_are_taken_as_https() {
+        let seed_link = |seed: &str| parse_seed_url(seed).map(String::from);
+
+        assert_eq!(Ok(String::from("https://example.com/")), seed_link("example.com"));
+        assert_eq!(Ok(String::from("https://example.com/docs")), seed_link("example.com/docs"));
+        assert_eq!(Ok(String::from("https://localhost:8000/")), seed_link("localhost:8000"));
+        assert_eq!(Ok(String::from("http://example.com/")), seed_link("http://example.com"));
+        assert_eq!(
+            Err(String::from("Invalid URL 'mailto:me@example.com', expected an http or https URL")),
+            seed_link("mailto:me@example.com")
+        );
+        assert!(seed_link("").is_err());
+        assert!(seed_link("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn test_tracking_params_are_stripped_and_the_rest_sorted() {
+        let normalization = UrlNormalization { strip_tracking_params: true, sort_query_params: true };
+
+        assert_eq!("https://example.com/a", normalized("https://example.com/a?utm_source=x#top", normalization));
+        assert_eq!(
+            "https://example.com/a?a=2&b=1&b=0",
+            normalized("https://example.com/a?b=1&UTM_Medium=email&gclid=abc&a=2&b=0", normalization)
+        );
+        assert_eq!("https://example.com/a?", normalized("https://example.com/a?", UrlNormalization::default()));
+    }
+}
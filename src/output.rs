@@ -0,0 +1,438 @@
+use std::ffi::OsStr;
+use std::fs::{remove_file, File, OpenOptions};
+use std::io::{stdout, BufReader, BufWriter, Read, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::to_string_pretty;
+
+use crate::metadata::Artifact;
+
+const GZIP_NAME: &str = "gzip";
+const ZSTD_NAME: &str = "zstd";
+const GZIP_EXTENSION: &str = "gz";
+const ZSTD_EXTENSION: &str = "zst";
+const ZSTD_LEVEL: i32 = 3;
+const CHUNK_NUMBER_WIDTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl FromStr for OutputCompression {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            GZIP_NAME => Ok(OutputCompression::Gzip),
+            ZSTD_NAME => Ok(OutputCompression::Zstd),
+            _ => Err(format!("Unknown compression '{}', expected one of: {}, {}", name, GZIP_NAME, ZSTD_NAME)),
+        }
+    }
+}
+
+impl OutputCompression {
+    // Works out how an existing output was compressed from its file extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str) {
+            Some(GZIP_EXTENSION) => OutputCompression::Gzip,
+            Some(ZSTD_EXTENSION) => OutputCompression::Zstd,
+            _ => OutputCompression::None,
+        }
+    }
+
+    pub fn output_filename(&self, path: &Path) -> PathBuf {
+        let extension = match self {
+            OutputCompression::None => return path.to_path_buf(),
+            OutputCompression::Gzip => GZIP_EXTENSION,
+            OutputCompression::Zstd => ZSTD_EXTENSION,
+        };
+
+        let mut filename = path.as_os_str().to_owned();
+        filename.push(".");
+        filename.push(extension);
+        PathBuf::from(filename)
+    }
+
+    // Output is compressed while it's being written, so the uncompressed file never touches disk.
+    // Appending adds a new gzip member/zstd frame, which decoders read back as one continuous stream.
+    pub fn create_writer(&self, path: &Path, append: bool) -> std::io::Result<OutputWriter> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(self.output_filename(path))?;
+        let file = BufWriter::new(file);
+
+        let writer = match self {
+            OutputCompression::None => OutputWriter::Plain(file),
+            OutputCompression::Gzip => OutputWriter::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            OutputCompression::Zstd => OutputWriter::Zstd(zstd::Encoder::new(file, ZSTD_LEVEL)?),
+        };
+
+        Ok(writer)
+    }
+
+    pub fn create_reader(&self, path: &Path) -> std::io::Result<Box<dyn Read>> {
+        let file = BufReader::new(File::open(path)?);
+
+        let reader: Box<dyn Read> = match self {
+            OutputCompression::None => Box::new(file),
+            OutputCompression::Gzip => Box::new(MultiGzDecoder::new(file)),
+            OutputCompression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+        };
+
+        Ok(reader)
+    }
+}
+
+// When a record stream moves on to its next chunk, e.g. links_by_page-0002.ndjson. Either limit starts one, and with
+// neither the stream is a single file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputRotation {
+    pub max_records: Option<usize>,
+    // Counted before compression, so compressed chunks come out smaller than this.
+    pub max_bytes: Option<u64>,
+}
+
+impl OutputRotation {
+    pub fn is_enabled(&self) -> bool {
+        self.max_records.is_some() || self.max_bytes.is_some()
+    }
+
+    // A record is never split, so one larger than max_bytes gets a chunk to itself.
+    fn is_full(&self, chunk_records: usize, chunk_bytes: u64, next_record_bytes: u64) -> bool {
+        chunk_records > 0
+            && (self.max_records.is_some_and(|max_records| chunk_records >= max_records)
+                || self.max_bytes.is_some_and(|max_bytes| chunk_bytes + next_record_bytes > max_bytes))
+    }
+}
+
+// Numbered from 1, e.g. links_by_page.ndjson's first chunk is links_by_page-0001.ndjson.
+pub fn chunk_path(path: &Path, chunk_number: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut filename = format!("{}-{:0width$}", stem, chunk_number, width = CHUNK_NUMBER_WIDTH);
+    if let Some(extension) = path.extension() {
+        filename.push('.');
+        filename.push_str(&extension.to_string_lossy());
+    }
+
+    path.with_file_name(filename)
+}
+
+pub enum OutputWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+    Stdout(Stdout),
+}
+
+impl OutputWriter {
+    // Compressed formats need their trailers written, which dropping the encoder won't report errors for.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            OutputWriter::Plain(mut file) => file.flush(),
+            OutputWriter::Gzip(encoder) => encoder.finish()?.flush(),
+            OutputWriter::Zstd(encoder) => encoder.finish()?.flush(),
+            OutputWriter::Stdout(mut stdout) => stdout.flush(),
+        }
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputWriter::Plain(file) => file.write(buf),
+            OutputWriter::Gzip(encoder) => encoder.write(buf),
+            OutputWriter::Zstd(encoder) => encoder.write(buf),
+            OutputWriter::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputWriter::Plain(file) => file.flush(),
+            OutputWriter::Gzip(encoder) => encoder.flush(),
+            OutputWriter::Zstd(encoder) => encoder.flush(),
+            OutputWriter::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+// Writes one JSON record per line as results come in, rather than holding them all until the end of the run.
+pub struct RecordSink {
+    output: Mutex<SinkOutput>,
+}
+
+struct SinkOutput {
+    writer: Option<OutputWriter>,
+    // Only when the records are split into chunks - standard output never is.
+    chunks: Option<Chunks>,
+}
+
+struct Chunks {
+    path: PathBuf,
+    compression: OutputCompression,
+    rotation: OutputRotation,
+    chunk_number: usize,
+    chunk_records: usize,
+    chunk_bytes: u64,
+    // Repeated at the top of every chunk, so each one can be loaded on its own.
+    header: Option<Vec<u8>>,
+}
+
+impl RecordSink {
+    // Appending to chunked records starts a new chunk after the last one there. Otherwise the chunks an earlier run
+    // left behind are removed, so none of them are mistaken for this run's.
+    pub fn new(
+        path: &Path,
+        print_to_file: bool,
+        compression: OutputCompression,
+        rotation: OutputRotation,
+        append: bool,
+    ) -> std::io::Result<Self> {
+        if !print_to_file {
+            let output = SinkOutput { writer: Some(OutputWriter::Stdout(stdout())), chunks: None };
+            return Ok(RecordSink { output: Mutex::new(output) });
+        }
+
+        if !rotation.is_enabled() {
+            let output = SinkOutput { writer: Some(compression.create_writer(path, append)?), chunks: None };
+            return Ok(RecordSink { output: Mutex::new(output) });
+        }
+
+        let existing_chunks = (1..)
+            .map(|chunk_number| compression.output_filename(&chunk_path(path, chunk_number)))
+            .take_while(|existing_chunk| existing_chunk.exists())
+            .collect::<Vec<_>>();
+        if !append {
+            existing_chunks.iter().try_for_each(remove_file)?;
+        }
+
+        let chunk_number = if append { existing_chunks.len() + 1 } else { 1 };
+        let writer = compression.create_writer(&chunk_path(path, chunk_number), false)?;
+        let chunks = Chunks {
+            path: path.to_path_buf(),
+            compression,
+            rotation,
+            chunk_number,
+            chunk_records: 0,
+            chunk_bytes: 0,
+            header: None,
+        };
+
+        Ok(RecordSink { output: Mutex::new(SinkOutput { writer: Some(writer), chunks: Some(chunks) }) })
+    }
+
+    // Written once, ahead of the records - or at the top of each chunk, when they're split into chunks.
+    pub fn write_header<T: Serialize>(&self, header: &T) {
+        let line = record_line(header);
+        let mut output = self.output.lock().expect("Failed to write header.");
+
+        if let Some(chunks) = output.chunks.as_mut() {
+            chunks.chunk_bytes += line.len() as u64;
+            chunks.header = Some(line.clone());
+        }
+        if let Some(writer) = output.writer.as_mut() {
+            writer.write_all(&line).expect("Failed to write header.");
+        }
+    }
+
+    pub fn write_record<T: Serialize>(&self, record: &T) {
+        let line = record_line(record);
+        let mut output = self.output.lock().expect("Failed to write record.");
+
+        if output.writer.is_none() {
+            return;
+        }
+
+        let SinkOutput { writer, chunks } = &mut *output;
+        if let Some(chunks) = chunks.as_mut() {
+            if chunks.rotation.is_full(chunks.chunk_records, chunks.chunk_bytes, line.len() as u64) {
+                let full_writer = writer.take().expect("Failed to start the next chunk.");
+                full_writer.finish().expect("Failed to finish writing records.");
+                *writer = Some(chunks.start_next_chunk().expect("Failed to start the next chunk."));
+            }
+
+            chunks.chunk_records += 1;
+            chunks.chunk_bytes += line.len() as u64;
+        }
+
+        if let Some(writer) = writer.as_mut() {
+            writer.write_all(&line).expect("Failed to write record.");
+        }
+    }
+
+    // Pushes buffered records out without closing the output, e.g. to free memory partway through a crawl.
+    pub fn flush(&self) {
+        let mut output = self.output.lock().expect("Failed to flush records.");
+
+        if let Some(writer) = output.writer.as_mut() {
+            writer.flush().expect("Failed to flush records.");
+        }
+    }
+
+    pub fn finish(&self) {
+        let mut output = self.output.lock().expect("Failed to finish writing records.");
+
+        if let Some(writer) = output.writer.take() {
+            writer.finish().expect("Failed to finish writing records.");
+        }
+    }
+}
+
+impl Chunks {
+    fn start_next_chunk(&mut self) -> std::io::Result<OutputWriter> {
+        self.chunk_number += 1;
+        self.chunk_records = 0;
+        self.chunk_bytes = 0;
+
+        let mut writer = self.compression.create_writer(&chunk_path(&self.path, self.chunk_number), false)?;
+        if let Some(header) = self.header.as_ref() {
+            writer.write_all(header)?;
+            self.chunk_bytes += header.len() as u64;
+        }
+
+        Ok(writer)
+    }
+}
+
+fn record_line<T: Serialize + ?Sized>(record: &T) -> Vec<u8> {
+    let mut line = serde_json::to_vec(record).expect("Failed to convert to JSON");
+    line.push(b'\n');
+    line
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T, path: &Path, print_to_file: bool, compression: OutputCompression) {
+    if print_to_file {
+        let mut writer = compression.create_writer(path, false).expect("Failed to convert to file.");
+        serde_json::to_writer_pretty(&mut writer, value).expect("Failed to convert to JSON");
+        writer.finish().unwrap();
+    } else {
+        let json_string = to_string_pretty(value).expect("Failed to convert to string.");
+        println!("{}", json_string);
+    }
+}
+
+// Plain-text outputs have nowhere to put the crawl metadata, so they're written as they are.
+pub fn print_text(text: &str, path: &Path, print_to_file: bool, compression: OutputCompression) {
+    if print_to_file {
+        let mut writer = compression.create_writer(path, false).expect("Failed to convert to file.");
+        writer.write_all(text.as_bytes()).expect("Failed to write text.");
+        writer.finish().unwrap();
+    } else {
+        print!("{}", text);
+    }
+}
+
+// Reads back the data from a JSON output written by an earlier run, compressed or not.
+pub fn read_artifact<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let reader = OutputCompression::from_path(path).create_reader(path).ok()?;
+    let artifact: Artifact<T> = serde_json::from_reader(reader).ok()?;
+
+    Some(artifact.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_output_filenames() {
+        let path = Path::new("all_links.json");
+
+        assert_eq!(Path::new("all_links.json"), OutputCompression::None.output_filename(path));
+        assert_eq!(Path::new("all_links.json.gz"), OutputCompression::Gzip.output_filename(path));
+        assert_eq!(Path::new("all_links.json.zst"), OutputCompression::Zstd.output_filename(path));
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(OutputCompression::Gzip, OutputCompression::from_path(Path::new("run/errors.json.gz")));
+        assert_eq!(OutputCompression::None, OutputCompression::from_path(Path::new("run/errors.json")));
+    }
+
+    #[test]
+    fn test_appended_compressed_records_read_back_as_one_stream() {
+        let path = std::env::temp_dir().join("crawling_appended_records_test.ndjson");
+        let compressed_path = OutputCompression::Gzip.output_filename(&path);
+
+        for (record, append) in [("first", false), ("second", true)] {
+            let rotation = OutputRotation::default();
+            let sink = RecordSink::new(&path, true, OutputCompression::Gzip, rotation, append).unwrap();
+            sink.write_record(&record);
+            sink.finish();
+        }
+
+        let mut written = String::new();
+        OutputCompression::Gzip.create_reader(&compressed_path).unwrap().read_to_string(&mut written).unwrap();
+        std::fs::remove_file(compressed_path).ok();
+
+        assert_eq!("\"first\"\n\"second\"\n", written);
+    }
+
+    #[test]
+    fn test_record_sink_writes_one_line_per_record() {
+        let path = std::env::temp_dir().join("crawling_record_sink_test.ndjson");
+
+        let sink = RecordSink::new(&path, true, OutputCompression::None, OutputRotation::default(), false).unwrap();
+        sink.write_record(&vec!["a"]);
+        sink.write_record(&vec!["b", "c"]);
+        sink.finish();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!("[\"a\"]\n[\"b\",\"c\"]\n", written);
+    }
+
+    #[test]
+    fn test_chunk_paths() {
+        assert_eq!(Path::new("run/results-0001.ndjson"), chunk_path(Path::new("run/results.ndjson"), 1));
+        assert_eq!(Path::new("results-0012"), chunk_path(Path::new("results"), 12));
+    }
+
+    #[test]
+    fn test_records_are_split_into_chunks() {
+        let path = std::env::temp_dir().join(format!("crawling_chunked_records_test-{}.ndjson", std::process::id()));
+        let read_chunk = |chunk_number| {
+            let chunk_path = OutputCompression::Gzip.output_filename(&chunk_path(&path, chunk_number));
+            let mut written = String::new();
+            let reader = OutputCompression::Gzip.create_reader(&chunk_path).map(|mut reader| {
+                reader.read_to_string(&mut written).unwrap();
+                written
+            });
+            std::fs::remove_file(chunk_path).ok();
+            reader.ok()
+        };
+
+        // Three records a chunk, or fewer once a chunk would go over 24 bytes - "header" takes up 9 of them.
+        let rotation = OutputRotation { max_records: Some(3), max_bytes: Some(24) };
+        let sink = RecordSink::new(&path, true, OutputCompression::Gzip, rotation, false).unwrap();
+        sink.write_header(&"header");
+        ["a", "b", "c", "d", "a long record"].iter().for_each(|record| sink.write_record(record));
+        sink.finish();
+
+        assert_eq!(Some(String::from("\"header\"\n\"a\"\n\"b\"\n\"c\"\n")), read_chunk(1));
+        assert_eq!(Some(String::from("\"header\"\n\"d\"\n")), read_chunk(2));
+        assert_eq!(Some(String::from("\"header\"\n\"a long record\"\n")), read_chunk(3));
+        assert_eq!(None, read_chunk(4));
+    }
+
+    #[test]
+    fn test_compression_from_name() {
+        assert_eq!(Ok(OutputCompression::Zstd), "zstd".parse());
+        assert!("rar".parse::<OutputCompression>().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+// Keeps requests to the same host at least the delay apart, however many workers are fetching from it. Each request
+// books the host's next free slot and sleeps until then, so waiting requests go out in the order they asked.
+pub struct HostPacer {
+    delay: Duration,
+    // Up to this much extra is waited before each request, picked at random every time, so repeated crawls don't
+    // send their requests at exactly the same moments.
+    jitter: Duration,
+    // Hosts that asked for a longer delay than the crawl-wide one, e.g. with a robots.txt Crawl-delay.
+    delay_by_host: Mutex<HashMap<String, Duration>>,
+    // The next slot is worked out from the last one when it's booked, so a delay set after a host's first request
+    // (e.g. from the robots.txt that request fetched) already applies to its second.
+    last_slot_by_host: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostPacer {
+    pub fn new(delay: Duration, jitter: Duration) -> Self {
+        HostPacer {
+            delay,
+            jitter,
+            delay_by_host: Mutex::new(HashMap::new()),
+            last_slot_by_host: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // A host can only slow its own requests down - a delay shorter than the crawl-wide one is ignored. Returns the
+    // delay the host ends up with.
+    pub fn set_host_delay(&self, host: &str, delay: Duration) -> Duration {
+        let host_delay = delay.max(self.delay);
+
+        self.delay_by_host
+            .lock()
+            .map(|mut delay_by_host| delay_by_host.insert(host.to_string(), host_delay))
+            .expect("Failed to set host delay.");
+
+        host_delay
+    }
+
+    pub async fn wait_turn(&self, host: &str) {
+        let wait = self.book_slot(host, Instant::now()) + self.random_jitter();
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // The jitter comes on top of the booked slot rather than moving it, so it never eats into the delay.
+    fn random_jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+
+        rand::thread_rng().gen_range(Duration::ZERO..=self.jitter)
+    }
+
+    // How long until the booked slot. A host that's been quiet for longer than its delay is free straight away.
+    fn book_slot(&self, host: &str, now: Instant) -> Duration {
+        let delay = self
+            .delay_by_host
+            .lock()
+            .map(|delay_by_host| delay_by_host.get(host).copied().unwrap_or(self.delay))
+            .expect("Failed to read host delay.");
+
+        let mut last_slot_by_host = self.last_slot_by_host.lock().expect("Failed to book request slot.");
+        let next_slot = last_slot_by_host.get(host).map(|last_slot| *last_slot + delay);
+        let slot = next_slot.filter(|next_slot| *next_slot > now).unwrap_or(now);
+        last_slot_by_host.insert(host.to_string(), slot);
+
+        slot - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_to_a_host_are_spaced_out() {
+        let pacer = HostPacer::new(Duration::from_millis(500), Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(Duration::ZERO, pacer.book_slot("example.com", now));
+        assert_eq!(Duration::from_millis(500), pacer.book_slot("example.com", now));
+        assert_eq!(Duration::from_millis(1000), pacer.book_slot("example.com", now));
+        assert_eq!(Duration::ZERO, pacer.book_slot("other.example.com", now));
+        assert_eq!(Duration::ZERO, pacer.book_slot("other.example.com", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_hosts_can_only_ask_for_a_longer_delay() {
+        let pacer = HostPacer::new(Duration::from_millis(500), Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(Duration::from_secs(2), pacer.set_host_delay("slow.example.com", Duration::from_secs(2)));
+        assert_eq!(Duration::from_millis(500), pacer.set_host_delay("fast.example.com", Duration::from_millis(100)));
+
+        pacer.book_slot("slow.example.com", now);
+        assert_eq!(Duration::from_secs(2), pacer.book_slot("slow.example.com", now));
+    }
+
+    #[test]
+    fn test_a_new_host_delay_applies_to_the_next_request() {
+        let pacer = HostPacer::new(Duration::ZERO, Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(Duration::ZERO, pacer.book_slot("example.com", now));
+        pacer.set_host_delay("example.com", Duration::from_secs(1));
+        assert_eq!(Duration::from_secs(1), pacer.book_slot("example.com", now));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_its_bound() {
+        let pacer = HostPacer::new(Duration::ZERO, Duration::from_millis(50));
+
+        assert!((0..100).all(|_| pacer.random_jitter() <= Duration::from_millis(50)));
+        assert_eq!(Duration::ZERO, HostPacer::new(Duration::ZERO, Duration::ZERO).random_jitter());
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, HashSet};
+
+// A page's rel="next" / rel="prev" relations, already resolved to crawlable URLs.
+#[derive(Default, Debug, PartialEq)]
+pub struct PaginationLinks {
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+// Each series starts at a page nothing points to and follows rel="next" from there, so it comes out in reading
+// order. Pages that only link to each other in a loop have no first page and aren't reported.
+pub fn build_series(next_by_page: &BTreeMap<String, String>) -> Vec<Vec<String>> {
+    let linked_pages: HashSet<&String> = next_by_page.values().collect();
+
+    next_by_page
+        .keys()
+        .filter(|page| !linked_pages.contains(page))
+        .map(|first_page| {
+            let mut series = vec![first_page.clone()];
+            let mut seen = HashSet::from([first_page]);
+            let mut current_page = first_page;
+
+            while let Some(next_page) = next_by_page.get(current_page) {
+                if !seen.insert(next_page) {
+                    break;
+                }
+
+                series.push(next_page.clone());
+                current_page = next_page;
+            }
+
+            series
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next_by_page(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(page, next)| (page.to_string(), next.to_string())).collect()
+    }
+
+    #[test]
+    fn test_series_are_in_reading_order() {
+        let next_by_page = next_by_page(&[
+            ("https://example.com/blog?page=2", "https://example.com/blog?page=3"),
+            ("https://example.com/blog", "https://example.com/blog?page=2"),
+            ("https://example.com/news", "https://example.com/news/2"),
+        ]);
+
+        let series = build_series(&next_by_page);
+
+        assert_eq!(2, series.len());
+        assert_eq!(
+            vec!["https://example.com/blog", "https://example.com/blog?page=2", "https://example.com/blog?page=3"],
+            series[0]
+        );
+    }
+
+    #[test]
+    fn test_looping_pages_are_not_a_series() {
+        let next_by_page = next_by_page(&[
+            ("https://example.com/a", "https://example.com/b"),
+            ("https://example.com/b", "https://example.com/a"),
+        ]);
+
+        assert!(build_series(&next_by_page).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+const TOO_MANY_REQUESTS_STATUS: u16 = 429;
+const SERVICE_UNAVAILABLE_STATUS: u16 = 503;
+// A request can be held up briefly after its turn comes round while the next one still goes out on time, so a gap
+// slightly under the delay doesn't mean it was ignored.
+const GAP_TOLERANCE: Duration = Duration::from_millis(20);
+
+// When each host was sent requests and how it answered, as evidence the crawl was well-behaved.
+#[derive(Default)]
+pub struct PolitenessTracker {
+    hosts: BTreeMap<String, HostActivity>,
+}
+
+#[derive(Default)]
+struct HostActivity {
+    requests: usize,
+    first_sent_at: Option<Instant>,
+    last_sent_at: Option<Instant>,
+    shortest_gap: Option<Duration>,
+    too_many_requests: usize,
+    service_unavailable: usize,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct HostPoliteness {
+    pub requests: usize,
+    // Both are None until there have been two requests to measure between.
+    pub average_gap_ms: Option<u64>,
+    pub shortest_gap_ms: Option<u64>,
+    pub too_many_requests_responses: usize,
+    pub service_unavailable_responses: usize,
+    // As honoured - capped, and never shorter than the crawl-wide delay.
+    pub crawl_delay_secs: Option<f64>,
+    // None without a Crawl-delay to honour.
+    pub crawl_delay_honored: Option<bool>,
+}
+
+impl PolitenessTracker {
+    pub fn record_request(&mut self, host: &str, sent_at: Instant) {
+        let activity = self.hosts.entry(host.to_string()).or_default();
+        activity.requests += 1;
+        activity.first_sent_at.get_or_insert(sent_at);
+
+        // Workers record their requests as they go out, so one can arrive just after a later one.
+        if let Some(last_sent_at) = activity.last_sent_at {
+            let gap = sent_at.saturating_duration_since(last_sent_at);
+            activity.shortest_gap = Some(activity.shortest_gap.map_or(gap, |shortest_gap| shortest_gap.min(gap)));
+        }
+        activity.last_sent_at = Some(activity.last_sent_at.map_or(sent_at, |last_sent_at| last_sent_at.max(sent_at)));
+    }
+
+    pub fn record_status(&mut self, host: &str, status: u16) {
+        let activity = self.hosts.entry(host.to_string()).or_default();
+
+        match status {
+            TOO_MANY_REQUESTS_STATUS => activity.too_many_requests += 1,
+            SERVICE_UNAVAILABLE_STATUS => activity.service_unavailable += 1,
+            _ => {}
+        }
+    }
+
+    pub fn record_crawl_delay(&mut self, host: &str, crawl_delay: Duration) {
+        self.hosts.entry(host.to_string()).or_default().crawl_delay = Some(crawl_delay);
+    }
+
+    pub fn report(&self) -> BTreeMap<String, HostPoliteness> {
+        self.hosts.iter().map(|(host, activity)| (host.clone(), activity.politeness())).collect()
+    }
+}
+
+impl HostActivity {
+    fn politeness(&self) -> HostPoliteness {
+        let average_gap = match (self.first_sent_at, self.last_sent_at, u32::try_from(self.requests)) {
+            (Some(first_sent_at), Some(last_sent_at), Ok(requests)) if requests > 1 => {
+                Some((last_sent_at - first_sent_at) / (requests - 1))
+            }
+            _ => None,
+        };
+
+        HostPoliteness {
+            requests: self.requests,
+            average_gap_ms: average_gap.map(|average_gap| average_gap.as_millis() as u64),
+            shortest_gap_ms: self.shortest_gap.map(|shortest_gap| shortest_gap.as_millis() as u64),
+            too_many_requests_responses: self.too_many_requests,
+            service_unavailable_responses: self.service_unavailable,
+            crawl_delay_secs: self.crawl_delay.map(|crawl_delay| crawl_delay.as_secs_f64()),
+            crawl_delay_honored: self.crawl_delay.map(|crawl_delay| {
+                self.shortest_gap.is_none_or(|shortest_gap| shortest_gap + GAP_TOLERANCE >= crawl_delay)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps_and_statuses_are_reported_per_host() {
+        let mut tracker = PolitenessTracker::default();
+        let start = Instant::now();
+
+        tracker.record_request("example.com", start);
+        tracker.record_request("example.com", start + Duration::from_millis(400));
+        tracker.record_request("example.com", start + Duration::from_millis(1000));
+        tracker.record_status("example.com", 429);
+        tracker.record_status("example.com", 200);
+        tracker.record_request("cdn.example.com", start);
+
+        let report = tracker.report();
+
+        assert_eq!(
+            HostPoliteness {
+                requests: 3,
+                average_gap_ms: Some(500),
+                shortest_gap_ms: Some(400),
+                too_many_requests_responses: 1,
+                service_unavailable_responses: 0,
+                crawl_delay_secs: None,
+                crawl_delay_honored: None,
+            },
+            report["example.com"]
+        );
+        assert_eq!(None, report["cdn.example.com"].average_gap_ms);
+    }
+
+    #[test]
+    fn test_crawl_delay_is_checked_against_the_shortest_gap() {
+        let mut tracker = PolitenessTracker::default();
+        let start = Instant::now();
+
+        tracker.record_crawl_delay("example.com", Duration::from_secs(1));
+        tracker.record_request("example.com", start);
+        tracker.record_request("example.com", start + Duration::from_millis(990));
+        assert_eq!(Some(true), tracker.report()["example.com"].crawl_delay_honored);
+
+        tracker.record_request("example.com", start + Duration::from_millis(1200));
+        assert_eq!(Some(false), tracker.report()["example.com"].crawl_delay_honored);
+    }
+}
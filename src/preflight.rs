@@ -0,0 +1,79 @@
+use std::error::Error as StdError;
+
+use thiserror::Error;
+
+// Why a seed's site failed the checks made before the crawl starts, worded for someone who typed the seed URL.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum PreflightFailure {
+    #[error("DNS lookup for {host} failed: {message}")]
+    Dns { host: String, message: String },
+    #[error("TLS handshake with {host} failed: {message}")]
+    Tls { host: String, message: String },
+    #[error("couldn't connect to {host}: {message}")]
+    Connection { host: String, message: String },
+    #[error("{host} didn't answer in time")]
+    Timeout { host: String },
+    // A server error for the seed itself - the site is up, but isn't serving pages.
+    #[error("the seed answered with a server error (HTTP {status})")]
+    SeedStatus { status: u16 },
+    #[error("robots.txt couldn't be fetched: {message}")]
+    Robots { message: String },
+}
+
+impl PreflightFailure {
+    // reqwest reports DNS, TLS and connection failures alike as connect errors, so they're told apart by the messages
+    // of the errors underneath it.
+    pub fn from_request_error(host: &str, err: &reqwest::Error) -> Self {
+        let host = host.to_string();
+        if err.is_timeout() {
+            return PreflightFailure::Timeout { host };
+        }
+
+        let chain = error_chain(err);
+        let message = chain.last().cloned().unwrap_or_default();
+        // The outermost message has the URL in it, which could contain any of the words looked for.
+        let lowercase_chain = chain[1..].join(": ").to_lowercase();
+        if lowercase_chain.contains("dns error") {
+            PreflightFailure::Dns { host, message }
+        } else if ["certificate", "tls", "ssl", "handshake"].iter().any(|word| lowercase_chain.contains(word)) {
+            PreflightFailure::Tls { host, message }
+        } else {
+            PreflightFailure::Connection { host, message }
+        }
+    }
+}
+
+// The error and the ones underneath it, outermost first. reqwest's own message is just "error sending request", and
+// the ones in between repeat what's under them, so the innermost is the clearest reason.
+fn error_chain(err: &(dyn StdError + 'static)) -> Vec<String> {
+    let mut messages = vec![err.to_string()];
+    let mut source = err.source();
+
+    while let Some(err) = source {
+        messages.push(err.to_string());
+        source = err.source();
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refused_connections_are_connection_failures() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(3)).build().unwrap();
+        let err = client.get(format!("http://{}/", address)).send().await.unwrap_err();
+
+        let failure = PreflightFailure::from_request_error("127.0.0.1", &err);
+        assert!(matches!(&failure, PreflightFailure::Connection { message, .. } if message.contains("refused")));
+        assert!(failure.to_string().starts_with("couldn't connect to 127.0.0.1: Connection refused"));
+    }
+}
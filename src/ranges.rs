@@ -0,0 +1,55 @@
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_RANGE};
+use reqwest::StatusCode;
+
+// Enough to show the resource is there without downloading it.
+pub const SAMPLE_RANGE: &str = "bytes=0-1023";
+const BYTES_UNIT: &str = "bytes";
+
+// The full size of a resource requested with SAMPLE_RANGE. Servers that ignore ranges answer 200 with the whole
+// resource, whose Content-Length is then the size. An empty resource can't satisfy the range, but the 416 still says
+// how big it is.
+pub fn resource_size(status: StatusCode, headers: &HeaderMap) -> Option<u64> {
+    if status == StatusCode::PARTIAL_CONTENT || status == StatusCode::RANGE_NOT_SATISFIABLE {
+        content_range_size(headers.get(CONTENT_RANGE)?.to_str().ok()?)
+    } else {
+        headers.get(CONTENT_LENGTH)?.to_str().ok()?.trim().parse().ok()
+    }
+}
+
+// "bytes 0-1023/146515" is 146515 bytes. The size is "*" when the server doesn't know it.
+fn content_range_size(content_range: &str) -> Option<u64> {
+    let (unit, range) = content_range.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case(BYTES_UNIT) {
+        return None;
+    }
+
+    range.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_size_comes_from_content_range() {
+        assert_eq!(Some(146515), content_range_size("bytes 0-1023/146515"));
+        assert_eq!(Some(0), content_range_size("bytes */0"));
+        assert_eq!(None, content_range_size("bytes 0-1023/*"));
+        assert_eq!(None, content_range_size("items 0-9/100"));
+    }
+
+    #[test]
+    fn test_size_falls_back_to_content_length_when_the_range_is_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("2048"));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-1023/5000000"));
+
+        assert_eq!(Some(2048), resource_size(StatusCode::OK, &headers));
+        assert_eq!(Some(5000000), resource_size(StatusCode::PARTIAL_CONTENT, &headers));
+
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes */0"));
+        assert_eq!(Some(0), resource_size(StatusCode::RANGE_NOT_SATISFIABLE, &headers));
+    }
+}
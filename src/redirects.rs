@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use reqwest::{header, Response, Url};
+use serde::{Deserialize, Serialize};
+
+const HTTPS_SCHEME: &str = "https";
+const HTTP_SCHEME: &str = "http";
+
+const FOLLOW_NAME: &str = "follow";
+const STOP_NAME: &str = "stop";
+
+// Whether a redirect is followed when it leaves the host it was sent to. Stopping leaves the redirect itself as the
+// response, and the off-domain target is never requested.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OffDomainRedirects {
+    #[default]
+    Follow,
+    Stop,
+}
+
+impl FromStr for OffDomainRedirects {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            FOLLOW_NAME => Ok(OffDomainRedirects::Follow),
+            STOP_NAME => Ok(OffDomainRedirects::Stop),
+            _ => Err(format!(
+                "Unknown off-domain redirect policy '{}', expected {} or {}",
+                name, FOLLOW_NAME, STOP_NAME
+            )),
+        }
+    }
+}
+
+// Why a redirect chain wasn't followed to the end.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedirectStop {
+    TooManyRedirects,
+    Loop,
+    OffDomain,
+    // Onto a host that's never crawled, whether or not off-domain redirects are followed.
+    BlockedHost,
+}
+
+// One redirect response on the way to the final URL.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RedirectHop {
+    pub link: String,
+    pub status: u16,
+}
+
+// Every hop a request went through, and where it ended up. When the chain was stopped, the target is the redirect
+// that wasn't followed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RedirectChain {
+    pub target: String,
+    pub hops: Vec<RedirectHop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<RedirectStop>,
+}
+
+// Where a redirect response points, resolved against its own URL. 304 Not Modified and 300 Multiple Choices are
+// answers in their own right, so they're not followed.
+pub fn redirect_target(response: &Response) -> Option<Url> {
+    if !response.status().is_redirection() || matches!(response.status().as_u16(), 300 | 304) {
+        return None;
+    }
+
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedirectIssue {
+    OffDomain,
+    HttpsToHttp,
+}
+
+// An internal link whose redirect lands somewhere it shouldn't - the usual leftovers of a half-finished migration.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RedirectAudit {
+    pub target: String,
+    pub issues: Vec<RedirectIssue>,
+}
+
+// Returns None when the redirect stays on the same domain and doesn't drop HTTPS.
+pub fn audit_redirect(link: &Url, target: &Url) -> Option<RedirectAudit> {
+    let mut issues = Vec::new();
+
+    if link.host_str() != target.host_str() {
+        issues.push(RedirectIssue::OffDomain);
+    }
+
+    if link.scheme() == HTTPS_SCHEME && target.scheme() == HTTP_SCHEME {
+        issues.push(RedirectIssue::HttpsToHttp);
+    }
+
+    if issues.is_empty() {
+        return None;
+    }
+
+    Some(RedirectAudit { target: target.to_string(), issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(link: &str, target: &str) -> Option<RedirectAudit> {
+        audit_redirect(&Url::parse(link).unwrap(), &Url::parse(target).unwrap())
+    }
+
+    #[test]
+    fn test_off_domain_redirect_policies_are_parsed() {
+        assert_eq!(Ok(OffDomainRedirects::Stop), "stop".parse());
+        assert_eq!(Ok(OffDomainRedirects::Follow), "follow".parse());
+        assert!("never".parse::<OffDomainRedirects>().is_err());
+    }
+
+    #[test]
+    fn test_same_domain_redirects_are_fine() {
+        assert_eq!(None, audit("https://example.com/old", "https://example.com/new"));
+        assert_eq!(None, audit("http://example.com/old", "https://example.com/old"));
+    }
+
+    #[test]
+    fn test_off_domain_and_downgraded_redirects_are_reported() {
+        let audit = audit("https://example.com/shop", "http://shop.example.net/").unwrap();
+
+        assert_eq!("http://shop.example.net/", audit.target);
+        assert_eq!(vec![RedirectIssue::OffDomain, RedirectIssue::HttpsToHttp], audit.issues);
+    }
+}
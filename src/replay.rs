@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::Artifact;
+use crate::output::OutputCompression;
+
+// How a run was started, written to run_manifest.json. Its metadata header holds the settings, seeds, crawler version
+// and random seed the run ended up with, and `crawl --replay` starts the same run again from it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    // The command line after the program's name, e.g. ["crawl", "https://example.com/", "--max-depth", "2"].
+    pub arguments: Vec<String>,
+    // The --config file as it was when the run started, so a replay doesn't depend on it staying the same.
+    pub config: Option<String>,
+}
+
+pub fn load_run_manifest(manifest_path: &Path) -> Result<Artifact<RunManifest>, String> {
+    let reader = OutputCompression::from_path(manifest_path)
+        .create_reader(manifest_path)
+        .map_err(|err| format!("Failed to read run manifest from {}: {}", manifest_path.display(), err))?;
+
+    serde_json::from_reader(reader)
+        .map_err(|err| format!("Invalid run manifest in {}: {}", manifest_path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::print_json;
+
+    #[test]
+    fn test_run_manifest_reads_back() {
+        let manifest_path = std::env::temp_dir().join(format!("run_manifest-{}.json", std::process::id()));
+        let run_manifest = RunManifest {
+            arguments: vec![String::from("crawl"), String::from("https://example.com/")],
+            config: Some(String::from("max-depth = 2\n")),
+        };
+
+        print_json(&Artifact { metadata: None, data: &run_manifest }, &manifest_path, true, OutputCompression::None);
+        let artifact = load_run_manifest(&manifest_path).unwrap();
+        std::fs::remove_file(&manifest_path).unwrap();
+
+        assert_eq!(run_manifest, artifact.data);
+        assert!(load_run_manifest(&manifest_path).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+const USER_AGENT_FIELD: &str = "user-agent";
+const ALLOW_FIELD: &str = "allow";
+const DISALLOW_FIELD: &str = "disallow";
+const CRAWL_DELAY_FIELD: &str = "crawl-delay";
+const SITEMAP_FIELD: &str = "sitemap";
+const ANY_USER_AGENT: &str = "*";
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct RobotsTxt {
+    pub groups: Vec<RobotsGroup>,
+    pub sitemaps: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct RobotsGroup {
+    pub user_agents: Vec<String>,
+    pub rules: Vec<RobotsRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crawl_delay: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RobotsRule {
+    pub kind: RuleKind,
+    pub path: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleKind {
+    Allow,
+    Disallow,
+}
+
+// What a host's robots.txt said, and which of its rules the crawler actually honoured.
+#[derive(Serialize)]
+pub struct RobotsReport {
+    pub robots_link: String,
+    #[serde(flatten)]
+    pub robots_txt: RobotsTxt,
+    pub honored_rules: Vec<RobotsRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub honored_crawl_delay_secs: Option<f64>,
+}
+
+impl RobotsTxt {
+    // Every rule from the groups that apply to us, combined as if they were one group.
+    pub fn rules_for(&self, user_agent: &str) -> Vec<RobotsRule> {
+        self.groups_for(user_agent).into_iter().flat_map(|group| group.rules.iter()).cloned().collect()
+    }
+
+    pub fn crawl_delay_for(&self, user_agent: &str) -> Option<Duration> {
+        let crawl_delay = self.groups_for(user_agent).into_iter().find_map(|group| group.crawl_delay)?;
+
+        Duration::try_from_secs_f64(crawl_delay).ok()
+    }
+
+    // The groups naming our user agent, or failing that the "*" groups - a crawler only follows the catch-all rules
+    // when nothing is written for it specifically. Names are matched case-insensitively anywhere in the user agent,
+    // so "Googlebot" applies to a full browser-style string too.
+    fn groups_for(&self, user_agent: &str) -> Vec<&RobotsGroup> {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let names_user_agent = |group: &&RobotsGroup| {
+            group
+                .user_agents
+                .iter()
+                .any(|name| name != ANY_USER_AGENT && user_agent.contains(&name.to_ascii_lowercase()))
+        };
+        let names_any_user_agent = |group: &&RobotsGroup| group.user_agents.iter().any(|name| name == ANY_USER_AGENT);
+
+        let named_groups: Vec<&RobotsGroup> = self.groups.iter().filter(names_user_agent).collect();
+        if !named_groups.is_empty() {
+            return named_groups;
+        }
+
+        self.groups.iter().filter(names_any_user_agent).collect()
+    }
+}
+
+// The most specific (longest) matching rule decides, and Allow wins a tie. A path no rule matches is allowed. The
+// path includes its query string, since rules like "/search?q=" are written against it.
+pub fn is_allowed(rules: &[RobotsRule], path: &str) -> bool {
+    rules
+        .iter()
+        .filter(|rule| pattern_matches(&rule.path, path))
+        .max_by_key(|rule| (rule.path.len(), rule.kind == RuleKind::Allow))
+        .is_none_or(|rule| rule.kind == RuleKind::Allow)
+}
+
+// Patterns match from the start of the path. "*" matches any run of characters, and a trailing "$" means the path
+// has to end there.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored_to_end) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut pieces = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(pieces.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let pieces: Vec<&str> = pieces.collect();
+    let Some((last_piece, middle_pieces)) = pieces.split_last() else {
+        return !anchored_to_end || rest.is_empty();
+    };
+
+    // Each piece takes its earliest match, which leaves the most room for the ones after it.
+    for piece in middle_pieces {
+        match rest.find(piece) {
+            Some(index) => rest = &rest[index + piece.len()..],
+            None => return false,
+        }
+    }
+
+    if anchored_to_end {
+        rest.ends_with(last_piece)
+    } else {
+        rest.contains(last_piece)
+    }
+}
+
+// Consecutive User-agent lines share the group that follows them; a User-agent line after any rules starts a new
+// group. Rules before the first User-agent line don't belong to anyone and are dropped, as are unknown fields.
+pub fn parse_robots_txt(text_content: &str) -> RobotsTxt {
+    let mut robots_txt = RobotsTxt::default();
+    let mut group_has_rules = false;
+
+    for line in text_content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let field = field.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        match field.as_str() {
+            USER_AGENT_FIELD => {
+                if group_has_rules || robots_txt.groups.is_empty() {
+                    robots_txt.groups.push(RobotsGroup::default());
+                    group_has_rules = false;
+                }
+
+                if let Some(group) = robots_txt.groups.last_mut() {
+                    group.user_agents.push(value.to_string());
+                }
+            }
+            ALLOW_FIELD | DISALLOW_FIELD => {
+                let kind = if field == ALLOW_FIELD { RuleKind::Allow } else { RuleKind::Disallow };
+
+                if let Some(group) = robots_txt.groups.last_mut() {
+                    // An empty Disallow means nothing is disallowed, so there's no rule to record.
+                    if !value.is_empty() {
+                        group.rules.push(RobotsRule { kind, path: value.to_string() });
+                    }
+                    group_has_rules = true;
+                }
+            }
+            CRAWL_DELAY_FIELD => {
+                if let Some(group) = robots_txt.groups.last_mut() {
+                    group.crawl_delay = value.parse().ok();
+                    group_has_rules = true;
+                }
+            }
+            SITEMAP_FIELD => robots_txt.sitemaps.push(value.to_string()),
+            _ => {}
+        }
+    }
+
+    robots_txt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_rules_and_sitemaps() {
+        let robots_txt = parse_robots_txt(include_str!("../resources/testing_robots.txt"));
+
+        assert_eq!(2, robots_txt.groups.len());
+        assert_eq!(vec!["Googlebot", "Bingbot"], robots_txt.groups[0].user_agents);
+        assert_eq!(Some(2.5), robots_txt.groups[0].crawl_delay);
+        assert_eq!(vec!["*"], robots_txt.groups[1].user_agents);
+        assert_eq!(
+            vec![
+                RobotsRule { kind: RuleKind::Disallow, path: String::from("/private") },
+                RobotsRule { kind: RuleKind::Allow, path: String::from("/private/public-page") },
+            ],
+            robots_txt.groups[1].rules
+        );
+        assert_eq!(vec!["https://example.com/sitemap.xml"], robots_txt.sitemaps);
+    }
+
+    #[test]
+    fn test_only_our_groups_rules_apply() {
+        let robots_txt = parse_robots_txt(include_str!("../resources/testing_robots.txt"));
+
+        let our_rules = robots_txt.rules_for("my-crawler/1.0");
+        assert!(is_allowed(&our_rules, "/search"));
+        assert!(!is_allowed(&our_rules, "/private/page"));
+        assert!(is_allowed(&our_rules, "/private/public-page"));
+
+        let bingbot_rules = robots_txt.rules_for("Mozilla/5.0 (compatible; bingbot/2.0)");
+        assert!(!is_allowed(&bingbot_rules, "/search?q=boots"));
+        assert!(is_allowed(&bingbot_rules, "/private/page"));
+    }
+
+    #[test]
+    fn test_wildcards_and_end_anchors() {
+        let text_content = "User-agent: *\nDisallow: /*.pdf$\nDisallow: /shop/*/reviews\nAllow: /shop/*/reviews/top\n";
+        let robots_txt = parse_robots_txt(text_content);
+        let rules = robots_txt.rules_for("my-crawler/1.0");
+
+        assert!(!is_allowed(&rules, "/files/guide.pdf"));
+        assert!(is_allowed(&rules, "/files/guide.pdf?download=1"));
+        assert!(!is_allowed(&rules, "/shop/boots/reviews/2"));
+        assert!(is_allowed(&rules, "/shop/boots/reviews/top"));
+        assert!(is_allowed(&rules, "/shop/boots"));
+    }
+
+    #[test]
+    fn test_crawl_delay_for_our_user_agent() {
+        let robots_txt = parse_robots_txt(include_str!("../resources/testing_robots.txt"));
+
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        assert_eq!(Some(Duration::from_millis(2500)), robots_txt.crawl_delay_for(googlebot));
+        assert_eq!(None, robots_txt.crawl_delay_for("my-crawler/1.0"));
+
+        let robots_txt = parse_robots_txt("User-agent: *\nCrawl-delay: 1\n\nUser-agent: Bingbot\nCrawl-delay: 5");
+        assert_eq!(Some(Duration::from_secs(1)), robots_txt.crawl_delay_for("my-crawler/1.0"));
+    }
+}
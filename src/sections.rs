@@ -0,0 +1,168 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+const DEPTH_SETTING: &str = "depth";
+const PAGES_SETTING: &str = "pages";
+const UNLIMITED: &str = "unlimited";
+
+// A part of the site to crawl, by path prefix, with its own limits. Once there are any rules they act as an allow
+// list, and a limit left unset on a rule doesn't apply, whatever the crawl-wide one is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SectionRule {
+    pub path_prefix: String,
+    // Link hops from a seed, the same as the crawl-wide max_depth.
+    pub max_depth: Option<usize>,
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SectionAdmission {
+    Admitted,
+    OutsideSections,
+    TooDeep,
+    OverBudget,
+}
+
+// Decides which discovered links get queued, and counts the pages queued in each section against its budget.
+pub struct SectionTracker<'a> {
+    rules: &'a [SectionRule],
+    // Only used without rules - with them, each section has its own.
+    max_depth: Option<usize>,
+    pages_queued: Vec<usize>,
+}
+
+impl<'a> SectionTracker<'a> {
+    pub fn new(rules: &'a [SectionRule], max_depth: Option<usize>) -> Self {
+        SectionTracker { rules, max_depth, pages_queued: vec![0; rules.len()] }
+    }
+
+    pub fn classify(&self, link: &str, depth: usize) -> SectionAdmission {
+        if self.rules.is_empty() {
+            return match self.max_depth {
+                Some(max_depth) if depth > max_depth => SectionAdmission::TooDeep,
+                _ => SectionAdmission::Admitted,
+            };
+        }
+
+        let Some(index) = matching_rule(self.rules, link) else {
+            return SectionAdmission::OutsideSections;
+        };
+
+        let rule = &self.rules[index];
+        if rule.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            SectionAdmission::TooDeep
+        } else if rule.max_pages.is_some_and(|max_pages| self.pages_queued[index] >= max_pages) {
+            SectionAdmission::OverBudget
+        } else {
+            SectionAdmission::Admitted
+        }
+    }
+
+    // Like classify, but an admitted link takes up one of its section's pages.
+    pub fn admit(&mut self, link: &str, depth: usize) -> bool {
+        if self.classify(link, depth) != SectionAdmission::Admitted {
+            return false;
+        }
+
+        if let Some(index) = matching_rule(self.rules, link) {
+            self.pages_queued[index] += 1;
+        }
+
+        true
+    }
+}
+
+// Parses "/docs", "/archive:depth=1" or "/blog:depth=2,pages=100". Either limit can also be "unlimited", to spell
+// out that there isn't one.
+pub fn parse_section_rule(rule: &str) -> Result<SectionRule, String> {
+    let invalid_rule = || {
+        format!("Invalid section rule '{}', expected e.g. '/docs' or '/archive:depth=1,pages=100'", rule)
+    };
+    let (path_prefix, settings) = rule.split_once(':').unwrap_or((rule, ""));
+
+    if !path_prefix.starts_with('/') {
+        return Err(invalid_rule());
+    }
+
+    let mut section_rule = SectionRule { path_prefix: path_prefix.to_string(), max_depth: None, max_pages: None };
+    for setting in settings.split(',').filter(|setting| !setting.trim().is_empty()) {
+        let (name, value) = setting.split_once('=').ok_or_else(invalid_rule)?;
+        let limit = match value.trim() {
+            UNLIMITED => None,
+            value => Some(value.parse::<usize>().map_err(|_| invalid_rule())?),
+        };
+
+        match name.trim() {
+            DEPTH_SETTING => section_rule.max_depth = limit,
+            PAGES_SETTING => section_rule.max_pages = limit,
+            _ => return Err(invalid_rule()),
+        }
+    }
+
+    Ok(section_rule)
+}
+
+// The most specific rule wins, so "/docs/old" can be limited inside an unlimited "/docs".
+fn matching_rule(rules: &[SectionRule], link: &str) -> Option<usize> {
+    let url = Url::parse(link).ok()?;
+
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| path_is_under(url.path(), &rule.path_prefix))
+        .max_by_key(|(_, rule)| rule.path_prefix.len())
+        .map(|(index, _)| index)
+}
+
+// Prefixes match whole path segments - "/docs" covers "/docs/intro" but not "/docsify".
+fn path_is_under(path: &str, path_prefix: &str) -> bool {
+    let path_prefix = path_prefix.trim_end_matches('/');
+
+    path.strip_prefix(path_prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_rules_are_parsed() {
+        let rule = |path_prefix: &str, max_depth: Option<usize>, max_pages: Option<usize>| SectionRule {
+            path_prefix: path_prefix.to_string(),
+            max_depth,
+            max_pages,
+        };
+
+        assert_eq!(Ok(rule("/docs", None, None)), parse_section_rule("/docs"));
+        assert_eq!(Ok(rule("/archive", Some(1), None)), parse_section_rule("/archive:depth=1"));
+        assert_eq!(Ok(rule("/blog", None, Some(100))), parse_section_rule("/blog:depth=unlimited, pages=100"));
+        assert!(parse_section_rule("docs").is_err());
+        assert!(parse_section_rule("/docs:depth=deep").is_err());
+        assert!(parse_section_rule("/docs:width=1").is_err());
+    }
+
+    #[test]
+    fn test_links_are_admitted_by_their_most_specific_section() {
+        let rules = vec![
+            parse_section_rule("/docs").unwrap(),
+            parse_section_rule("/docs/old:depth=1").unwrap(),
+            parse_section_rule("/archive:pages=1").unwrap(),
+        ];
+        let mut sections = SectionTracker::new(&rules, Some(1));
+
+        assert_eq!(SectionAdmission::Admitted, sections.classify("https://example.com/docs/a/b", 5));
+        assert_eq!(SectionAdmission::TooDeep, sections.classify("https://example.com/docs/old/a", 2));
+        assert_eq!(SectionAdmission::OutsideSections, sections.classify("https://example.com/docsify", 1));
+        assert!(sections.admit("https://example.com/archive/2019", 1));
+        assert!(!sections.admit("https://example.com/archive/2020", 1));
+        assert_eq!(SectionAdmission::OverBudget, sections.classify("https://example.com/archive/2021", 1));
+    }
+
+    #[test]
+    fn test_crawl_wide_depth_applies_without_rules() {
+        let sections = SectionTracker::new(&[], Some(1));
+
+        assert_eq!(SectionAdmission::Admitted, sections.classify("https://example.com/a", 1));
+        assert_eq!(SectionAdmission::TooDeep, sections.classify("https://example.com/a/b", 2));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::archive::sha256_hex;
+
+// How many different values of a parameter have to serve the same page before it's taken to be a session ID.
+const MIN_MATCHING_VALUES: usize = 3;
+// Session IDs are long random tokens. Short or purely alphabetic values are far more likely to mean something.
+const MIN_OPAQUE_VALUE_LENGTH: usize = 16;
+
+// Sites that track sessions in the URL hand every new visitor - and a crawler without cookies is always a new visitor -
+// a fresh ID, so each page turns up again under endless URLs. A parameter is collapsed once several of its opaque
+// values are seen serving the same content on otherwise identical URLs.
+#[derive(Default)]
+pub struct SessionParamDetector {
+    // Parameter name -> the URL without it -> content hash -> the values seen serving that content.
+    values_by_content: HashMap<String, HashMap<String, HashMap<String, HashSet<String>>>>,
+    session_params: BTreeMap<String, SessionParam>,
+    pages_observed: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SessionParam {
+    // One of the links it was spotted on.
+    pub example_link: String,
+    // Links already queued with it are fetched without it, but earlier pages will have been fetched with it.
+    pub detected_after_pages: usize,
+}
+
+impl SessionParamDetector {
+    // Returns the parameters this page showed to be session IDs, for the first time.
+    pub fn observe(&mut self, link: &str, html: &str) -> Vec<String> {
+        self.pages_observed += 1;
+        let Some(query) = Url::parse(link).ok().and_then(|url| url.query().map(str::to_string)) else {
+            return Vec::new();
+        };
+
+        let mut detected_params = Vec::new();
+        for (name, value) in query_params(&query) {
+            if !is_opaque(value) || self.session_params.contains_key(name) {
+                continue;
+            }
+
+            // Links on the page carry an ID too - this one, or a fresh one each response - so they're blanked out
+            // before the content is compared.
+            let content_hash = sha256_hex(blank_param_values(&html.replace(value, ""), name).as_bytes());
+            let link_without_param = remove_params(link, |param_name| param_name == name);
+            let values = self
+                .values_by_content
+                .entry(name.to_string())
+                .or_default()
+                .entry(link_without_param)
+                .or_default()
+                .entry(content_hash)
+                .or_default();
+            values.insert(value.to_string());
+
+            if values.len() >= MIN_MATCHING_VALUES {
+                self.values_by_content.remove(name);
+                let session_param =
+                    SessionParam { example_link: link.to_string(), detected_after_pages: self.pages_observed };
+                self.session_params.insert(name.to_string(), session_param);
+                detected_params.push(name.to_string());
+            }
+        }
+
+        detected_params
+    }
+
+    // Returns None if the link has no session parameters to drop.
+    pub fn strip(&self, link: &str) -> Option<String> {
+        if self.session_params.is_empty() {
+            return None;
+        }
+
+        let url = Url::parse(link).ok()?;
+        let has_session_param = query_params(url.query()?).any(|(name, _)| self.session_params.contains_key(name));
+
+        has_session_param.then(|| remove_params(link, |name| self.session_params.contains_key(name)))
+    }
+
+    pub fn session_params(&self) -> &BTreeMap<String, SessionParam> {
+        &self.session_params
+    }
+}
+
+// Split by hand rather than decoded, so the parameters that are kept go back exactly as they were written.
+fn query_params(query: &str) -> impl Iterator<Item = (&str, &str)> {
+    query.split('&').map(|param| param.split_once('=').unwrap_or((param, "")))
+}
+
+fn remove_params(link: &str, is_removed: impl Fn(&str) -> bool) -> String {
+    let Ok(mut url) = Url::parse(link) else {
+        return link.to_string();
+    };
+
+    let kept_params: Vec<&str> = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !is_removed(param.split_once('=').map_or(*param, |(name, _)| name)))
+        .collect();
+    let query = kept_params.join("&");
+    url.set_query(Some(&query).filter(|query| !query.is_empty()).map(String::as_str));
+
+    url.to_string()
+}
+
+// "?sid=a1b2c3d4&page=2" becomes "?sid=&page=2".
+fn blank_param_values(html: &str, name: &str) -> String {
+    let param_start = format!("{}=", name);
+    let mut blanked_html = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(value_start) = rest.find(&param_start).map(|param_index| param_index + param_start.len()) {
+        blanked_html.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+        rest = rest.trim_start_matches(is_opaque_character);
+    }
+    blanked_html.push_str(rest);
+
+    blanked_html
+}
+
+fn is_opaque_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '-' || character == '_'
+}
+
+fn is_opaque(value: &str) -> bool {
+    value.len() >= MIN_OPAQUE_VALUE_LENGTH
+        && value.chars().all(is_opaque_character)
+        && value.chars().any(|character| character.is_ascii_digit())
+        && value.chars().any(|character| character.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Like a server handing out a new ID each response, the page's links don't carry the ID it was fetched with.
+    fn page_with_session(session_id: &str) -> String {
+        format!("<html><body><a href=\"/about?sid={}\">About</a></body></html>", session_id.to_uppercase())
+    }
+
+    #[test]
+    fn test_session_param_is_detected_from_matching_content() {
+        let mut detector = SessionParamDetector::default();
+        let session_ids = ["a1b2c3d4e5f6a7b8c9d0", "9f8e7d6c5b4a39281706", "0aa1bb2cc3dd4ee5ff66"];
+
+        let link = |session_id: &str| format!("https://example.com/shop?page=2&sid={}", session_id);
+        assert!(detector.observe(&link(session_ids[0]), &page_with_session(session_ids[0])).is_empty());
+        assert!(detector.observe(&link(session_ids[1]), &page_with_session(session_ids[1])).is_empty());
+        assert_eq!(
+            vec![String::from("sid")],
+            detector.observe(&link(session_ids[2]), &page_with_session(session_ids[2]))
+        );
+
+        assert_eq!(3, detector.session_params()["sid"].detected_after_pages);
+        assert_eq!(
+            Some(String::from("https://example.com/shop?page=2")),
+            detector.strip("https://example.com/shop?page=2&sid=ffffffffffffffff0000")
+        );
+        assert_eq!(Some(String::from("https://example.com/about")), detector.strip("https://example.com/about?sid=x"));
+        assert_eq!(None, detector.strip("https://example.com/shop?page=3"));
+    }
+
+    #[test]
+    fn test_params_that_change_the_content_are_kept() {
+        let mut detector = SessionParamDetector::default();
+        let product_ids = ["a1b2c3d4e5f6a7b8c9d0", "9f8e7d6c5b4a39281706", "0aa1bb2cc3dd4ee5ff66"];
+
+        for (index, product_id) in product_ids.iter().enumerate() {
+            let link = format!("https://example.com/product?id={}", product_id);
+            detector.observe(&link, &format!("<html><body>Product {}</body></html>", index));
+        }
+
+        // Short values are never taken for session IDs, however many serve the same page.
+        for page in ["1", "2", "3", "4"] {
+            detector.observe(&format!("https://example.com/list?page={}", page), "<html></html>");
+        }
+
+        assert!(detector.session_params().is_empty());
+    }
+}
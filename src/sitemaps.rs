@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+
+use crate::encoding::decode_html;
+
+const SITEMAP_INDEX_TAG: &str = "<sitemapindex";
+const LOC_START_TAG: &str = "<loc>";
+const LOC_END_TAG: &str = "</loc>";
+const CDATA_START: &str = "<![CDATA[";
+const CDATA_END: &str = "]]>";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+pub const DEFAULT_SITEMAP_PATH: &str = "/sitemap.xml";
+// Indexes can nest, and a site can list far more sitemaps than are worth fetching.
+const MAX_SITEMAPS: usize = 1000;
+
+#[derive(Debug, PartialEq)]
+pub enum Sitemap {
+    UrlSet(Vec<String>),
+    // Lists other sitemaps rather than pages.
+    Index(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SitemapKind {
+    #[serde(rename = "urlset")]
+    UrlSet,
+    Index,
+}
+
+#[derive(Serialize)]
+pub struct SitemapSummary {
+    pub kind: SitemapKind,
+    // Pages for a urlset, sitemaps for an index - before any of them are scoped to the crawl.
+    pub entries: usize,
+}
+
+// Every sitemap read, and the in-scope pages they added to the crawl.
+#[derive(Default, Serialize)]
+pub struct SitemapReport {
+    pub sitemaps: BTreeMap<String, SitemapSummary>,
+    pub failed_sitemaps: BTreeMap<String, String>,
+    pub seeded_links: BTreeSet<String>,
+    // Sitemaps left unread once MAX_SITEMAPS had been.
+    pub sitemaps_over_limit: usize,
+    #[serde(skip)]
+    reads_started: HashSet<String>,
+}
+
+impl SitemapReport {
+    // Returns false if the sitemap has already been read (or is being read), or too many sitemaps have been.
+    pub fn start_read(&mut self, sitemap_link: &str) -> bool {
+        if self.reads_started.contains(sitemap_link) {
+            return false;
+        }
+
+        if self.reads_started.len() >= MAX_SITEMAPS {
+            self.sitemaps_over_limit += 1;
+            return false;
+        }
+
+        self.reads_started.insert(sitemap_link.to_string())
+    }
+
+    pub fn record_sitemap(&mut self, sitemap_link: String, kind: SitemapKind, entries: usize) {
+        self.sitemaps.insert(sitemap_link, SitemapSummary { kind, entries });
+    }
+
+    pub fn record_failure(&mut self, sitemap_link: String, reason: String) {
+        self.failed_sitemaps.insert(sitemap_link, reason);
+    }
+}
+
+// How many of the sitemaps' pages the crawl reached by following links from its seeds - the ones it reached only
+// because a sitemap listed them are orphans as far as visitors are concerned.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SitemapCoverage {
+    pub sitemap_links: usize,
+    pub reached_by_links: usize,
+    pub percentage: f64,
+}
+
+// None when the sitemaps listed no pages in scope. Links are compared without a trailing slash, as they're recorded.
+pub fn sitemap_coverage<'a>(
+    sitemap_links: &BTreeSet<String>,
+    linked_pages: impl IntoIterator<Item = &'a str>,
+) -> Option<SitemapCoverage> {
+    if sitemap_links.is_empty() {
+        return None;
+    }
+
+    let linked_pages: HashSet<&str> = linked_pages.into_iter().map(|page| page.trim_end_matches('/')).collect();
+    let reached_by_links =
+        sitemap_links.iter().filter(|sitemap_link| linked_pages.contains(sitemap_link.trim_end_matches('/'))).count();
+
+    Some(SitemapCoverage {
+        sitemap_links: sitemap_links.len(),
+        reached_by_links,
+        percentage: 100.0 * reached_by_links as f64 / sitemap_links.len() as f64,
+    })
+}
+
+// Only the <loc> of each entry matters to the crawler - lastmod, priority and the rest are ignored.
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    let locs = loc_values(xml);
+
+    if xml.contains(SITEMAP_INDEX_TAG) {
+        Sitemap::Index(locs)
+    } else {
+        Sitemap::UrlSet(locs)
+    }
+}
+
+// Sitemaps are often served as .xml.gz, and without decompression support in the client a gzip Content-Encoding isn't
+// undone either, so the body itself is checked for gzip rather than trusting the URL or headers. The XML is decoded by
+// its declaration, as sitemaps aren't always UTF-8.
+pub fn decompress_sitemap(body: &[u8]) -> Option<String> {
+    if !body.starts_with(&GZIP_MAGIC) {
+        return Some(decode_html(body, None));
+    }
+
+    let mut xml = Vec::new();
+    GzDecoder::new(body).read_to_end(&mut xml).ok()?;
+    Some(decode_html(&xml, None))
+}
+
+fn loc_values(xml: &str) -> Vec<String> {
+    let mut locs = Vec::new();
+    let mut rest = xml;
+
+    while let Some(loc_start) = rest.find(LOC_START_TAG) {
+        rest = &rest[loc_start + LOC_START_TAG.len()..];
+        let Some(loc_end) = rest.find(LOC_END_TAG) else {
+            break;
+        };
+
+        let loc = rest[..loc_end].trim();
+        let loc = loc.strip_prefix(CDATA_START).and_then(|loc| loc.strip_suffix(CDATA_END)).unwrap_or(loc);
+        if !loc.is_empty() {
+            locs.push(unescape_xml(loc.trim()));
+        }
+        rest = &rest[loc_end + LOC_END_TAG.len()..];
+    }
+
+    locs
+}
+
+// The sitemap protocol requires URLs to be entity-escaped, e.g. "&amp;" between query parameters.
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn test_urlset_and_index_are_told_apart() {
+        let urlset = include_str!("../resources/testing_sitemap.xml");
+        let index = "<?xml version=\"1.0\"?><sitemapindex><sitemap><loc>https://example.com/sitemap-blog.xml.gz</loc>\
+                     </sitemap></sitemapindex>";
+
+        assert_eq!(
+            Sitemap::UrlSet(vec![
+                String::from("https://example.com/"),
+                String::from("https://example.com/shop?category=boots&sort=price"),
+                String::from("https://example.com/about"),
+            ]),
+            parse_sitemap(urlset)
+        );
+        assert_eq!(Sitemap::Index(vec![String::from("https://example.com/sitemap-blog.xml.gz")]), parse_sitemap(index));
+    }
+
+    #[test]
+    fn test_coverage_counts_sitemap_pages_reached_by_links() {
+        let sitemap_links = BTreeSet::from([
+            String::from("https://example.com"),
+            String::from("https://example.com/about"),
+            String::from("https://example.com/orphan"),
+            String::from("https://example.com/old"),
+        ]);
+        let linked_pages = ["https://example.com/", "https://example.com/about", "https://example.com/contact"];
+
+        let coverage = sitemap_coverage(&sitemap_links, linked_pages).unwrap();
+
+        assert_eq!(4, coverage.sitemap_links);
+        assert_eq!(2, coverage.reached_by_links);
+        assert_eq!(50.0, coverage.percentage);
+        assert_eq!(None, sitemap_coverage(&BTreeSet::new(), linked_pages));
+    }
+
+    #[test]
+    fn test_each_sitemap_is_read_once() {
+        let mut report = SitemapReport::default();
+
+        assert!(report.start_read("https://example.com/sitemap.xml"));
+        assert!(!report.start_read("https://example.com/sitemap.xml"));
+        (0..MAX_SITEMAPS).for_each(|index| {
+            report.start_read(&format!("https://example.com/sitemap-{}.xml", index));
+        });
+        assert_eq!(1, report.sitemaps_over_limit);
+    }
+
+    #[test]
+    fn test_gzipped_sitemaps_are_decompressed() {
+        let xml = include_str!("../resources/testing_sitemap.xml");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+
+        assert_eq!(Some(xml.to_string()), decompress_sitemap(&encoder.finish().unwrap()));
+        assert_eq!(Some(xml.to_string()), decompress_sitemap(xml.as_bytes()));
+    }
+
+    #[test]
+    fn test_sitemaps_are_decoded_by_their_declaration() {
+        let xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><urlset><url><loc>https://example.com/caf\xe9</loc>";
+
+        let xml = decompress_sitemap(xml).unwrap();
+
+        assert_eq!(Sitemap::UrlSet(vec![String::from("https://example.com/café")]), parse_sitemap(&xml));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use reqwest::Url;
+use serde::Serialize;
+use uuid::Uuid;
+
+// Real pages share a site's navigation and footer with its error page, so only a near-identical page counts.
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+// What the site serves for a URL that can't exist, as the set of words on the page. Error pages often repeat the
+// requested path back, so words from the path are left out of both sides of the comparison.
+#[derive(Serialize, Debug)]
+pub struct NotFoundFingerprint {
+    pub probe_link: String,
+    pub status: u16,
+    #[serde(skip)]
+    words: HashSet<String>,
+}
+
+// Each site's not-found fingerprint, and the pages that answered with a success status but matched it.
+#[derive(Default, Serialize)]
+pub struct SoftNotFoundReport {
+    pub fingerprints: BTreeMap<String, NotFoundFingerprint>,
+    pub soft_not_found_pages: BTreeSet<String>,
+}
+
+impl NotFoundFingerprint {
+    pub fn new(probe_link: String, status: u16, page_text: &str) -> Self {
+        let words = page_words(&probe_link, page_text);
+        NotFoundFingerprint { probe_link, status, words }
+    }
+
+    // An empty error page says nothing about what a soft 404 looks like, so it matches nothing.
+    pub fn matches(&self, link: &str, page_text: &str) -> bool {
+        !self.words.is_empty() && similarity(&self.words, &page_words(link, page_text)) >= SIMILARITY_THRESHOLD
+    }
+}
+
+impl SoftNotFoundReport {
+    pub fn record_fingerprint(&mut self, origin: String, fingerprint: NotFoundFingerprint) {
+        self.fingerprints.insert(origin, fingerprint);
+    }
+
+    // Returns whether the page looks like its site's not-found page.
+    pub fn check_page(&mut self, origin: &str, link: &str, page_text: &str) -> bool {
+        let is_soft_not_found = self
+            .fingerprints
+            .get(origin)
+            .is_some_and(|fingerprint| fingerprint.matches(link, page_text));
+
+        if is_soft_not_found {
+            self.soft_not_found_pages.insert(link.to_string());
+        }
+
+        is_soft_not_found
+    }
+}
+
+// A path on the site that nothing should ever answer with real content. It's nothing but the UUID, as any words in
+// it would be left out of the fingerprint and then missing from the comparison.
+pub fn probe_link(origin: &Url) -> Option<String> {
+    origin.join(&Uuid::new_v4().simple().to_string()).ok().map(|probe_url| probe_url.to_string())
+}
+
+fn page_words(link: &str, page_text: &str) -> HashSet<String> {
+    let path_words: HashSet<String> = Url::parse(link).map(|url| words(url.path()).collect()).unwrap_or_default();
+
+    words(page_text).filter(|word| !path_words.contains(word)).collect()
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().all(|character| character.is_ascii_digit()))
+        .map(str::to_lowercase)
+}
+
+// Jaccard similarity - the share of all the words on either page that appear on both.
+fn similarity(first: &HashSet<String>, second: &HashSet<String>) -> f64 {
+    let union_count = first.union(second).count();
+    if union_count == 0 {
+        return 1.0;
+    }
+
+    first.intersection(second).count() as f64 / union_count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOT_FOUND_TEXT: &str = "Shop Home Boots Sale Sorry, that page wasn't found. Try searching instead. © 2024";
+    const PROBE_LINK: &str = "https://example.com/0fa08f8bf3704e33947596a31a3d8e00";
+
+    #[test]
+    fn test_pages_matching_the_not_found_page_are_soft_404s() {
+        let probe_text = format!("{} /0fa08f8bf3704e33947596a31a3d8e00", NOT_FOUND_TEXT);
+        let fingerprint = NotFoundFingerprint::new(String::from(PROBE_LINK), 200, &probe_text);
+
+        let old_boots_text = format!("{} /old-boots", NOT_FOUND_TEXT);
+        assert!(fingerprint.matches("https://example.com/old-boots", &old_boots_text));
+        let boots_text = "Shop Home Boots Sale Leather boots in brown and black";
+        assert!(!fingerprint.matches("https://example.com/boots", boots_text));
+    }
+
+    #[test]
+    fn test_soft_404s_are_only_checked_against_their_own_site() {
+        let mut report = SoftNotFoundReport::default();
+        let fingerprint = NotFoundFingerprint::new(String::from(PROBE_LINK), 200, NOT_FOUND_TEXT);
+        report.record_fingerprint(String::from("https://example.com"), fingerprint);
+
+        assert!(report.check_page("https://example.com", "https://example.com/gone", NOT_FOUND_TEXT));
+        assert!(!report.check_page("https://other.example.com", "https://other.example.com/gone", NOT_FOUND_TEXT));
+        assert_eq!(BTreeSet::from([String::from("https://example.com/gone")]), report.soft_not_found_pages);
+    }
+}
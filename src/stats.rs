@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::latency::{path_template, LatencyHistogram};
+use crate::sitemaps::SitemapCoverage;
+
+const SLOWEST_PAGES_KEPT: usize = 10;
+
+// Aggregates only - nothing in here grows with the number of pages crawled beyond one entry per
+// host/status/path template.
+#[derive(Default)]
+pub struct CrawlStats {
+    pub pages_processed: usize,
+    pub responses: usize,
+    pub failed_requests: usize,
+    // Rate-limited requests that were put back into the frontier to try again later.
+    pub requeued_requests: usize,
+    pub total_response_time: Duration,
+    pub crawl_duration: Duration,
+    pub status_counts: BTreeMap<u16, usize>,
+    pub hosts: BTreeMap<String, HostStats>,
+    // Kept sorted slowest first and capped at SLOWEST_PAGES_KEPT.
+    pub slowest_pages: Vec<(String, Duration)>,
+    pub latency_by_template: BTreeMap<String, LatencyHistogram>,
+    // Only once sitemaps have been read, and worked out when the crawl finishes.
+    pub sitemap_coverage: Option<SitemapCoverage>,
+}
+
+// The same numbers broken down for a single host, so crawls that span several hosts stay readable.
+#[derive(Default, Debug)]
+pub struct HostStats {
+    pub pages: usize,
+    pub responses: usize,
+    pub failed_requests: usize,
+    pub errors: usize,
+    pub bytes: u64,
+    pub total_response_time: Duration,
+}
+
+impl HostStats {
+    pub fn average_response_time(&self) -> Duration {
+        match u32::try_from(self.responses) {
+            Ok(responses) if responses > 0 => self.total_response_time / responses,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl CrawlStats {
+    pub fn record_response(&mut self, link: &str, status: u16, elapsed: Duration) {
+        self.responses += 1;
+        self.total_response_time += elapsed;
+        *self.status_counts.entry(status).or_default() += 1;
+
+        let host_stats = self.host_stats(link);
+        host_stats.responses += 1;
+        host_stats.total_response_time += elapsed;
+
+        if status >= 400 {
+            self.record_error(link);
+        }
+
+        self.record_duration(link, elapsed);
+        self.latency_by_template.entry(path_template(link)).or_default().record(elapsed);
+    }
+
+    pub fn record_request_failure(&mut self, link: &str) {
+        self.failed_requests += 1;
+        self.host_stats(link).failed_requests += 1;
+        self.record_error(link);
+    }
+
+    pub fn record_page_processed(&mut self, link: &str) {
+        self.pages_processed += 1;
+        self.host_stats(link).pages += 1;
+    }
+
+    pub fn record_bytes(&mut self, link: &str, bytes: u64) {
+        self.host_stats(link).bytes += bytes;
+    }
+
+    fn record_error(&mut self, link: &str) {
+        self.host_stats(link).errors += 1;
+    }
+
+    fn host_stats(&mut self, link: &str) -> &mut HostStats {
+        let host = Url::parse(link)
+            .ok()
+            .and_then(|parsed_url| parsed_url.host_str().map(str::to_string))
+            .unwrap_or_default();
+
+        self.hosts.entry(host).or_default()
+    }
+
+    // Responses that came back 4xx/5xx - links on the site that point at something broken.
+    pub fn broken_links(&self) -> usize {
+        self.status_counts.range(400..).map(|(_, count)| count).sum()
+    }
+
+    pub fn top_error_hosts(&self, count: usize) -> Vec<(&String, &usize)> {
+        let mut hosts: Vec<(&String, &usize)> = self
+            .hosts
+            .iter()
+            .filter(|(_, host_stats)| host_stats.errors > 0)
+            .map(|(host, host_stats)| (host, &host_stats.errors))
+            .collect();
+        hosts.sort_by(|(host_a, errors_a), (host_b, errors_b)| errors_b.cmp(errors_a).then(host_a.cmp(host_b)));
+        hosts.truncate(count);
+        hosts
+    }
+
+    fn record_duration(&mut self, link: &str, elapsed: Duration) {
+        let position = self.slowest_pages.partition_point(|(_, duration)| *duration >= elapsed);
+
+        if position < SLOWEST_PAGES_KEPT {
+            self.slowest_pages.insert(position, (link.to_string(), elapsed));
+            self.slowest_pages.truncate(SLOWEST_PAGES_KEPT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slowest_pages_are_sorted_and_capped() {
+        let mut stats = CrawlStats::default();
+
+        for millis in 0..(SLOWEST_PAGES_KEPT as u64 + 5) {
+            stats.record_response(&format!("https://example.com/{}", millis), 200, Duration::from_millis(millis));
+        }
+
+        assert_eq!(SLOWEST_PAGES_KEPT, stats.slowest_pages.len());
+        assert_eq!(Duration::from_millis(SLOWEST_PAGES_KEPT as u64 + 4), stats.slowest_pages[0].1);
+    }
+
+    #[test]
+    fn test_error_statuses_count_against_host() {
+        let mut stats = CrawlStats::default();
+
+        stats.record_response("https://example.com/a", 404, Duration::ZERO);
+        stats.record_response("https://example.com/b", 200, Duration::ZERO);
+        stats.record_request_failure("https://cdn.example.com/c");
+        stats.record_response("https://example.com/d", 500, Duration::ZERO);
+
+        let top_hosts = stats.top_error_hosts(1);
+
+        assert_eq!(vec![(&String::from("example.com"), &2)], top_hosts);
+        assert_eq!(Some(&1), stats.status_counts.get(&404));
+        assert_eq!(2, stats.broken_links());
+        assert_eq!(1, stats.failed_requests);
+    }
+
+    #[test]
+    fn test_host_stats_are_kept_per_host() {
+        let mut stats = CrawlStats::default();
+
+        stats.record_response("https://example.com/a", 200, Duration::from_millis(10));
+        stats.record_response("https://example.com/b", 200, Duration::from_millis(30));
+        stats.record_page_processed("https://example.com/a");
+        stats.record_bytes("https://example.com/a", 2048);
+        stats.record_request_failure("https://blog.example.com/");
+
+        assert_eq!(2, stats.hosts.len());
+        assert_eq!(1, stats.hosts["example.com"].pages);
+        assert_eq!(2048, stats.hosts["example.com"].bytes);
+        assert_eq!(Duration::from_millis(20), stats.hosts["example.com"].average_response_time());
+        assert_eq!(1, stats.hosts["blog.example.com"].errors);
+    }
+
+    #[test]
+    fn test_latencies_are_grouped_by_path_template() {
+        let mut stats = CrawlStats::default();
+
+        stats.record_response("https://example.com/product/1", 200, Duration::from_millis(10));
+        stats.record_response("https://example.com/product/2", 200, Duration::from_millis(30));
+        stats.record_response("https://example.com/about", 200, Duration::from_millis(5));
+
+        assert_eq!(2, stats.latency_by_template.len());
+        assert_eq!(2, stats.latency_by_template["/product/{id}"].count());
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::io::{stdout, IsTerminal};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::errors::CrawlError;
+use crate::latency::LatencyHistogram;
+use crate::sitemaps::SitemapCoverage;
+use crate::stats::{CrawlStats, HostStats};
+
+const TOP_ERROR_HOSTS_SHOWN: usize = 5;
+const SLOWEST_TEMPLATES_SHOWN: usize = 10;
+const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
+const BOLD: &str = "\x1b[1m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// Just the aggregate numbers, for dashboards and CI annotations that don't need per-URL data.
+#[derive(Serialize)]
+pub struct SummaryReport {
+    pub pages_crawled: usize,
+    pub links_discovered: usize,
+    pub requests: usize,
+    pub failed_requests: usize,
+    pub requeued_requests: usize,
+    pub broken_links: usize,
+    pub error_rate: f64,
+    pub elapsed_seconds: f64,
+    pub average_response_seconds: f64,
+    pub status_counts: BTreeMap<u16, usize>,
+    pub latency_by_template: BTreeMap<String, LatencyPercentiles>,
+    pub hosts: BTreeMap<String, HostSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sitemap_coverage: Option<SitemapCoverage>,
+}
+
+// What scrape_site hands back, so callers can act on how the crawl went without reading any of the outputs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CrawlReport {
+    pub pages_visited: usize,
+    pub links_discovered: usize,
+    // Requests that never got a response, e.g. timeouts and refused connections.
+    pub failed_requests: usize,
+    // Responses with a 4xx or 5xx status.
+    pub error_responses: usize,
+    // From the start of scrape_site, so reading robots.txt and sitemaps is included.
+    pub elapsed: Duration,
+    pub bytes_downloaded: u64,
+    // The crawl was stopped early, so the numbers only cover the pages finished before that.
+    pub cancelled: bool,
+    // Every URL that failed, with why - the same as errors.json.
+    pub errors: BTreeMap<String, CrawlError>,
+}
+
+impl CrawlReport {
+    pub fn has_errors(&self) -> bool {
+        self.failed_requests > 0 || self.error_responses > 0
+    }
+}
+
+#[derive(Serialize)]
+pub struct HostSummary {
+    pub pages: usize,
+    pub requests: usize,
+    pub errors: usize,
+    pub bytes: u64,
+    pub average_response_seconds: f64,
+}
+
+#[derive(Serialize)]
+pub struct LatencyPercentiles {
+    pub responses: usize,
+    pub p50_seconds: f64,
+    pub p90_seconds: f64,
+    pub p99_seconds: f64,
+}
+
+impl From<&LatencyHistogram> for LatencyPercentiles {
+    fn from(histogram: &LatencyHistogram) -> Self {
+        LatencyPercentiles {
+            responses: histogram.count(),
+            p50_seconds: histogram.percentile(0.5).as_secs_f64(),
+            p90_seconds: histogram.percentile(0.9).as_secs_f64(),
+            p99_seconds: histogram.percentile(0.99).as_secs_f64(),
+        }
+    }
+}
+
+pub fn build_summary_report(stats: &CrawlStats, links_discovered: usize) -> SummaryReport {
+    let requests = stats.responses + stats.failed_requests;
+    let errors = stats.failed_requests + stats.broken_links();
+
+    SummaryReport {
+        pages_crawled: stats.pages_processed,
+        links_discovered,
+        requests,
+        failed_requests: stats.failed_requests,
+        requeued_requests: stats.requeued_requests,
+        broken_links: stats.broken_links(),
+        error_rate: ratio(errors as f64, requests),
+        elapsed_seconds: stats.crawl_duration.as_secs_f64(),
+        average_response_seconds: ratio(stats.total_response_time.as_secs_f64(), stats.responses),
+        status_counts: stats.status_counts.clone(),
+        latency_by_template: stats
+            .latency_by_template
+            .iter()
+            .map(|(template, histogram)| (template.clone(), LatencyPercentiles::from(histogram)))
+            .collect(),
+        hosts: stats.hosts.iter().map(|(host, host_stats)| (host.clone(), HostSummary::from(host_stats))).collect(),
+        sitemap_coverage: stats.sitemap_coverage.clone(),
+    }
+}
+
+pub fn build_crawl_report(
+    stats: &CrawlStats,
+    links_discovered: usize,
+    errors: BTreeMap<String, CrawlError>,
+    elapsed: Duration,
+    cancelled: bool,
+) -> CrawlReport {
+    CrawlReport {
+        pages_visited: stats.pages_processed,
+        links_discovered,
+        failed_requests: stats.failed_requests,
+        error_responses: stats.broken_links(),
+        elapsed,
+        bytes_downloaded: stats.hosts.values().map(|host_stats| host_stats.bytes).sum(),
+        cancelled,
+        errors,
+    }
+}
+
+impl From<&HostStats> for HostSummary {
+    fn from(host_stats: &HostStats) -> Self {
+        HostSummary {
+            pages: host_stats.pages,
+            requests: host_stats.responses + host_stats.failed_requests,
+            errors: host_stats.errors,
+            bytes: host_stats.bytes,
+            average_response_seconds: host_stats.average_response_time().as_secs_f64(),
+        }
+    }
+}
+
+fn ratio(numerator: f64, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator / denominator as f64
+    }
+}
+
+// Escape codes only make sense on a terminal - piped or redirected output stays plain.
+pub fn should_use_color() -> bool {
+    stdout().is_terminal() && env::var_os(NO_COLOR_ENV_VAR).is_none()
+}
+
+pub fn render_summary(stats: &CrawlStats, use_color: bool) -> String {
+    let paint = |color: &str, text: String| {
+        if use_color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text
+        }
+    };
+
+    let mut summary = String::new();
+
+    // The headline number for sites that rely on their sitemaps, so it comes first.
+    if let Some(coverage) = &stats.sitemap_coverage {
+        let coverage_color = if coverage.reached_by_links < coverage.sitemap_links { YELLOW } else { GREEN };
+        writeln!(summary, "{}", paint(BOLD, String::from("Sitemap coverage"))).unwrap();
+        writeln!(
+            summary,
+            "  {}  {} of {} sitemap pages reached by following links",
+            paint(coverage_color, format!("{:>7.1}%", coverage.percentage)),
+            coverage.reached_by_links,
+            coverage.sitemap_links
+        )
+        .unwrap();
+    }
+
+    writeln!(summary, "{}", paint(BOLD, String::from("Status codes"))).unwrap();
+    for (status, count) in stats.status_counts.iter() {
+        writeln!(summary, "  {}  {:>8}", paint(status_color(*status), status.to_string()), count).unwrap();
+    }
+
+    // A single-host crawl's numbers are already the totals, so the breakdown only appears once there's more than one.
+    if stats.hosts.len() > 1 {
+        let heading = String::from("Hosts (pages / errors / downloaded / average response)");
+        writeln!(summary, "{}", paint(BOLD, heading)).unwrap();
+        for (host, host_stats) in stats.hosts.iter() {
+            writeln!(
+                summary,
+                "  {:>8}  {}  {:>10}  {:>10.3?}  {}",
+                host_stats.pages,
+                paint(if host_stats.errors > 0 { RED } else { GREEN }, format!("{:>8}", host_stats.errors)),
+                format_bytes(host_stats.bytes),
+                host_stats.average_response_time(),
+                host
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(summary, "{}", paint(BOLD, String::from("Top error hosts"))).unwrap();
+    for (host, errors) in stats.top_error_hosts(TOP_ERROR_HOSTS_SHOWN) {
+        writeln!(summary, "  {}  {}", paint(RED, format!("{:>8}", errors)), host).unwrap();
+    }
+
+    writeln!(summary, "{}", paint(BOLD, String::from("Slowest pages"))).unwrap();
+    for (link, duration) in stats.slowest_pages.iter() {
+        writeln!(summary, "  {:>10.3?}  {}", duration, link).unwrap();
+    }
+
+    // Only the slowest sections are printed - the JSON summary has all of them.
+    let mut templates: Vec<(&String, LatencyPercentiles)> = stats
+        .latency_by_template
+        .iter()
+        .map(|(template, histogram)| (template, LatencyPercentiles::from(histogram)))
+        .collect();
+    templates.sort_by(|(_, a), (_, b)| b.p90_seconds.total_cmp(&a.p90_seconds));
+    templates.truncate(SLOWEST_TEMPLATES_SHOWN);
+
+    writeln!(summary, "{}", paint(BOLD, String::from("Response times by section (p50 / p90 / p99)"))).unwrap();
+    for (template, percentiles) in templates {
+        let p50 = Duration::from_secs_f64(percentiles.p50_seconds);
+        let p90 = Duration::from_secs_f64(percentiles.p90_seconds);
+        let p99 = Duration::from_secs_f64(percentiles.p99_seconds);
+        writeln!(summary, "  {:>10.3?}  {:>10.3?}  {:>10.3?}  {}", p50, p90, p99, template).unwrap();
+    }
+
+    summary
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn status_color(status: u16) -> &'static str {
+    match status {
+        200..=299 => GREEN,
+        300..=399 => CYAN,
+        400..=499 => YELLOW,
+        _ => RED,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_stats() -> CrawlStats {
+        let mut stats = CrawlStats::default();
+        stats.record_response("https://example.com/a", 200, Duration::from_millis(120));
+        stats.record_response("https://example.com/b", 404, Duration::from_millis(40));
+        stats
+    }
+
+    #[test]
+    fn test_summary_report_error_rate() {
+        let mut stats = sample_stats();
+        stats.record_request_failure("https://example.com/c");
+
+        let report = build_summary_report(&stats, 3);
+
+        assert_eq!(3, report.requests);
+        assert_eq!(1, report.broken_links);
+        assert!((report.error_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_crawl_report_totals_every_host() {
+        let mut stats = sample_stats();
+        stats.record_page_processed("https://example.com/a");
+        stats.record_bytes("https://example.com/a", 2048);
+        stats.record_bytes("https://blog.example.com/", 512);
+
+        let errors = BTreeMap::from([(String::from("https://example.com/b"), CrawlError::HttpStatus { status: 404 })]);
+        let report = build_crawl_report(&stats, 5, errors, Duration::from_secs(2), false);
+
+        assert_eq!(1, report.pages_visited);
+        assert_eq!(5, report.links_discovered);
+        assert_eq!(1, report.error_responses);
+        assert_eq!(2560, report.bytes_downloaded);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_plain_summary_has_no_escape_codes() {
+        let mut stats = sample_stats();
+        stats.sitemap_coverage = Some(SitemapCoverage { sitemap_links: 8, reached_by_links: 6, percentage: 75.0 });
+        let summary = render_summary(&stats, false);
+
+        assert!(!summary.contains('\x1b'));
+        assert!(summary.contains("75.0%  6 of 8 sitemap pages"));
+        assert!(summary.contains("404"));
+        assert!(summary.contains("https://example.com/a"));
+        assert!(summary.contains("Response times by section"));
+    }
+
+    #[test]
+    fn test_hosts_only_shown_for_multiple_hosts() {
+        let mut stats = sample_stats();
+        assert!(!render_summary(&stats, false).contains("Hosts"));
+
+        stats.record_response("https://blog.example.com/", 200, Duration::from_millis(10));
+        stats.record_bytes("https://blog.example.com/", 1536);
+        let summary = render_summary(&stats, false);
+
+        assert!(summary.contains("Hosts"));
+        assert!(summary.contains("1.5 KiB"));
+        assert_eq!(2, build_summary_report(&stats, 0).hosts.len());
+    }
+
+    #[test]
+    fn test_colored_summary_highlights_statuses() {
+        let summary = render_summary(&sample_stats(), true);
+
+        assert!(summary.contains(&format!("{}404{}", YELLOW, RESET)));
+    }
+}
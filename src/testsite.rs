@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const ROBOTS_TXT_PATH: &str = "/robots.txt";
+const ROOT_PATH: &str = "/";
+const HTML_CONTENT_TYPE: &str = "text/html";
+const TEXT_CONTENT_TYPE: &str = "text/plain";
+
+// The shape of a generated site. Everything is derived from these numbers, so the same spec always
+// produces the same pages and links.
+#[derive(Clone, Debug)]
+pub struct SiteSpec {
+    pub page_count: usize,
+    pub branching_factor: usize,
+    pub broken_links: usize,
+    pub redirect_chains: usize,
+    pub redirect_chain_length: usize,
+    pub disallowed_paths: Vec<String>,
+}
+
+impl Default for SiteSpec {
+    fn default() -> Self {
+        SiteSpec {
+            page_count: 50,
+            branching_factor: 3,
+            broken_links: 2,
+            redirect_chains: 1,
+            redirect_chain_length: 2,
+            disallowed_paths: vec![String::from("/private")],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SyntheticResponse {
+    Page(String),
+    Redirect(String),
+}
+
+pub struct SyntheticSite {
+    responses: HashMap<String, SyntheticResponse>,
+    robots_txt: String,
+}
+
+impl SyntheticSite {
+    // Pages form a tree: page i links to pages i*b+1..=i*b+b, and every page links back to the root.
+    pub fn generate(spec: &SiteSpec) -> Self {
+        let page_count = spec.page_count.max(1);
+        let mut links_by_page: Vec<Vec<String>> = (0..page_count)
+            .map(|page| {
+                let first_child = page * spec.branching_factor + 1;
+                let mut links: Vec<String> = (first_child..first_child + spec.branching_factor)
+                    .filter(|child| *child < page_count)
+                    .map(page_path)
+                    .collect();
+                links.push(String::from(ROOT_PATH));
+                links
+            })
+            .collect();
+
+        for broken_link in 0..spec.broken_links {
+            links_by_page[broken_link % page_count].push(format!("/missing/{}", broken_link));
+        }
+
+        let mut responses = HashMap::new();
+
+        for chain in 0..spec.redirect_chains {
+            let hops = spec.redirect_chain_length.max(1);
+            let target_page = chain % page_count;
+
+            for hop in 0..hops {
+                let location = if hop + 1 == hops { page_path(target_page) } else { redirect_path(chain, hop + 1) };
+                responses.insert(redirect_path(chain, hop), SyntheticResponse::Redirect(location));
+            }
+
+            links_by_page[target_page].push(redirect_path(chain, 0));
+        }
+
+        for disallowed_path in spec.disallowed_paths.iter() {
+            let hidden_page = format!("{}/page", disallowed_path.trim_end_matches('/'));
+            responses.insert(hidden_page.clone(), SyntheticResponse::Page(render_page(&hidden_page, &[])));
+            links_by_page[0].push(hidden_page);
+        }
+
+        for (page, links) in links_by_page.iter().enumerate() {
+            let path = page_path(page);
+            responses.insert(path.clone(), SyntheticResponse::Page(render_page(&path, links)));
+        }
+
+        let mut robots_txt = String::from("User-agent: *\n");
+        for disallowed_path in spec.disallowed_paths.iter() {
+            writeln!(robots_txt, "Disallow: {}", disallowed_path).unwrap();
+        }
+
+        SyntheticSite { responses, robots_txt }
+    }
+
+    pub fn response(&self, path: &str) -> Option<&SyntheticResponse> {
+        self.responses.get(path)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.responses.values().filter(|response| matches!(response, SyntheticResponse::Page(_))).count()
+    }
+
+    // Accepts connections until the listener fails. Bind to port 0 in tests to get a free port.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let site = self.clone();
+
+            tokio::spawn(async move {
+                site.handle_connection(stream).await.ok();
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut reader = BufReader::new(&mut stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await?;
+
+        // Headers aren't needed for anything, but they have to be read off the socket before responding.
+        let mut header_line = String::new();
+        while reader.read_line(&mut header_line).await? > 2 {
+            header_line.clear();
+        }
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or(ROOT_PATH);
+        let path = path.split(['?', '#']).next().unwrap_or(ROOT_PATH);
+
+        let http_response = match (path, self.response(path)) {
+            (ROBOTS_TXT_PATH, _) => http_response("200 OK", TEXT_CONTENT_TYPE, "", &self.robots_txt),
+            (_, Some(SyntheticResponse::Page(html))) => http_response("200 OK", HTML_CONTENT_TYPE, "", html),
+            (_, Some(SyntheticResponse::Redirect(location))) => {
+                let location_header = format!("Location: {}\r\n", location);
+                http_response("301 Moved Permanently", HTML_CONTENT_TYPE, &location_header, "")
+            }
+            (_, None) => http_response("404 Not Found", HTML_CONTENT_TYPE, "", "<html><body>Not found</body></html>"),
+        };
+
+        stream.write_all(http_response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+fn page_path(page: usize) -> String {
+    match page {
+        0 => String::from(ROOT_PATH),
+        _ => format!("/page/{}", page),
+    }
+}
+
+fn redirect_path(chain: usize, hop: usize) -> String {
+    format!("/redirect/{}/{}", chain, hop)
+}
+
+fn render_page(path: &str, links: &[String]) -> String {
+    let mut html = format!("<html>\n<head>\n    <title>{}</title>\n</head>\n<body>\n", path);
+
+    for link in links {
+        writeln!(html, "    <a href=\"{}\">{}</a>", link, link).unwrap();
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn http_response(status: &str, content_type: &str, extra_headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        extra_headers,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_spec() -> SiteSpec {
+        SiteSpec {
+            page_count: 7,
+            branching_factor: 2,
+            broken_links: 1,
+            redirect_chains: 1,
+            redirect_chain_length: 2,
+            disallowed_paths: vec![String::from("/private")],
+        }
+    }
+
+    #[test]
+    fn test_generated_site_is_a_tree_of_pages() {
+        let site = SyntheticSite::generate(&small_spec());
+
+        // Seven tree pages plus the page hidden under the disallowed path.
+        assert_eq!(8, site.page_count());
+
+        let Some(SyntheticResponse::Page(root_html)) = site.response("/") else { panic!("Root page missing") };
+        assert!(root_html.contains("href=\"/page/1\""));
+        assert!(root_html.contains("href=\"/page/2\""));
+        assert!(root_html.contains("href=\"/missing/0\""));
+        assert!(root_html.contains("href=\"/private/page\""));
+    }
+
+    #[test]
+    fn test_redirect_chain_ends_at_a_page() {
+        let site = SyntheticSite::generate(&small_spec());
+
+        assert_eq!(Some(&SyntheticResponse::Redirect(String::from("/redirect/0/1"))), site.response("/redirect/0/0"));
+        assert_eq!(Some(&SyntheticResponse::Redirect(String::from("/"))), site.response("/redirect/0/1"));
+    }
+
+    #[tokio::test]
+    async fn test_served_site_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(SyntheticSite::generate(&small_spec())).serve(listener));
+
+        let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+        let base = format!("http://{}", address);
+
+        let page = client.get(format!("{}/page/3", base)).send().await.unwrap();
+        assert_eq!(200, page.status().as_u16());
+        assert_eq!("text/html", page.headers()["Content-Type"]);
+
+        let missing = client.get(format!("{}/missing/0", base)).send().await.unwrap();
+        assert_eq!(404, missing.status().as_u16());
+
+        let redirect = client.get(format!("{}/redirect/0/0", base)).send().await.unwrap();
+        assert_eq!(301, redirect.status().as_u16());
+
+        let robots = client.get(format!("{}/robots.txt", base)).send().await.unwrap().text().await.unwrap();
+        assert!(robots.contains("Disallow: /private"));
+    }
+}
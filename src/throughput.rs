@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use reqwest::Response;
+use tokio::time::{timeout_at, Instant};
+
+use crate::errors::CrawlError;
+
+// How long a body is given before its rate is held against it. Servers often flush their headers and then take a
+// moment to render the page, which says nothing about how the rest will arrive.
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+// Reads the whole body, giving up once it's arriving at less than the minimum rate - so an endpoint that trickles a
+// byte at a time, or goes quiet halfway through, can't hold a worker until the request timeout. Time spent waiting
+// for the next chunk counts, so a stalled connection is caught as soon as it's fallen behind.
+//
+// A body over the maximum size is abandoned as soon as it's declared or read past it, so one huge response can't
+// balloon the crawl's memory. A rate or size of 0 turns that guard off.
+pub async fn read_body(
+    mut response: Response,
+    min_bytes_per_sec: u64,
+    max_body_size: u64,
+) -> Result<Vec<u8>, CrawlError> {
+    let too_large = |size: u64| max_body_size > 0 && size > max_body_size;
+    if response.content_length().is_some_and(too_large) {
+        return Err(CrawlError::TooLarge { max_body_size });
+    }
+
+    let body_start = Instant::now();
+    let mut body = Vec::new();
+
+    loop {
+        let chunk = if min_bytes_per_sec == 0 {
+            response.chunk().await
+        } else {
+            // The latest the next chunk can arrive with what's been read so far still at the minimum rate.
+            let allowed_time = Duration::from_secs_f64(body.len() as f64 / min_bytes_per_sec as f64);
+            let deadline = body_start + allowed_time.max(GRACE_PERIOD);
+
+            let Ok(chunk) = timeout_at(deadline, response.chunk()).await else {
+                let bytes_per_sec = (body.len() as f64 / body_start.elapsed().as_secs_f64()) as u64;
+                return Err(CrawlError::TooSlow { bytes_per_sec });
+            };
+            chunk
+        };
+
+        match chunk.map_err(body_error)? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => return Ok(body),
+        }
+
+        if too_large(body.len() as u64) {
+            return Err(CrawlError::TooLarge { max_body_size });
+        }
+    }
+}
+
+fn body_error(err: reqwest::Error) -> CrawlError {
+    CrawlError::Body { message: err.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    // Answers one request with the headers and the first part of a body, then holds the connection open without
+    // sending the rest.
+    async fn serve_stalled_body(listener: TcpListener, sent_body: &'static str, content_length: usize) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let headers = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", content_length);
+        stream.write_all(headers.as_bytes()).await.unwrap();
+        stream.write_all(sent_body.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    }
+
+    #[tokio::test]
+    async fn test_stalled_bodies_are_abandoned_after_the_grace_period() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_stalled_body(listener, "<html>", 100_000));
+
+        let response = reqwest::get(format!("http://{}/", address)).await.unwrap();
+        let read_start = Instant::now();
+        let read_result = read_body(response, 1024, 0).await;
+
+        assert!(matches!(read_result, Err(CrawlError::TooSlow { bytes_per_sec }) if bytes_per_sec < 10));
+        assert!(read_start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_complete_bodies_are_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_stalled_body(listener, "<html></html>", 13));
+
+        let response = reqwest::get(format!("http://{}/", address)).await.unwrap();
+
+        assert_eq!(Ok(b"<html></html>".to_vec()), read_body(response, 1024, 0).await);
+    }
+
+    #[tokio::test]
+    async fn test_bodies_over_the_maximum_size_are_abandoned() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_stalled_body(listener, "<html></html>", 13));
+
+        let response = reqwest::get(format!("http://{}/", address)).await.unwrap();
+
+        assert_eq!(Err(CrawlError::TooLarge { max_body_size: 12 }), read_body(response, 0, 12).await);
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_lengths_are_checked_as_they_are_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let chunk = "a".repeat(1000);
+            stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+            loop {
+                let chunk = format!("{:x}\r\n{}\r\n", chunk.len(), chunk);
+                if stream.write_all(chunk.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let response = reqwest::get(format!("http://{}/", address)).await.unwrap();
+
+        assert_eq!(Err(CrawlError::TooLarge { max_body_size: 10_000 }), read_body(response, 1024, 10_000).await);
+    }
+}
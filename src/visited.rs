@@ -0,0 +1,104 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{remove_file, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Every link seen so far. Under memory pressure the links themselves move out to a file on disk and only a 64-bit
+// fingerprint of each is kept for lookups - a collision would wrongly skip a link, but at crawl sizes that's
+// vanishingly unlikely.
+#[derive(Default)]
+pub struct VisitedLinks {
+    links: HashSet<Arc<str>>,
+    spilled_fingerprints: HashSet<u64>,
+    spill_path: Option<PathBuf>,
+}
+
+impl VisitedLinks {
+    // Returns None if the link has been seen before. Most links found on a page already have been, so they're
+    // checked by reference and only a new link is copied - once, into an Arc shared with whoever queues it.
+    pub fn insert(&mut self, link: &str) -> Option<Arc<str>> {
+        if self.links.contains(link) || self.spilled_fingerprints.contains(&fingerprint(link)) {
+            return None;
+        }
+
+        let link: Arc<str> = Arc::from(link);
+        self.links.insert(link.clone());
+        Some(link)
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len() + self.spilled_fingerprints.len()
+    }
+
+    // Appends the links held in memory to the spill file, returning how many were moved out.
+    pub fn spill(&mut self, spill_path: &Path) -> std::io::Result<usize> {
+        let mut writer = BufWriter::new(OpenOptions::new().create(true).append(true).open(spill_path)?);
+
+        for link in self.links.iter() {
+            writeln!(writer, "{}", link)?;
+        }
+        writer.flush()?;
+
+        let spilled_count = self.links.len();
+        self.spilled_fingerprints.extend(self.links.drain().map(|link| fingerprint(&link)));
+        self.links.shrink_to_fit();
+        self.spill_path = Some(spill_path.to_path_buf());
+
+        Ok(spilled_count)
+    }
+
+    // Everything seen so far, including what was spilled, leaving the set as it is.
+    pub fn all(&self) -> std::io::Result<BTreeSet<String>> {
+        let mut all_links: BTreeSet<String> = self.links.iter().map(|link| link.to_string()).collect();
+
+        if let Some(spill_path) = &self.spill_path {
+            for line in BufReader::new(File::open(spill_path)?).lines() {
+                all_links.insert(line?);
+            }
+        }
+
+        Ok(all_links)
+    }
+
+}
+
+// The spill file lives as long as the set does, however the crawl ends - it's read back for every output that lists
+// the visited links.
+impl Drop for VisitedLinks {
+    fn drop(&mut self) {
+        if let Some(spill_path) = self.spill_path.take() {
+            let _ = remove_file(spill_path);
+        }
+    }
+}
+
+fn fingerprint(link: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    link.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spilled_links_are_still_visited() {
+        let spill_path = std::env::temp_dir().join(format!("visited-{}.spill", std::process::id()));
+        let mut visited_links = VisitedLinks::default();
+        visited_links.insert("https://example.com/a");
+
+        assert_eq!(1, visited_links.spill(&spill_path).unwrap());
+        assert_eq!(None, visited_links.insert("https://example.com/a"));
+        assert_eq!(Some(Arc::from("https://example.com/b")), visited_links.insert("https://example.com/b"));
+        assert_eq!(2, visited_links.len());
+        assert_eq!(2, visited_links.all().unwrap().len());
+        assert_eq!(2, visited_links.all().unwrap().len());
+
+        drop(visited_links);
+        assert!(!spill_path.exists());
+    }
+}